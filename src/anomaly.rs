@@ -0,0 +1,184 @@
+//! Baselines of measurements of the responses to each operation, to find outliers.
+//!
+//! For every operation, the last responses make up a baseline: the median of a measurement
+//! (such as the latency or the size of the response) and the median absolute deviation (MAD)
+//! from it, which outliers hardly affect. The executor compares each response to the baseline
+//! of its operation, and leaves the details of an outlier in the state, where an
+//! [`AnomalyFeedback`] objective picks them up and moves them to the metadata of the crash.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    marker::PhantomData,
+    num::NonZeroU32,
+};
+
+use libafl::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    Error, HasMetadata,
+};
+use libafl_bolts::{serdeany::SerdeAny, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::input::Method;
+
+/// Number of recent measurements of an operation that make up its baseline
+const WINDOW: usize = 100;
+/// Number of measurements of an operation needed before responses are compared to its baseline
+const MIN_SAMPLES: usize = 10;
+
+/// The median of the recent measurements of an operation, and their median absolute deviation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Baseline {
+    pub median: u64,
+    pub mad: u64,
+}
+
+impl Baseline {
+    fn of(values: &VecDeque<u64>) -> Self {
+        let mut sorted: Vec<u64> = values.iter().copied().collect();
+        sorted.sort_unstable();
+        let median = sorted[sorted.len() / 2];
+        let mut deviations: Vec<u64> = sorted.iter().map(|v| v.abs_diff(median)).collect();
+        deviations.sort_unstable();
+        Self {
+            median,
+            mad: deviations[deviations.len() / 2],
+        }
+    }
+
+    /// Whether the value is more than `multiple` times the median, and more than `multiple`
+    /// MADs above it.
+    pub fn is_exceeded(&self, value: u64, multiple: NonZeroU32) -> bool {
+        let multiple = u64::from(multiple.get());
+        value > self.median.saturating_mul(multiple)
+            && value
+                > self
+                    .median
+                    .saturating_add(self.mad.saturating_mul(multiple))
+    }
+}
+
+/// Recent measurements of the responses to each operation. They are saved with the campaign
+/// state, so a resumed campaign keeps its baselines.
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(from = "Vec<OperationValues>", into = "Vec<OperationValues>")]
+pub struct OperationBaselines {
+    /// Recent measurements of each operation, oldest first
+    values: HashMap<(Method, String), VecDeque<u64>>,
+}
+
+/// Recent measurements of a single operation, since JSON maps can not have the operation
+/// as their key
+#[derive(Clone, Serialize, Deserialize)]
+struct OperationValues {
+    method: Method,
+    path: String,
+    values: VecDeque<u64>,
+}
+
+impl From<OperationBaselines> for Vec<OperationValues> {
+    fn from(baselines: OperationBaselines) -> Self {
+        baselines
+            .values
+            .into_iter()
+            .map(|((method, path), values)| OperationValues {
+                method,
+                path,
+                values,
+            })
+            .collect()
+    }
+}
+
+impl From<Vec<OperationValues>> for OperationBaselines {
+    fn from(operations: Vec<OperationValues>) -> Self {
+        Self {
+            values: operations
+                .into_iter()
+                .map(|operation| ((operation.method, operation.path), operation.values))
+                .collect(),
+        }
+    }
+}
+
+impl OperationBaselines {
+    /// Adds a measurement of a response to an operation. Returns the baseline of the operation
+    /// before this measurement, if enough responses to it were measured.
+    pub fn observe(&mut self, method: Method, path: &str, value: u64) -> Option<Baseline> {
+        let values = self.values.entry((method, path.to_owned())).or_default();
+        let baseline = (values.len() >= MIN_SAMPLES).then(|| Baseline::of(values));
+        if values.len() == WINDOW {
+            values.pop_front();
+        }
+        values.push_back(value);
+        baseline
+    }
+}
+
+/// Objective that is interesting when the executor left metadata of type `M` about the last
+/// input in the state. Moves the metadata from the state to the crash.
+pub struct AnomalyFeedback<M> {
+    name: Cow<'static, str>,
+    phantom: PhantomData<M>,
+}
+
+impl<M> AnomalyFeedback<M> {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name: Cow::Borrowed(name),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<M> Named for AnomalyFeedback<M> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<M, S> StateInitializer<S> for AnomalyFeedback<M> {}
+
+impl<EM, I, M, OT, S> Feedback<EM, I, OT, S> for AnomalyFeedback<M>
+where
+    M: SerdeAny,
+    S: HasMetadata,
+{
+    fn is_interesting(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        Ok(state.has_metadata::<M>())
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        if let Some(metadata) = state.metadata_map_mut().remove::<M>() {
+            testcase.add_metadata(*metadata);
+        }
+        Ok(())
+    }
+}
+
+/// Leaves the metadata about the last input in the state for its [`AnomalyFeedback`], or
+/// removes the metadata about an earlier input.
+pub fn store_in_state<M: SerdeAny, S: HasMetadata>(state: &mut S, metadata: Option<M>) {
+    match metadata {
+        Some(metadata) => state.add_metadata(metadata),
+        None => {
+            let _ = state.metadata_map_mut().remove::<M>();
+        }
+    }
+}
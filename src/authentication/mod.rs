@@ -1,4 +1,4 @@
-use std::{borrow::Cow, fs::File, path::Path};
+use std::{borrow::Cow, path::Path};
 
 use anyhow::{Context, Result};
 use cookie_store::{Cookie, RawCookie};
@@ -6,7 +6,7 @@ use openapiv3::OpenAPI;
 use reqwest::header::{HeaderMap, IntoHeaderName, AUTHORIZATION};
 use url::Url;
 
-use crate::configuration::Configuration;
+use crate::{configuration::Configuration, interpolation::read_yaml_file};
 
 pub mod basic;
 pub mod bearer;
@@ -70,11 +70,8 @@ pub fn initialize() -> Result<Authentication> {
 pub fn initialize_from_config(config_path: Option<&Path>) -> Result<Authentication> {
     let auth_mode = match config_path {
         None => Mode::None,
-        Some(path) => serde_yaml::from_reader(File::open(path).with_context(|| {
-            format!("Error opening file given for --authentication, which is {path:?}")
-        })?)
-        .with_context(|| {
-            format!("Error parsing file given for --authentication, which is {path:?}")
+        Some(path) => read_yaml_file(path).with_context(|| {
+            format!("Error reading file given for --authentication, which is {path:?}")
         })?,
     };
 
@@ -139,7 +136,7 @@ impl Authentication {
     }
 
     /// Return the last Autorization header value, without refreshing it if expired.
-    pub fn last_header(&self) -> Option<Cow<str>> {
+    pub fn last_header(&self) -> Option<Cow<'_, str>> {
         match self {
             Authentication::Raw(text) => Some(Cow::from(text)),
             Authentication::Basic(config) => Some(Cow::from(format!("Basic {config}"))),
@@ -3,7 +3,7 @@ use log::Level::Info;
 use openapiv3::OpenAPI;
 use url::Url;
 
-use crate::header;
+use crate::{configuration::Configuration, header};
 
 fn send_request(
     client: reqwest::blocking::Client,
@@ -47,7 +47,7 @@ pub fn verify_auth(api: OpenAPI) -> Result<()> {
         reqwest::blocking::Client::builder().cookie_provider(std::sync::Arc::clone(&cookie_store));
 
     let mut default_headers = authentication.generate_headers();
-    default_headers.extend(header::get_default_headers()?);
+    default_headers.extend(header::get_default_headers(Configuration::must_get())?);
 
     let client = client_builder.default_headers(default_headers).build()?;
 
@@ -0,0 +1,163 @@
+//! In-process broker through which parallel fuzzing workers share their corpus.
+//!
+//! Every worker publishes the corpus entries it finds, and regularly receives the entries
+//! published by the other workers since it last asked. The received entries are evaluated
+//! by the receiving worker, so they only end up in its corpus if they are interesting there
+//! too. Entries that every worker has received are dropped, so the broker only holds the
+//! entries published since the slowest worker last asked.
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use crate::input::OpenApiInput;
+
+/// Broker shared by all workers of a fuzzing campaign.
+pub struct Broker {
+    shared: Mutex<Shared>,
+}
+
+/// The published entries that not every worker has received yet.
+struct Shared {
+    /// Published corpus entries, with the index of the worker that found them
+    testcases: VecDeque<(usize, OpenApiInput)>,
+    /// Number of entries published before the first one in `testcases`
+    dropped: usize,
+    /// For each worker, the number of published entries it has received
+    cursors: Vec<usize>,
+}
+
+impl Broker {
+    /// Creates a new broker for the given number of workers, without any corpus entries.
+    pub fn new(workers: usize) -> Self {
+        Self {
+            shared: Mutex::new(Shared {
+                testcases: VecDeque::new(),
+                dropped: 0,
+                cursors: vec![0; workers],
+            }),
+        }
+    }
+
+    /// Offers a new corpus entry found by `worker` to the other workers.
+    pub fn publish(&self, worker: usize, input: OpenApiInput) {
+        self.shared
+            .lock()
+            .unwrap()
+            .testcases
+            .push_back((worker, input));
+    }
+
+    /// Returns the entries published by other workers than `worker` since it last asked,
+    /// and drops the entries that all workers have received now.
+    pub fn receive(&self, worker: usize) -> Vec<OpenApiInput> {
+        let mut shared = self.shared.lock().unwrap();
+        let Shared {
+            testcases,
+            dropped,
+            cursors,
+        } = &mut *shared;
+        let received = testcases
+            .iter()
+            .skip(cursors[worker] - *dropped)
+            .filter(|(origin, _)| *origin != worker)
+            .map(|(_, input)| input.clone())
+            .collect();
+        cursors[worker] = *dropped + testcases.len();
+
+        let received_by_all = cursors.iter().min().copied().unwrap_or_default() - *dropped;
+        testcases.drain(..received_by_all);
+        *dropped += received_by_all;
+        received
+    }
+
+    /// Returns the number of entries that not every worker has received yet.
+    #[cfg(test)]
+    fn pending(&self) -> usize {
+        self.shared.lock().unwrap().testcases.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Broker;
+    use crate::input::OpenApiInput;
+
+    fn input(path: &str) -> OpenApiInput {
+        serde_yaml::from_str(&format!("- method: GET\n  path: {path}\n")).unwrap()
+    }
+
+    fn paths(inputs: Vec<OpenApiInput>) -> Vec<String> {
+        inputs
+            .into_iter()
+            .map(|input| input.0[0].path.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_cursors() {
+        let broker = Broker::new(3);
+        broker.publish(0, input("/a"));
+        broker.publish(1, input("/b"));
+
+        // Workers receive what the others published since they last asked
+        assert_eq!(paths(broker.receive(0)), ["/b"]);
+        assert!(broker.receive(0).is_empty());
+        broker.publish(2, input("/c"));
+        assert_eq!(paths(broker.receive(0)), ["/c"]);
+        assert_eq!(paths(broker.receive(1)), ["/a", "/c"]);
+
+        // Entries are kept until every worker received them
+        assert_eq!(broker.pending(), 3);
+        assert_eq!(paths(broker.receive(2)), ["/a", "/b"]);
+        assert_eq!(broker.pending(), 0);
+        broker.publish(1, input("/d"));
+        assert_eq!(paths(broker.receive(2)), ["/d"]);
+        assert_eq!(broker.pending(), 1);
+        assert_eq!(paths(broker.receive(0)), ["/d"]);
+        assert!(broker.receive(1).is_empty());
+        assert_eq!(broker.pending(), 0);
+    }
+
+    #[test]
+    fn test_two_workers() {
+        const ENTRIES: usize = 200;
+        let broker = Broker::new(2);
+        let received: Vec<Vec<String>> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..2)
+                .map(|worker| {
+                    let broker = &broker;
+                    scope.spawn(move || {
+                        let mut received = Vec::new();
+                        for entry in 0..ENTRIES {
+                            broker.publish(worker, input(&format!("/{worker}/{entry}")));
+                            received.extend(paths(broker.receive(worker)));
+                        }
+                        received
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        // Collect what was published after a worker last asked
+        let received: Vec<Vec<String>> = received
+            .into_iter()
+            .enumerate()
+            .map(|(worker, mut received)| {
+                received.extend(paths(broker.receive(worker)));
+                received
+            })
+            .collect();
+
+        // Each worker received every entry of the other, once and in order
+        for (worker, received) in received.iter().enumerate() {
+            let other = 1 - worker;
+            let expected: Vec<_> = (0..ENTRIES)
+                .map(|entry| format!("/{other}/{entry}"))
+                .collect();
+            assert_eq!(received, &expected);
+        }
+        assert_eq!(broker.pending(), 0);
+    }
+}
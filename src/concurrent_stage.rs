@@ -0,0 +1,462 @@
+//! A power-scheduled mutational stage that sends its mutated inputs in concurrent groups.
+//!
+//! Like LibAFL's `StdPowerMutationalStage`, the stage mutates the current testcase a number of
+//! times depending on its power score. Instead of sending the mutated inputs one at a time, it
+//! sends up to `--concurrent-sequences` of them at the same time, so a slow endpoint does not
+//! stall the others. The coverage of a group is fetched once and can not be attributed to a
+//! single input. So when a group covers new lines or endpoints, its inputs are run again one at
+//! a time and evaluated as usual. The same goes for inputs with a response that was slow or
+//! large compared to the usual responses to its operation, and for inputs that crashed: when
+//! the target goes down, every input that was in flight fails, and only running them on their
+//! own tells which one took it down.
+
+use std::{borrow::Cow, marker::PhantomData, num::NonZeroUsize};
+
+use libafl::{
+    events::{EventFirer, EventProcessor, EventRestarter},
+    executors::ExitKind,
+    fuzzer::Evaluator,
+    mutators::{MutationResult, Mutator},
+    observers::ObserversTuple,
+    schedulers::{testcase_score::CorpusPowerTestcaseScore, TestcaseScore},
+    stages::{RetryCountRestartHelper, Stage},
+    state::HasCurrentTestcase,
+    Error,
+};
+
+use crate::{
+    executor::{FuzzerState, SequenceExecutor},
+    input::OpenApiInput,
+};
+
+/// Name under which the stage keeps its progress in the state
+const STAGE_NAME: &str = "concurrent_power";
+
+/// The mutational stage using power schedules, sending mutated inputs in concurrent groups.
+pub struct ConcurrentMutationalStage<EM, M, Z> {
+    name: Cow<'static, str>,
+    mutator: M,
+    /// Maximum number of inputs sent at the same time
+    group_size: NonZeroUsize,
+    phantom: PhantomData<(EM, Z)>,
+}
+
+impl<EM, M, Z> ConcurrentMutationalStage<EM, M, Z> {
+    /// Creates a stage that sends up to `group_size` mutated inputs at the same time.
+    pub fn new(mutator: M, group_size: NonZeroUsize) -> Self {
+        Self {
+            name: Cow::Borrowed(STAGE_NAME),
+            mutator,
+            group_size,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<EM, M, Z> ConcurrentMutationalStage<EM, M, Z>
+where
+    M: Mutator<OpenApiInput, FuzzerState>,
+{
+    /// Sends the mutated inputs of a group at the same time. Inputs that may be interesting or
+    /// that crashed are run again on their own and evaluated as usual.
+    fn evaluate_group<'h, OT>(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut SequenceExecutor<'h, OT>,
+        state: &mut FuzzerState,
+        manager: &mut EM,
+        group: Vec<OpenApiInput>,
+    ) -> Result<(), Error>
+    where
+        EM: EventFirer<OpenApiInput, FuzzerState>
+            + EventRestarter<FuzzerState>
+            + EventProcessor<EM, FuzzerState, Z>,
+        OT: ObserversTuple<OpenApiInput, FuzzerState>,
+        Z: Evaluator<SequenceExecutor<'h, OT>, EM, OpenApiInput, FuzzerState>,
+    {
+        if group.len() <= 1 {
+            // Nothing to send concurrently
+            for mutated in group {
+                let (_, corpus_id) = fuzzer.evaluate_filtered(state, executor, manager, mutated)?;
+                self.mutator.post_exec(state, corpus_id)?;
+            }
+            return Ok(());
+        }
+
+        let (members, new_coverage) = executor.run_group(state, manager, &group)?;
+        for (mutated, member) in group.into_iter().zip(members) {
+            // Latencies are not reliable while other requests are in flight
+            let corpus_id = if new_coverage
+                || member.findings.is_anomalous()
+                || member.exit_kind == ExitKind::Crash
+            {
+                fuzzer
+                    .evaluate_filtered(state, executor, manager, mutated)?
+                    .1
+            } else {
+                None
+            };
+            self.mutator.post_exec(state, corpus_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'h, EM, M, OT, Z> Stage<SequenceExecutor<'h, OT>, EM, FuzzerState, Z>
+    for ConcurrentMutationalStage<EM, M, Z>
+where
+    EM: EventFirer<OpenApiInput, FuzzerState>
+        + EventRestarter<FuzzerState>
+        + EventProcessor<EM, FuzzerState, Z>,
+    M: Mutator<OpenApiInput, FuzzerState>,
+    OT: ObserversTuple<OpenApiInput, FuzzerState>,
+    Z: Evaluator<SequenceExecutor<'h, OT>, EM, OpenApiInput, FuzzerState>,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut SequenceExecutor<'h, OT>,
+        state: &mut FuzzerState,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let iterations = {
+            let mut testcase = state.current_testcase_mut()?;
+            CorpusPowerTestcaseScore::compute(state, &mut testcase)? as usize
+        };
+        let Ok(input) = state.current_input_cloned() else {
+            return Ok(());
+        };
+
+        let mut remaining = iterations;
+        while remaining > 0 {
+            let group_size = remaining.min(self.group_size.get());
+            remaining -= group_size;
+            let mut group = Vec::with_capacity(group_size);
+            for _ in 0..group_size {
+                let mut mutated = input.clone();
+                if self.mutator.mutate(state, &mut mutated)? != MutationResult::Skipped {
+                    group.push(mutated);
+                }
+            }
+
+            self.evaluate_group(fuzzer, executor, state, manager, group)?;
+        }
+        Ok(())
+    }
+
+    fn should_restart(&mut self, state: &mut FuzzerState) -> Result<bool, Error> {
+        // Make sure we don't get stuck crashing on a single testcase
+        RetryCountRestartHelper::should_restart(state, &self.name, 3)
+    }
+
+    fn clear_progress(&mut self, state: &mut FuzzerState) -> Result<(), Error> {
+        RetryCountRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        borrow::Cow,
+        collections::HashSet,
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        num::{NonZeroU32, NonZeroUsize},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex, OnceLock, PoisonError,
+        },
+        time::{Duration, Instant},
+    };
+
+    use libafl::{
+        corpus::{Corpus, InMemoryOnDiskCorpus, OnDiskCorpus},
+        events::NopEventManager,
+        executors::ExitKind,
+        feedback_and_fast, feedback_not,
+        feedbacks::{CrashFeedback, Feedback, StateInitializer},
+        fuzzer::StdFuzzer,
+        mutators::{MutationResult, NopMutator},
+        schedulers::QueueScheduler,
+        state::{HasCorpus, HasExecutions, HasSolutions},
+        Error,
+    };
+    use libafl_bolts::{rands::StdRand, Named};
+    use openapiv3::OpenAPI;
+
+    use super::ConcurrentMutationalStage;
+    use crate::{
+        configuration::{Configuration, CrashCriterion, PartialConfiguration},
+        coverage_clients::{dummy::DummyCoverageClient, endpoint::EndpointCoverageClient},
+        crash_buckets::{CrashBuckets, BUCKETS_FILE},
+        executor::{FuzzerState, SequenceExecutor},
+        input::OpenApiInput,
+        openapi::filter::OperationFilter,
+        output::OutputPaths,
+        state::OpenApiFuzzerState,
+        target::Target,
+    };
+
+    /// Number of requests to `/grow` that get a small response, later ones get a large one
+    const SMALL_RESPONSES: usize = 10;
+    /// How long the server is down after a request to `/down`
+    const DOWNTIME: Duration = Duration::from_millis(300);
+    /// How long the server takes to answer a request to `/inflight`
+    const INFLIGHT_DELAY: Duration = Duration::from_millis(100);
+
+    /// Serves `/crash` with a server error, `/grow` with a response that becomes large after
+    /// a while, `/inflight` after a delay, and everything else with an empty object. A request
+    /// to `/down` makes the server close all connections without a response for a while.
+    /// Returns the specification of the server, which is set up as the target once for all
+    /// tests.
+    fn target_api() -> OpenAPI {
+        static PORT: OnceLock<u16> = OnceLock::new();
+        let port = *PORT.get_or_init(|| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let grown = Arc::new(AtomicUsize::new(0));
+            let down_until = Arc::new(Mutex::new(Instant::now()));
+            std::thread::spawn(move || {
+                for mut stream in listener.incoming().map(Result::unwrap) {
+                    let grown = Arc::clone(&grown);
+                    let down_until = Arc::clone(&down_until);
+                    std::thread::spawn(move || {
+                        let mut reader = BufReader::new(&stream);
+                        let mut request_line = String::new();
+                        reader.read_line(&mut request_line).unwrap();
+                        // Skip the headers; the requests have no body
+                        let mut header = String::new();
+                        while reader.read_line(&mut header).unwrap() > 2 {
+                            header.clear();
+                        }
+                        let path = request_line.split([' ', '?']).nth(1);
+                        if path == Some("/inflight") {
+                            std::thread::sleep(INFLIGHT_DELAY);
+                        }
+                        if Instant::now() < *down_until.lock().unwrap() {
+                            return;
+                        }
+                        let (status, body) = match path {
+                            Some("/down") => {
+                                *down_until.lock().unwrap() = Instant::now() + DOWNTIME;
+                                return;
+                            }
+                            Some("/crash") => ("500 Internal Server Error", "{}".to_owned()),
+                            Some("/grow")
+                                if grown.fetch_add(1, Ordering::SeqCst) >= SMALL_RESPONSES =>
+                            {
+                                ("200 OK", format!("\"{}\"", "x".repeat(10_000)))
+                            }
+                            _ => ("200 OK", "{}".to_owned()),
+                        };
+                        write!(
+                            stream,
+                            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\n\
+                             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                            body.len()
+                        )
+                        .unwrap();
+                    });
+                }
+            });
+            port
+        });
+        let api: OpenAPI = serde_yaml::from_str(&format!(
+            "
+openapi: 3.0.0
+info:
+  title: test
+  version: '1'
+servers:
+- url: http://127.0.0.1:{port}
+paths:
+{}",
+            ["/a", "/b", "/crash", "/grow", "/down", "/inflight"]
+                .map(|path| format!(
+                    "
+  {path}:
+    get:
+      responses:
+        '200':
+          description: OK
+"
+                ))
+                .concat()
+        ))
+        .unwrap();
+        // Without a target command, there is nothing to stop when the returned value is dropped
+        static TARGET: OnceLock<()> = OnceLock::new();
+        TARGET.get_or_init(|| drop(Target::start(&config(None), &api).unwrap()));
+        api
+    }
+
+    fn config(response_size_multiple: Option<NonZeroU32>) -> Configuration {
+        PartialConfiguration {
+            openapi_spec: Some("openapi.yaml".into()),
+            // The responses are not validated against the specification
+            crash_criterion: Some(CrashCriterion::Only5xx),
+            response_size_multiple,
+            seed: Some(7),
+            health_check: Some("/health".to_owned()),
+            ..Default::default()
+        }
+        .try_into()
+        .unwrap()
+    }
+
+    /// Feedback that is interesting for inputs to a path that was not sent before, which
+    /// stands in for the endpoint coverage.
+    #[derive(Default)]
+    struct NewPathFeedback(HashSet<String>);
+
+    impl Named for NewPathFeedback {
+        fn name(&self) -> &Cow<'static, str> {
+            &Cow::Borrowed("NewPathFeedback")
+        }
+    }
+
+    impl<S> StateInitializer<S> for NewPathFeedback {}
+
+    impl<EM, OT, S> Feedback<EM, OpenApiInput, OT, S> for NewPathFeedback {
+        fn is_interesting(
+            &mut self,
+            _state: &mut S,
+            _manager: &mut EM,
+            input: &OpenApiInput,
+            _observers: &OT,
+            _exit_kind: &ExitKind,
+        ) -> Result<bool, Error> {
+            Ok(self.0.insert(input.0[0].path.clone()))
+        }
+    }
+
+    /// The sorted paths of the inputs in a corpus.
+    fn paths(corpus: &impl Corpus<OpenApiInput>) -> Vec<String> {
+        let mut paths: Vec<String> = corpus
+            .ids()
+            .map(|id| {
+                let mut testcase = corpus.get(id).unwrap().borrow_mut();
+                corpus.load_input_into(&mut testcase).unwrap();
+                testcase.input().as_ref().unwrap().0[0].path.clone()
+            })
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// Evaluates rounds of inputs with the stage, in groups of up to `group_size`. Returns
+    /// the paths of the corpus entries and of the crashes, and the number of executions after
+    /// each round.
+    fn evaluate_rounds(
+        rounds: &[&[&str]],
+        group_size: usize,
+        config: &Configuration,
+    ) -> (Vec<String>, Vec<String>, Vec<u64>) {
+        // The server is shared, and taking it down must not affect the other tests
+        static SERVER: Mutex<()> = Mutex::new(());
+        let _server = SERVER.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut api = target_api();
+        let filter = OperationFilter::apply(&mut api, &[], &[]).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        // Crashes do not belong in the corpus
+        let mut feedback = feedback_and_fast!(
+            feedback_not!(CrashFeedback::new()),
+            NewPathFeedback::default()
+        );
+        let mut objective = CrashFeedback::new();
+        let mut state: FuzzerState = OpenApiFuzzerState::new(
+            StdRand::with_seed(0),
+            InMemoryOnDiskCorpus::new(dir.path().join("queue")).unwrap(),
+            OnDiskCorpus::new(dir.path().join("crashes")).unwrap(),
+            &mut feedback,
+            &mut objective,
+            api.clone(),
+        )
+        .unwrap();
+        let mut executor = SequenceExecutor::new(
+            (),
+            &api,
+            &filter,
+            config,
+            Box::new(DummyCoverageClient::new()),
+            Arc::new(Mutex::new(EndpointCoverageClient::new(&api))),
+            Arc::new(Mutex::new(
+                CrashBuckets::load(&dir.path().join(BUCKETS_FILE), NonZeroUsize::MIN).unwrap(),
+            )),
+            &OutputPaths::new(config).unwrap(),
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut manager = NopEventManager::new();
+        let mut stage = ConcurrentMutationalStage::new(
+            NopMutator::new(MutationResult::Mutated),
+            NonZeroUsize::new(group_size).unwrap(),
+        );
+
+        let mut executions = Vec::new();
+        for round in rounds {
+            let inputs: Vec<OpenApiInput> = round
+                .iter()
+                .map(|path| serde_yaml::from_str(&format!("- method: GET\n  path: {path}\n")))
+                .collect::<Result<_, _>>()
+                .unwrap();
+            for group in inputs.chunks(group_size) {
+                stage
+                    .evaluate_group(
+                        &mut fuzzer,
+                        &mut executor,
+                        &mut state,
+                        &mut manager,
+                        group.to_vec(),
+                    )
+                    .unwrap();
+            }
+            executions.push(*state.executions());
+        }
+        (paths(state.corpus()), paths(state.solutions()), executions)
+    }
+
+    #[test]
+    fn test_group_coverage() {
+        let config = config(None);
+        let rounds: &[&[&str]] = &[&["/a", "/b", "/crash"], &["/a", "/b", "/crash"]];
+        let (corpus, crashes, executions) = evaluate_rounds(rounds, 3, &config);
+        // The first group covers new endpoints, so its inputs are run again one by one. The
+        // second does not, and only its crash is run again and saved.
+        assert_eq!(executions, [6, 10]);
+        assert_eq!(corpus, ["/a", "/b"]);
+        assert_eq!(crashes, ["/crash", "/crash"]);
+
+        // Which gives the same corpus and crashes as sending the inputs one by one
+        let (sequential_corpus, sequential_crashes, sequential_executions) =
+            evaluate_rounds(rounds, 1, &config);
+        assert_eq!(sequential_executions, [3, 6]);
+        assert_eq!(corpus, sequential_corpus);
+        assert_eq!(crashes, sequential_crashes);
+    }
+
+    #[test]
+    fn test_group_anomaly() {
+        let config = config(NonZeroU32::new(10));
+        // The first group covers new endpoints, after which the responses to `/grow` make up
+        // a baseline of small responses
+        let warm_up = vec!["/grow"; SMALL_RESPONSES - 2];
+        let rounds: &[&[&str]] = &[&["/a", "/grow"], &warm_up, &["/grow", "/a"]];
+        let (_, crashes, executions) = evaluate_rounds(rounds, 2, &config);
+        // Only the input with the large response is run again on its own
+        assert_eq!(executions, [4, 12, 15]);
+        assert!(crashes.is_empty());
+    }
+
+    #[test]
+    fn test_group_target_down() {
+        let config = config(None);
+        // The inputs to `/inflight` are still waiting for a response when `/down` takes the
+        // server down, so they fail as well
+        let rounds: &[&[&str]] = &[&["/down", "/inflight", "/inflight"]];
+        let (_, crashes, executions) = evaluate_rounds(rounds, 3, &config);
+        // Running them again one by one shows which input to blame
+        assert_eq!(executions, [6]);
+        assert_eq!(crashes, ["/down"]);
+    }
+}
@@ -3,15 +3,26 @@ use std::{
     io,
     io::ErrorKind,
     net::{SocketAddr, ToSocketAddrs},
+    num::{NonZeroU32, NonZeroU64, NonZeroUsize},
     path::{Path, PathBuf},
 };
 
+use anyhow::Context;
 use clap::{value_parser, Parser, Subcommand, ValueEnum};
 use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    crash_rules::CrashRule, interpolation::read_yaml_file, openapi::filter::OperationSelector,
+    reset::ResetHook,
+};
 
 const DEFAULT_REQUEST_TIMEOUT: u64 = 30000;
 const DEFAULT_METHOD_MUTATION_STRATEGY: MethodMutationStrategy = MethodMutationStrategy::FollowSpec;
 const DEFAULT_LOG_LEVEL: log::LevelFilter = log::LevelFilter::Info;
+const DEFAULT_WORKERS: NonZeroUsize = NonZeroUsize::MIN;
+const DEFAULT_MAX_TRANSPORT_FAILURES: NonZeroU32 = NonZeroU32::new(3).unwrap();
+const DEFAULT_MAX_CRASHES_PER_BUCKET: NonZeroUsize = NonZeroUsize::new(3).unwrap();
 
 lazy_static! {
     static ref CONFIGURATION: Result<Configuration, anyhow::Error> =
@@ -28,6 +39,7 @@ pub struct Cli {
 
 /// The list of supported subcommands.
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)] // parsed once, boxing the fields would only hurt readability
 pub enum Commands {
     /// Print the version and exit
     Version,
@@ -42,6 +54,10 @@ pub enum Commands {
         /// over the configuration file.
         #[arg(long, value_parser, value_name = "CONFIG_FILE.YAML")]
         config: Option<PathBuf>,
+        /// The name of a profile in the configuration file. The settings in the profile
+        /// take precedence over the rest of the configuration file.
+        #[arg(long, value_parser, requires = "config")]
+        profile: Option<String>,
         /// OpenAPI specification
         #[arg(long, value_parser, value_name = "OPENAPI_SPEC.YAML")]
         openapi_spec: Option<PathBuf>,
@@ -55,6 +71,12 @@ pub enum Commands {
         /// passed through an API specification.
         #[arg(long, value_parser, value_name = "STATIC_HEADERS.YAML")]
         header: Option<PathBuf>,
+        /// Base URL to send requests to, instead of the servers in the specification.
+        #[arg(long, value_parser, value_name = "URL")]
+        target_url: Option<Url>,
+        /// Index of the server in the specification to send requests to. Defaults to 0.
+        #[arg(long, value_parser)]
+        server_index: Option<usize>,
         // Manually added possible values below, since automatically showing possible values of an external (remote) enum
         // such as log::LevelFilter is not well supported.
         // See https://github.com/serde-rs/serde/issues/1301, https://github.com/serde-rs/serde/issues/723
@@ -62,6 +84,45 @@ pub enum Commands {
         #[arg(value_parser = clap::value_parser!(log::LevelFilter), long, value_enum, env = "LOG_LEVEL", ignore_case = true)]
         log_level: Option<log::LevelFilter>,
     },
+    /// Check the configuration and all files it refers to, and report every problem found
+    ValidateConfig {
+        /// The path to a configuration file. If present, the configuration file is used
+        /// to configure the fuzzer. Arguments given on the command line take precedence
+        /// over the configuration file.
+        #[arg(long, value_parser, value_name = "CONFIG_FILE.YAML")]
+        config: Option<PathBuf>,
+        /// The name of a profile in the configuration file. The settings in the profile
+        /// take precedence over the rest of the configuration file.
+        #[arg(long, value_parser, requires = "config")]
+        profile: Option<String>,
+        /// OpenAPI specification
+        #[arg(long, value_parser, value_name = "OPENAPI_SPEC.YAML")]
+        openapi_spec: Option<PathBuf>,
+        /// The path to an initial corpus given as a directory with yaml files.
+        #[arg(short, long, value_name = "CORPUS_DIRECTORY")]
+        initial_corpus: Option<PathBuf>,
+        /// The host address of the coverage agent from which the coverage map can be obtained.
+        /// Can be either a hostname or an IP address, and must include a port.
+        #[arg(value_parser=parse_socket_addr, long)]
+        coverage_host: Option<SocketAddr>,
+        /// The format in which your instrumentation provides coverage information.
+        #[arg(value_parser, long, value_enum, ignore_case = true)]
+        coverage_format: Option<CoverageFormat>,
+        /// How to log in to the API server. The value should be the name of a YAML file
+        /// that contains the login configuration. See login.md for information on how
+        /// to build one.
+        #[arg(long, value_parser, value_name = "AUTH.YAML")]
+        authentication: Option<PathBuf>,
+        /// Custom (static) headers that should be added to each request.
+        #[arg(long, value_parser, value_name = "STATIC_HEADERS.YAML")]
+        header: Option<PathBuf>,
+        /// Base URL to send requests to, instead of the servers in the specification.
+        #[arg(long, value_parser, value_name = "URL")]
+        target_url: Option<Url>,
+        /// Index of the server in the specification to send requests to. Defaults to 0.
+        #[arg(long, value_parser)]
+        server_index: Option<usize>,
+    },
     /// Generate a starting corpus and write it to a directory, then exit
     OutputCorpus {
         /// A directory to output the corpus to
@@ -74,6 +135,11 @@ pub enum Commands {
         /// inferred relationships between the endpoints and their parameters
         #[arg(long, value_parser, value_name = "REPORTS/")]
         report_path: Option<PathBuf>,
+        /// Seed for sampling example values. For a given seed and specification, the
+        /// generated corpus is always the same. If omitted, a seed is derived from the
+        /// current time and logged.
+        #[arg(long, value_name = "SEED")]
+        seed: Option<u64>,
     },
     /// Reproduce a crash file generated during an earlier fuzzing run
     Reproduce {
@@ -82,6 +148,10 @@ pub enum Commands {
         /// over the configuration file.
         #[arg(long, value_parser, value_name = "CONFIG_FILE.YAML")]
         config: Option<PathBuf>,
+        /// The name of a profile in the configuration file. The settings in the profile
+        /// take precedence over the rest of the configuration file.
+        #[arg(long, value_parser, requires = "config")]
+        profile: Option<String>,
         /// The crash file to reproduce
         #[arg(value_name = "CRASH_FILE")]
         crash_file: PathBuf,
@@ -98,6 +168,16 @@ pub enum Commands {
         /// passed through an API specification.
         #[arg(long, value_parser, value_name = "STATIC_HEADERS.YAML")]
         header: Option<PathBuf>,
+        /// Base URL to send requests to, instead of the servers in the specification.
+        #[arg(long, value_parser, value_name = "URL")]
+        target_url: Option<Url>,
+        /// Index of the server in the specification to send requests to. Defaults to 0.
+        #[arg(long, value_parser)]
+        server_index: Option<usize>,
+        /// Hooks that reset the state of the target before the crash is replayed, as for
+        /// fuzz. Can be given more than once.
+        #[arg(long = "reset-hook", value_parser, value_name = "HOOK")]
+        reset_hooks: Option<Vec<ResetHook>>,
         // Manually added possible values below, since automatically showing possible values of an external (remote) enum
         // such as log::LevelFilter is not well supported.
         // See https://github.com/serde-rs/serde/issues/1301, https://github.com/serde-rs/serde/issues/723
@@ -105,6 +185,52 @@ pub enum Commands {
         #[arg(value_parser = clap::value_parser!(log::LevelFilter), long, value_enum, env = "LOG_LEVEL", ignore_case = true)]
         log_level: Option<log::LevelFilter>,
     },
+    /// List the crash buckets of an earlier fuzzing run, with their number of crashes
+    Triage {
+        /// The crash_buckets.json file, or the directory that contains it (such as the
+        /// run directory, or <OUTPUT_DIR>/latest)
+        #[arg(value_name = "CRASH_BUCKETS", default_value = ".")]
+        crash_buckets: PathBuf,
+    },
+    /// Convert a HAR file of a recorded session into a request sequence in a corpus directory
+    ImportHar {
+        /// The HAR file to import
+        #[arg(value_name = "HAR_FILE")]
+        har_file: PathBuf,
+        /// A directory to save the request sequence to
+        #[arg(value_name = "CORPUS_DIRECTORY")]
+        corpus_directory: PathBuf,
+        /// OpenAPI specification to match the requests to
+        #[arg(long, value_parser, value_name = "OPENAPI_SPEC.YAML")]
+        openapi_spec: PathBuf,
+    },
+    /// Export a crash file or corpus entry as a HAR file, with the transcript saved with it
+    ExportHar {
+        /// The path to a configuration file. If present, the configuration file is used
+        /// to configure the fuzzer. Arguments given on the command line take precedence
+        /// over the configuration file.
+        #[arg(long, value_parser, value_name = "CONFIG_FILE.YAML")]
+        config: Option<PathBuf>,
+        /// The name of a profile in the configuration file. The settings in the profile
+        /// take precedence over the rest of the configuration file.
+        #[arg(long, value_parser, requires = "config")]
+        profile: Option<String>,
+        /// The crash file or corpus entry to export
+        #[arg(value_name = "INPUT_FILE")]
+        input_file: PathBuf,
+        /// The HAR file to write
+        #[arg(value_name = "HAR_FILE")]
+        har_file: PathBuf,
+        /// The OpenAPI specification of the program under test
+        #[arg(long, value_name = "OPENAPI_SPEC.YAML")]
+        openapi_spec: Option<PathBuf>,
+        /// Base URL to send requests to, instead of the servers in the specification.
+        #[arg(long, value_parser, value_name = "URL")]
+        target_url: Option<Url>,
+        /// Index of the server in the specification to send requests to. Defaults to 0.
+        #[arg(long, value_parser)]
+        server_index: Option<usize>,
+    },
     /// Fuzz test an OpenAPI backend
     Fuzz {
         /// The path to a configuration file. If present, the configuration file is used
@@ -113,6 +239,11 @@ pub enum Commands {
         #[arg(long, value_parser, value_name = "CONFIG_FILE.YAML")]
         config: Option<PathBuf>,
 
+        /// The name of a profile in the configuration file. The settings in the profile
+        /// take precedence over the rest of the configuration file.
+        #[arg(long, value_parser, requires = "config")]
+        profile: Option<String>,
+
         /// The path to the open api specification of the target. The specification must
         /// also contain the "server"-field at which the target is hosted.
         #[arg(value_parser, value_name = "OPENAPI_SPEC.YAML")]
@@ -201,6 +332,126 @@ pub enum Commands {
         /// If no coverage is obtained anymore please check if the prefix is correct. If you use the trace debug level all skipped segment names are logged.
         #[arg(value_parser, long)]
         jacoco_class_prefix: Option<String>,
+
+        /// Directory in which the fuzzer state is saved when the campaign ends (also in an
+        /// error) and every five minutes while it runs. If the directory already contains a
+        /// saved state, the campaign continues from that state instead of starting from scratch.
+        #[arg(long, value_parser, value_name = "RESUME_DIRECTORY")]
+        resume: Option<PathBuf>,
+
+        /// Number of workers that fuzz the target in parallel. Each worker has its own HTTP
+        /// client and cookie store; new corpus entries are shared between the workers.
+        /// Code coverage is attributed to the input of whichever worker fetches it first.
+        /// Defaults to 1.
+        #[arg(value_parser, long)]
+        workers: Option<NonZeroUsize>,
+
+        /// Seed for the random number generators of the fuzzer. For a given seed and target,
+        /// corpus generation and mutations are deterministic, so a run can be replayed exactly.
+        /// If omitted, a seed is derived from the current time. The seed is logged and
+        /// recorded in the report database.
+        #[arg(value_parser, long)]
+        seed: Option<u64>,
+
+        /// Directory in which all output of the fuzzer is stored. Every run gets its own
+        /// subdirectory `runs/<timestamp>`, and `latest` links to the most recent run.
+        /// If omitted, the output is written to `queue`, `crashes` and `reports` in the working
+        /// directory.
+        #[arg(long, value_parser, value_name = "OUTPUT_DIRECTORY")]
+        output_dir: Option<PathBuf>,
+
+        /// Base URL to send requests to, instead of the servers in the specification.
+        /// For example `http://localhost:8080/api/v1`.
+        #[arg(long, value_parser, value_name = "URL")]
+        target_url: Option<Url>,
+
+        /// Index of the server in the specification to send requests to. Variables in its
+        /// URL are replaced by their default values. Defaults to 0, the first server.
+        #[arg(long, value_parser)]
+        server_index: Option<usize>,
+
+        /// Only fuzz the operations matching one of these selectors, which have the form
+        /// path:<GLOB>, method:<METHOD>, operation-id:<ID> or tag:<TAG>. Can be given
+        /// more than once.
+        #[arg(long, value_parser, value_name = "SELECTOR")]
+        include_operations: Option<Vec<OperationSelector>>,
+
+        /// Never fuzz the operations matching one of these selectors, which have the same
+        /// form as for --include-operations. Can be given more than once.
+        #[arg(long, value_parser, value_name = "SELECTOR")]
+        exclude_operations: Option<Vec<OperationSelector>>,
+
+        /// Maximum number of requests per second sent to the target, by all workers together.
+        /// Unlimited if omitted.
+        #[arg(long, value_parser, value_name = "REQUESTS_PER_SECOND")]
+        rate_limit: Option<NonZeroU32>,
+
+        /// Maximum number of requests that the workers have in flight at the same time.
+        /// Unlimited if omitted.
+        #[arg(long, value_parser)]
+        max_concurrent_requests: Option<NonZeroUsize>,
+
+        /// Endpoint that tells whether the target is up, as a path relative to the target URL
+        /// (such as /health) or as a full URL. It is up if a GET request does not result in a
+        /// server error. Without it, the target is up if it accepts connections.
+        #[arg(long, value_parser, value_name = "PATH_OR_URL")]
+        health_check: Option<String>,
+
+        /// Shell command that starts the target, run before fuzzing and again whenever the target
+        /// stops responding. A command that keeps running (use `exec` to replace the shell) is
+        /// stopped before a restart and when the campaign ends.
+        #[arg(long, value_parser, value_name = "COMMAND")]
+        target_command: Option<String>,
+
+        /// Number of consecutive requests without a response after which the target is considered
+        /// down, even if its health check passes. Defaults to 3.
+        #[arg(long, value_parser)]
+        max_transport_failures: Option<NonZeroU32>,
+
+        /// Hooks that reset the state of the target before a request sequence, which have the
+        /// form http:<METHOD> <URL>, command:<COMMAND> or sql:<DATABASE> < <SCRIPT>. Can be given
+        /// more than once; the hooks run in that order. Can not be combined with more than one
+        /// worker or with concurrent sequences, since a reset changes the state under the
+        /// sequences that are sent at the same time.
+        #[arg(long = "reset-hook", value_parser, value_name = "HOOK")]
+        reset_hooks: Option<Vec<ResetHook>>,
+
+        /// Run the reset hooks before every this many request sequences, instead of before
+        /// every sequence.
+        #[arg(long, value_parser, value_name = "SEQUENCES")]
+        reset_interval: Option<NonZeroUsize>,
+
+        /// Requests without a response that are saved as findings: requests that time out
+        /// (timeout) or connections that the target drops without responding (reset). They are
+        /// saved to the timeouts and resets directories, next to the crashes.
+        #[arg(long, value_enum, value_delimiter = ',', ignore_case = true)]
+        transport_objectives: Option<Vec<TransportObjective>>,
+
+        /// Number of mutated request sequences that each worker sends concurrently. Sequences
+        /// are sent one at a time if omitted.
+        #[arg(long, value_parser, value_name = "SEQUENCES")]
+        concurrent_sequences: Option<NonZeroUsize>,
+
+        /// Report a request as a crash when it takes more than this many times the median
+        /// latency of its operation (and more than this many median absolute deviations above it).
+        #[arg(long, value_parser, value_name = "MULTIPLE")]
+        latency_multiple: Option<NonZeroU32>,
+
+        /// Report a request as a crash when it takes longer than this many milliseconds.
+        #[arg(long, value_parser, value_name = "MILLISECONDS")]
+        latency_threshold: Option<NonZeroU64>,
+
+        /// Report a response as a crash when it is more than this many times the median size of
+        /// the responses to its operation (and more than this many median absolute deviations
+        /// above it).
+        #[arg(long, value_parser, value_name = "MULTIPLE")]
+        response_size_multiple: Option<NonZeroU32>,
+
+        /// Number of crashes with the same error signature (operation, status, kind of error and
+        /// stack frames or normalized response body) that are saved. Later crashes with that signature
+        /// only count as hits of its bucket. Defaults to 3.
+        #[arg(long, value_parser, value_name = "N")]
+        max_crashes_per_bucket: Option<NonZeroUsize>,
     },
 }
 
@@ -208,12 +459,25 @@ impl Commands {
     fn config_filename(&self) -> Option<&PathBuf> {
         match self {
             Commands::VerifyAuth { config, .. }
+            | Commands::ValidateConfig { config, .. }
             | Commands::Reproduce { config, .. }
+            | Commands::ExportHar { config, .. }
             | Commands::Fuzz { config, .. } => config.as_ref(),
             _ => None,
         }
     }
 
+    fn profile(&self) -> Option<&str> {
+        match self {
+            Commands::VerifyAuth { profile, .. }
+            | Commands::ValidateConfig { profile, .. }
+            | Commands::Reproduce { profile, .. }
+            | Commands::ExportHar { profile, .. }
+            | Commands::Fuzz { profile, .. } => profile.as_deref(),
+            _ => None,
+        }
+    }
+
     fn fuzzer_config(self) -> Result<PartialConfiguration, anyhow::Error> {
         match self {
             Commands::VerifyAuth {
@@ -221,12 +485,37 @@ impl Commands {
                 authentication,
                 header,
                 log_level,
+                target_url,
+                server_index,
                 ..
             } => Ok(PartialConfiguration {
                 openapi_spec,
                 authentication,
                 header,
                 log_level,
+                target_url,
+                server_index,
+                ..Default::default()
+            }),
+            Commands::ValidateConfig {
+                openapi_spec,
+                initial_corpus,
+                coverage_host,
+                coverage_format,
+                authentication,
+                header,
+                target_url,
+                server_index,
+                ..
+            } => Ok(PartialConfiguration {
+                openapi_spec,
+                initial_corpus,
+                coverage_host,
+                coverage_format,
+                authentication,
+                header,
+                target_url,
+                server_index,
                 ..Default::default()
             }),
             Commands::Reproduce {
@@ -234,12 +523,29 @@ impl Commands {
                 authentication,
                 header,
                 log_level,
+                target_url,
+                server_index,
+                reset_hooks,
                 ..
             } => Ok(PartialConfiguration {
                 openapi_spec,
                 authentication,
                 header,
                 log_level,
+                target_url,
+                server_index,
+                reset_hooks,
+                ..Default::default()
+            }),
+            Commands::ExportHar {
+                openapi_spec,
+                target_url,
+                server_index,
+                ..
+            } => Ok(PartialConfiguration {
+                openapi_spec,
+                target_url,
+                server_index,
                 ..Default::default()
             }),
             Commands::Fuzz {
@@ -259,6 +565,27 @@ impl Commands {
                 header,
                 log_level,
                 jacoco_class_prefix,
+                resume,
+                workers,
+                seed,
+                output_dir,
+                target_url,
+                server_index,
+                include_operations,
+                exclude_operations,
+                rate_limit,
+                max_concurrent_requests,
+                health_check,
+                target_command,
+                max_transport_failures,
+                reset_hooks,
+                reset_interval,
+                transport_objectives,
+                concurrent_sequences,
+                latency_multiple,
+                latency_threshold,
+                response_size_multiple,
+                max_crashes_per_bucket,
                 ..
             } => Ok(PartialConfiguration {
                 openapi_spec,
@@ -277,6 +604,28 @@ impl Commands {
                 header,
                 log_level,
                 jacoco_class_prefix,
+                resume,
+                workers,
+                seed,
+                output_dir,
+                target_url,
+                server_index,
+                include_operations,
+                exclude_operations,
+                rate_limit,
+                max_concurrent_requests,
+                health_check,
+                target_command,
+                max_transport_failures,
+                reset_hooks,
+                reset_interval,
+                transport_objectives,
+                concurrent_sequences,
+                latency_multiple,
+                latency_threshold,
+                response_size_multiple,
+                crash_rules: None,
+                max_crashes_per_bucket,
             }),
             _ => Err(anyhow!(
                 "Tried to generate fuzzer configuration from a non-fuzz command line"
@@ -295,7 +644,7 @@ impl Commands {
 /// using TryFrom can fail.
 ///
 #[derive(Debug, Default, PartialEq, Eq, Deserialize, Parser)]
-struct PartialConfiguration {
+pub(crate) struct PartialConfiguration {
     /// The path to the open api specification of the target. The specification must
     /// also contain the "server"-field at which the target is hosted.
     #[clap(value_parser, value_name = "OPENAPI_SPEC.YAML")]
@@ -384,6 +733,132 @@ struct PartialConfiguration {
     /// If no coverage is obtained anymore please check if the prefix is correct. If you use the trace debug level all skipped segment names are logged.
     #[clap(value_parser, long)]
     pub jacoco_class_prefix: Option<String>,
+
+    /// Directory in which the fuzzer state is saved when the campaign ends (also in an
+    /// error) and every five minutes while it runs. If the directory already contains a
+    /// saved state, the campaign continues from that state instead of starting from scratch.
+    #[clap(long, value_parser, value_name = "RESUME_DIRECTORY")]
+    pub resume: Option<PathBuf>,
+
+    /// Number of workers that fuzz the target in parallel. Each worker has its own HTTP
+    /// client and cookie store; new corpus entries are shared between the workers.
+    /// Code coverage is attributed to the input of whichever worker fetches it first.
+    /// Defaults to 1.
+    #[clap(value_parser, long)]
+    pub workers: Option<NonZeroUsize>,
+
+    /// Seed for the random number generators of the fuzzer. For a given seed and target,
+    /// corpus generation and mutations are deterministic, so a run can be replayed exactly.
+    /// If omitted, a seed is derived from the current time. The seed is logged and
+    /// recorded in the report database.
+    #[clap(value_parser, long)]
+    pub seed: Option<u64>,
+
+    /// Directory in which all output of the fuzzer is stored. Every run gets its own
+    /// subdirectory `runs/<timestamp>`, and `latest` links to the most recent run.
+    /// If omitted, the output is written to `queue`, `crashes` and `reports` in the working
+    /// directory.
+    #[clap(long, value_parser, value_name = "OUTPUT_DIRECTORY")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Base URL to send requests to, instead of the servers in the specification.
+    /// For example `http://localhost:8080/api/v1`.
+    #[clap(long, value_parser, value_name = "URL")]
+    pub target_url: Option<Url>,
+
+    /// Index of the server in the specification to send requests to. Variables in its
+    /// URL are replaced by their default values. Defaults to 0, the first server.
+    #[clap(long, value_parser)]
+    pub server_index: Option<usize>,
+
+    /// Only fuzz the operations matching one of these selectors, which have the form
+    /// path:<GLOB>, method:<METHOD>, operation-id:<ID> or tag:<TAG>. Can be given
+    /// more than once.
+    #[clap(long, value_parser, value_name = "SELECTOR")]
+    pub include_operations: Option<Vec<OperationSelector>>,
+
+    /// Never fuzz the operations matching one of these selectors, which have the same
+    /// form as for --include-operations. Can be given more than once.
+    #[clap(long, value_parser, value_name = "SELECTOR")]
+    pub exclude_operations: Option<Vec<OperationSelector>>,
+
+    /// Maximum number of requests per second sent to the target, by all workers together.
+    /// Unlimited if omitted.
+    #[clap(long, value_parser, value_name = "REQUESTS_PER_SECOND")]
+    pub rate_limit: Option<NonZeroU32>,
+
+    /// Maximum number of requests that the workers have in flight at the same time.
+    /// Unlimited if omitted.
+    #[clap(long, value_parser)]
+    pub max_concurrent_requests: Option<NonZeroUsize>,
+
+    /// Endpoint that tells whether the target is up, as a path relative to the target URL
+    /// (such as /health) or as a full URL. It is up if a GET request does not result in a
+    /// server error. Without it, the target is up if it accepts connections.
+    #[clap(long, value_parser, value_name = "PATH_OR_URL")]
+    pub health_check: Option<String>,
+
+    /// Shell command that starts the target, run before fuzzing and again whenever the target
+    /// stops responding. A command that keeps running (use `exec` to replace the shell) is
+    /// stopped before a restart and when the campaign ends.
+    #[clap(long, value_parser, value_name = "COMMAND")]
+    pub target_command: Option<String>,
+
+    /// Number of consecutive requests without a response after which the target is considered
+    /// down, even if its health check passes. Defaults to 3.
+    #[clap(long, value_parser)]
+    pub max_transport_failures: Option<NonZeroU32>,
+
+    /// Hooks that reset the state of the target before a request sequence, which have the
+    /// form http:<METHOD> <URL>, command:<COMMAND> or sql:<DATABASE> < <SCRIPT>. Can be given
+    /// more than once; the hooks run in that order. Can not be combined with more than one
+    /// worker or with concurrent sequences, since a reset changes the state under the
+    /// sequences that are sent at the same time.
+    #[clap(long = "reset-hook", value_parser, value_name = "HOOK")]
+    pub reset_hooks: Option<Vec<ResetHook>>,
+
+    /// Run the reset hooks before every this many request sequences, instead of before
+    /// every sequence.
+    #[clap(long, value_parser, value_name = "SEQUENCES")]
+    pub reset_interval: Option<NonZeroUsize>,
+
+    /// Requests without a response that are saved as findings: requests that time out
+    /// (timeout) or connections that the target drops without responding (reset). They are
+    /// saved to the timeouts and resets directories, next to the crashes.
+    #[clap(long, value_enum, value_delimiter = ',', ignore_case = true)]
+    pub transport_objectives: Option<Vec<TransportObjective>>,
+
+    /// Number of mutated request sequences that each worker sends concurrently. Sequences
+    /// are sent one at a time if omitted.
+    #[clap(long, value_parser, value_name = "SEQUENCES")]
+    pub concurrent_sequences: Option<NonZeroUsize>,
+
+    /// Report a request as a crash when it takes more than this many times the median
+    /// latency of its operation (and more than this many median absolute deviations above it).
+    #[clap(long, value_parser, value_name = "MULTIPLE")]
+    pub latency_multiple: Option<NonZeroU32>,
+
+    /// Report a request as a crash when it takes longer than this many milliseconds.
+    #[clap(long, value_parser, value_name = "MILLISECONDS")]
+    pub latency_threshold: Option<NonZeroU64>,
+
+    /// Report a response as a crash when it is more than this many times the median size of
+    /// the responses to its operation (and more than this many median absolute deviations
+    /// above it).
+    #[clap(long, value_parser, value_name = "MULTIPLE")]
+    pub response_size_multiple: Option<NonZeroU32>,
+
+    /// Rules that decide which responses are crashes before the crash criterion does. Each
+    /// rule has a name, and optionally statuses (such as 500 or 5xx), a regular expression that
+    /// the body must contain and an action (crash or ignore). Only in the configuration file.
+    #[clap(skip)]
+    pub crash_rules: Option<Vec<CrashRule>>,
+
+    /// Number of crashes with the same error signature (operation, status, kind of error and
+    /// stack frames or normalized response body) that are saved. Later crashes with that signature
+    /// only count as hits of its bucket. Defaults to 3.
+    #[clap(long, value_parser, value_name = "N")]
+    pub max_crashes_per_bucket: Option<NonZeroUsize>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
@@ -426,6 +901,26 @@ pub enum CrashCriterion {
     Only5xx,
 }
 
+/// Requests without a response that are saved as findings.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, ValueEnum, Deserialize)]
+pub enum TransportObjective {
+    /// The request timed out
+    #[serde(alias = "timeout")]
+    Timeout,
+    /// The target reset or closed the connection before responding
+    #[serde(alias = "reset")]
+    Reset,
+}
+
+impl std::fmt::Display for TransportObjective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Timeout => "timeout",
+            Self::Reset => "reset",
+        })
+    }
+}
+
 /// The main configuration object.
 #[derive(PartialEq, Eq)]
 pub struct Configuration {
@@ -481,6 +976,78 @@ pub struct Configuration {
 
     /// Log level to output. This flag takes precedence over the environment variable.
     pub log_level: log::LevelFilter,
+
+    /// Directory in which the fuzzer state is saved when the campaign ends (also in an
+    /// error) and every five minutes while it runs. If the directory already contains a
+    /// saved state, the campaign continues from that state instead of starting from scratch.
+    pub resume: Option<PathBuf>,
+
+    /// Number of workers that fuzz the target in parallel. Each worker has its own HTTP
+    /// client and cookie store; new corpus entries are shared between the workers.
+    pub workers: NonZeroUsize,
+
+    /// Seed for the random number generators of the fuzzer. For a given seed and target,
+    /// corpus generation and mutations are deterministic, so a run can be replayed exactly.
+    pub seed: u64,
+
+    /// Directory in which all output of the fuzzer is stored, with a subdirectory per run.
+    /// If `None`, the output is written to the working directory.
+    pub output_dir: Option<PathBuf>,
+
+    /// Base URL to send requests to, instead of the servers in the specification.
+    /// For example `http://localhost:8080/api/v1`.
+    pub target_url: Option<Url>,
+
+    /// Index of the server in the specification to send requests to, if no target URL is given.
+    pub server_index: usize,
+
+    /// Only the operations matching one of these selectors are fuzzed (all if empty)
+    pub include_operations: Vec<OperationSelector>,
+
+    /// The operations matching one of these selectors are never fuzzed
+    pub exclude_operations: Vec<OperationSelector>,
+
+    /// Maximum number of requests per second sent to the target, or `None` for no limit.
+    pub rate_limit: Option<NonZeroU32>,
+
+    /// Maximum number of requests in flight at the same time, or `None` for no limit.
+    pub max_concurrent_requests: Option<NonZeroUsize>,
+
+    /// Path or URL of the health-check endpoint of the target, if any.
+    pub health_check: Option<String>,
+
+    /// Shell command that (re)starts the target, if any.
+    pub target_command: Option<String>,
+
+    /// Number of consecutive requests without a response after which the target is down.
+    pub max_transport_failures: NonZeroU32,
+
+    /// Hooks that reset the state of the target before a request sequence.
+    pub reset_hooks: Vec<ResetHook>,
+
+    /// Number of request sequences between two runs of the reset hooks.
+    pub reset_interval: NonZeroUsize,
+
+    /// Requests without a response that are saved as findings.
+    pub transport_objectives: Vec<TransportObjective>,
+
+    /// Number of mutated request sequences that each worker sends concurrently.
+    pub concurrent_sequences: NonZeroUsize,
+
+    /// Multiple of the median latency of an operation above which a request is a crash.
+    pub latency_multiple: Option<NonZeroU32>,
+
+    /// Latency in milliseconds above which a request is a crash.
+    pub latency_threshold: Option<NonZeroU64>,
+
+    /// Multiple of the median response size of an operation above which a response is a crash.
+    pub response_size_multiple: Option<NonZeroU32>,
+
+    /// Rules that decide which responses are crashes before the crash criterion does.
+    pub crash_rules: Vec<CrashRule>,
+
+    /// Number of crashes with the same error signature that are saved.
+    pub max_crashes_per_bucket: NonZeroUsize,
 }
 
 /// CoverageConfiguration holds all the coverage-agent-specific configuration.
@@ -540,6 +1107,29 @@ impl TryFrom<PartialConfiguration> for Configuration {
             bail!("No OpenAPI specification file given");
         }
 
+        if value.workers.is_some_and(|workers| workers.get() > 1)
+            && value
+                .reset_hooks
+                .as_ref()
+                .is_some_and(|hooks| !hooks.is_empty())
+        {
+            bail!(
+                "Reset hooks can not be combined with more than one worker, since a reset changes the state of the target under the request sequences of the other workers",
+            );
+        }
+        if value
+            .concurrent_sequences
+            .is_some_and(|sequences| sequences.get() > 1)
+            && value
+                .reset_hooks
+                .as_ref()
+                .is_some_and(|hooks| !hooks.is_empty())
+        {
+            bail!(
+                "Reset hooks can not be combined with concurrent sequences, since the sequences sent at the same time would change each other's state after a single reset",
+            );
+        }
+
         Ok(Self {
             openapi_spec: value.openapi_spec,
             initial_corpus: value.initial_corpus,
@@ -569,6 +1159,32 @@ impl TryFrom<PartialConfiguration> for Configuration {
             authentication: value.authentication,
             header: value.header,
             log_level: value.log_level.unwrap_or(DEFAULT_LOG_LEVEL),
+            resume: value.resume,
+            workers: value.workers.unwrap_or(DEFAULT_WORKERS),
+            seed: value.seed.unwrap_or_else(libafl_bolts::current_nanos),
+            output_dir: value.output_dir,
+            target_url: value.target_url,
+            server_index: value.server_index.unwrap_or(0),
+            include_operations: value.include_operations.unwrap_or_default(),
+            exclude_operations: value.exclude_operations.unwrap_or_default(),
+            rate_limit: value.rate_limit,
+            max_concurrent_requests: value.max_concurrent_requests,
+            health_check: value.health_check,
+            target_command: value.target_command,
+            max_transport_failures: value
+                .max_transport_failures
+                .unwrap_or(DEFAULT_MAX_TRANSPORT_FAILURES),
+            reset_hooks: value.reset_hooks.unwrap_or_default(),
+            reset_interval: value.reset_interval.unwrap_or(NonZeroUsize::MIN),
+            transport_objectives: value.transport_objectives.unwrap_or_default(),
+            concurrent_sequences: value.concurrent_sequences.unwrap_or(NonZeroUsize::MIN),
+            latency_multiple: value.latency_multiple,
+            latency_threshold: value.latency_threshold,
+            response_size_multiple: value.response_size_multiple,
+            crash_rules: value.crash_rules.unwrap_or_default(),
+            max_crashes_per_bucket: value
+                .max_crashes_per_bucket
+                .unwrap_or(DEFAULT_MAX_CRASHES_PER_BUCKET),
         })
     }
 }
@@ -582,7 +1198,9 @@ impl PartialConfiguration {
         let cli_config = Cli::parse();
         // Load any configuration file
         let mut file_config = match cli_config.command.config_filename() {
-            Some(filename) => PartialConfiguration::from_yaml_file(filename)?,
+            Some(filename) => {
+                PartialConfiguration::from_yaml_file(filename, cli_config.command.profile())?
+            }
             None => return cli_config.command.fuzzer_config(),
         };
 
@@ -591,10 +1209,24 @@ impl PartialConfiguration {
         Ok(file_config)
     }
 
-    /// Loads a Configuration from a yaml file
-    fn from_yaml_file(filename: &Path) -> Result<Self, anyhow::Error> {
-        let file = std::fs::File::open(filename)?;
-        Ok(serde_yaml::from_reader(file)?)
+    /// Loads a Configuration from a yaml file, interpolating environment variables.
+    /// If a profile is given, its settings from the `profiles` section of the file
+    /// take precedence over the rest of the file.
+    fn from_yaml_file(filename: &Path, profile: Option<&str>) -> Result<Self, anyhow::Error> {
+        let mut contents: serde_yaml::Mapping = read_yaml_file(filename)?;
+        let profiles = contents.remove("profiles");
+        let mut config: Self = serde_yaml::from_value(contents.into())?;
+        if let Some(profile) = profile {
+            let profile_config = profiles
+                .as_ref()
+                .and_then(|profiles| profiles.get(profile))
+                .ok_or_else(|| anyhow!("Profile {profile} not found in {}", filename.display()))?;
+            config.overwrite_from(
+                serde_yaml::from_value(profile_config.clone())
+                    .with_context(|| format!("Could not parse profile {profile}"))?,
+            );
+        }
+        Ok(config)
     }
 
     /// Overwrites `self` with the options given in other. If `other` contains
@@ -621,6 +1253,40 @@ impl PartialConfiguration {
             jacoco_class_prefix: other
                 .jacoco_class_prefix
                 .or_else(|| self.jacoco_class_prefix.take()),
+            resume: other.resume.or(self.resume.take()),
+            workers: other.workers.or(self.workers.take()),
+            seed: other.seed.or(self.seed.take()),
+            output_dir: other.output_dir.or(self.output_dir.take()),
+            target_url: other.target_url.or(self.target_url.take()),
+            server_index: other.server_index.or(self.server_index.take()),
+            include_operations: other.include_operations.or(self.include_operations.take()),
+            exclude_operations: other.exclude_operations.or(self.exclude_operations.take()),
+            rate_limit: other.rate_limit.or(self.rate_limit.take()),
+            max_concurrent_requests: other
+                .max_concurrent_requests
+                .or(self.max_concurrent_requests.take()),
+            health_check: other.health_check.or(self.health_check.take()),
+            target_command: other.target_command.or(self.target_command.take()),
+            max_transport_failures: other
+                .max_transport_failures
+                .or(self.max_transport_failures.take()),
+            reset_hooks: other.reset_hooks.or(self.reset_hooks.take()),
+            reset_interval: other.reset_interval.or(self.reset_interval.take()),
+            transport_objectives: other
+                .transport_objectives
+                .or(self.transport_objectives.take()),
+            concurrent_sequences: other
+                .concurrent_sequences
+                .or(self.concurrent_sequences.take()),
+            latency_multiple: other.latency_multiple.or(self.latency_multiple.take()),
+            latency_threshold: other.latency_threshold.or(self.latency_threshold.take()),
+            response_size_multiple: other
+                .response_size_multiple
+                .or(self.response_size_multiple.take()),
+            crash_rules: other.crash_rules.or(self.crash_rules.take()),
+            max_crashes_per_bucket: other
+                .max_crashes_per_bucket
+                .or(self.max_crashes_per_bucket.take()),
         };
     }
 }
@@ -648,7 +1314,10 @@ fn parse_socket_addr(arg: &str) -> Result<SocketAddr, io::Error> {
 
 #[cfg(test)]
 mod tests {
-    use std::{convert::TryInto, num::NonZeroU64};
+    use std::{
+        convert::TryInto,
+        num::{NonZeroU64, NonZeroUsize},
+    };
 
     use super::{
         parse_socket_addr, Configuration, CoverageConfiguration, CoverageFormat, OutputFormat,
@@ -746,6 +1415,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_try_from_reset_hooks_with_workers() {
+        let stored_config = |workers| PartialConfiguration {
+            openapi_spec: Some("open_api.yaml".into()),
+            reset_hooks: Some(vec!["http:POST /reset".parse().unwrap()]),
+            workers: NonZeroUsize::new(workers),
+            ..Default::default()
+        };
+
+        assert!(Configuration::try_from(stored_config(1)).is_ok());
+        match Configuration::try_from(stored_config(2)) {
+            Ok(_) => panic!("Reset hooks with several workers were accepted"),
+            Err(e) => assert!(e
+                .to_string()
+                .starts_with("Reset hooks can not be combined with more than one worker")),
+        }
+    }
+
+    #[test]
+    fn test_try_from_reset_hooks_with_concurrent_sequences() {
+        let stored_config = |sequences| PartialConfiguration {
+            openapi_spec: Some("open_api.yaml".into()),
+            reset_hooks: Some(vec!["http:POST /reset".parse().unwrap()]),
+            concurrent_sequences: NonZeroUsize::new(sequences),
+            ..Default::default()
+        };
+
+        assert!(Configuration::try_from(stored_config(1)).is_ok());
+        match Configuration::try_from(stored_config(4)) {
+            Ok(_) => panic!("Reset hooks with concurrent sequences were accepted"),
+            Err(e) => assert!(e
+                .to_string()
+                .starts_with("Reset hooks can not be combined with concurrent sequences")),
+        }
+    }
+
     #[test]
     fn test_overwrite() {
         let mut file_config: PartialConfiguration = PartialConfiguration {
@@ -776,4 +1481,31 @@ mod tests {
         file_config.overwrite_from(cli_config);
         assert_eq!(file_config, result_config);
     }
+
+    #[test]
+    fn test_from_yaml_file_with_profile() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(
+            &mut file,
+            b"openapi_spec: open_api.yaml
+timeout: 60
+profiles:
+  ci:
+    timeout: 600
+    report: true
+",
+        )
+        .unwrap();
+
+        let base = PartialConfiguration::from_yaml_file(file.path(), None).unwrap();
+        assert_eq!(base.timeout, NonZeroU64::new(60));
+        assert_eq!(base.report, None);
+
+        let ci = PartialConfiguration::from_yaml_file(file.path(), Some("ci")).unwrap();
+        assert_eq!(ci.openapi_spec, Some("open_api.yaml".into()));
+        assert_eq!(ci.timeout, NonZeroU64::new(600));
+        assert_eq!(ci.report, Some(true));
+
+        assert!(PartialConfiguration::from_yaml_file(file.path(), Some("local")).is_err());
+    }
 }
@@ -12,7 +12,7 @@ use reqwest::{
     Url,
 };
 
-use super::CoverageClient;
+use super::{restore_map, CoverageClient, SavedCoverage};
 use crate::coverage_clients::MAP_SIZE;
 
 #[derive(Debug, serde::Deserialize)]
@@ -136,4 +136,26 @@ impl CoverageClient for CoverbandCoverageClient {
         let _ = self.latest_coverage_information;
         unimplemented!()
     }
+
+    fn save_state(&self) -> Result<serde_json::Value, anyhow::Error> {
+        Ok(serde_json::to_value(SavedCoverage {
+            cov_map_total: self.cov_map_baseline.to_vec(),
+            bit_idx_mapping: self
+                .bit_idx_mapping
+                .iter()
+                .map(|(key, idx)| (key.clone(), *idx))
+                .collect(),
+            first_unused_idx: self.first_unused_idx,
+            max_ratio: self.max_ratio,
+        })?)
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), anyhow::Error> {
+        let saved: SavedCoverage<String, u32> = serde_json::from_value(state)?;
+        restore_map(&mut self.cov_map_baseline, &saved.cov_map_total)?;
+        self.bit_idx_mapping = saved.bit_idx_mapping.into_iter().collect();
+        self.first_unused_idx = saved.first_unused_idx;
+        self.max_ratio = saved.max_ratio;
+        Ok(())
+    }
 }
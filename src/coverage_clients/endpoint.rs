@@ -20,8 +20,9 @@ use std::{
 use build_html::{escape_html, Container, ContainerType, Html, HtmlContainer, HtmlPage};
 use indexmap::{map::Entry, IndexMap};
 use openapiv3::{OpenAPI, StatusCode};
+use serde::{Deserialize, Serialize};
 
-use super::{CoverageClient, MAP_SIZE};
+use super::{restore_map, CoverageClient, MAP_SIZE};
 use crate::input::Method;
 
 const HIT_SYMBOL: &str = "&#x2714;&#xfe0f;";
@@ -37,7 +38,7 @@ pub struct EndpointCoverageClient {
     max_ratio: (u64, u64),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(clippy::enum_variant_names)]
 enum Coverage {
     /// This status code occurs in the specification, but was not seen
@@ -48,6 +49,16 @@ enum Coverage {
     UnexpectedFound(String, String),
 }
 
+/// Cumulative state of the endpoint coverage client, used to continue a campaign with
+/// `--resume`. The index map is stored as a list, preserving the order (and therefore the
+/// coverage map index) of every method-path-status triplet.
+#[derive(Serialize, Deserialize)]
+struct SavedEndpointCoverage {
+    endpoint_cov_map: Vec<((Method, String, StatusCode), Coverage)>,
+    cov_map_total: Vec<u8>,
+    len: usize,
+}
+
 impl EndpointCoverageClient {
     /// Creates a new endpoint coverage client given an API specification.
     pub fn new(api: &OpenAPI) -> Self {
@@ -93,11 +104,39 @@ impl EndpointCoverageClient {
         input: String,
         output: String,
     ) {
+        let index = self.record(
+            (method, path, StatusCode::Code(status.as_u16())),
+            input,
+            output,
+        );
+        // Update LibAFL's coverage mapping in any case, since upon reset
+        // all bits are reset to zero.
+        self.cov_map[index / 8] |= 0b10000000 >> (index % 8);
+    }
+
+    /// Merges the coverage found by another endpoint coverage client (e.g. the one of
+    /// another worker) into the cumulative coverage of this one.
+    pub fn merge(&mut self, other: &EndpointCoverageClient) {
+        for (key, coverage) in &other.endpoint_cov_map {
+            if let Coverage::ExpectedFound(input, output)
+            | Coverage::UnexpectedFound(input, output) = coverage
+            {
+                self.record(key.clone(), input.clone(), output.clone());
+            }
+        }
+    }
+
+    /// Records that the method-path-status triplet `key` was seen, and sets its bit in
+    /// `self.cov_map_total`. Returns the index of the triplet in the coverage maps.
+    fn record(
+        &mut self,
+        key: (Method, String, StatusCode),
+        input: String,
+        output: String,
+    ) -> usize {
         // Get the coverage entry for the method-path-status combination.
         // The entry may be Vacant or Occupied, see below for what this means.
-        let entry = self
-            .endpoint_cov_map
-            .entry((method, path, StatusCode::Code(status.as_u16())));
+        let entry = self.endpoint_cov_map.entry(key);
         // Must get the index before entry.insert below, which needs ownership of the entry
         let index = entry.index();
 
@@ -115,9 +154,8 @@ impl EndpointCoverageClient {
                 }
             }
         }
-        // Update LibAFL's coverage mappings in any case, since upon reset
-        // all bits are reset to zero. Note that the index does correspond to the same
-        // method-path-status triplet between runs (the nth-triplet is stored at index n).
+        // Note that the index does correspond to the same method-path-status triplet
+        // between runs (the nth-triplet is stored at index n).
         // For this reason it is important NOT TO REMOVE entries from the endpoint_cov_map
         // during a run: once a triplet is inserted, its index must be unique since this
         // determines the mapping into the AFL-coverage maps.
@@ -126,8 +164,8 @@ impl EndpointCoverageClient {
         // Map the method-path-status triplets via their index to *bits*, not bytes.
         // Hence the bitwise operations, the first 8 indices get mapped into the first byte
         // and since we always *add* coverage, we can OR the corresponding bit into that byte.
-        self.cov_map[index / 8] |= 0b10000000 >> (index % 8);
         self.cov_map_total[index / 8] |= 0b10000000 >> (index % 8);
+        index
     }
 
     fn export_filesystem(&self, base_path: &Path) -> Result<(), libafl::Error> {
@@ -301,6 +339,39 @@ impl CoverageClient for Arc<Mutex<EndpointCoverageClient>> {
         let endpoint_path = report_path.join("endpointcoverage");
         let _ = self.lock().unwrap().export_filesystem(&endpoint_path);
     }
+
+    fn save_state(&self) -> Result<serde_json::Value, anyhow::Error> {
+        let guard = self.lock().unwrap();
+        Ok(serde_json::to_value(SavedEndpointCoverage {
+            endpoint_cov_map: guard
+                .endpoint_cov_map
+                .iter()
+                .map(|(key, cov)| (key.clone(), cov.clone()))
+                .collect(),
+            cov_map_total: guard.cov_map_total.to_vec(),
+            len: guard.len,
+        })?)
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), anyhow::Error> {
+        let saved: SavedEndpointCoverage = serde_json::from_value(state)?;
+        let mut guard = self.lock().unwrap();
+        // Indices into the coverage maps must keep meaning the same triplet, so the saved
+        // map has to start with the triplets from the current specification, in order.
+        if guard
+            .endpoint_cov_map
+            .keys()
+            .zip(saved.endpoint_cov_map.iter().map(|(key, _)| key))
+            .any(|(current, saved)| current != saved)
+            || saved.endpoint_cov_map.len() < guard.endpoint_cov_map.len()
+        {
+            bail!("Saved endpoint coverage does not match the current API specification");
+        }
+        restore_map(&mut guard.cov_map_total, &saved.cov_map_total)?;
+        guard.endpoint_cov_map = saved.endpoint_cov_map.into_iter().collect();
+        guard.len = saved.len;
+        Ok(())
+    }
 }
 
 const COVERAGE_EXPORT_JAVASCRIPT: &str = r##"
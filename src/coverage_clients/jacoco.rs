@@ -19,7 +19,7 @@ use crate::{
     configuration::{Configuration, CoverageConfiguration},
     coverage_clients::{
         read_utilities::{read_bool_array, read_cesu8, read_char, read_u64be},
-        CoverageClient, MAP_SIZE,
+        restore_map, CoverageClient, SavedCoverage, MAP_SIZE,
     },
 };
 
@@ -55,6 +55,7 @@ pub struct JacocoCoverageClient<'a> {
     bit_idx_mapping: HashMap<u64, usize>,
     first_unused_idx: usize,
 
+    socket_address: SocketAddr,
     stream: TeeStream,
     max_ratio: (u64, u64),
     done: bool,
@@ -129,6 +130,7 @@ impl<'a> JacocoCoverageClient<'a> {
             cov_map_total: [0; MAP_SIZE],
             bit_idx_mapping: HashMap::new(),
             first_unused_idx: 0,
+            socket_address: *socket_address,
             stream: conn,
             max_ratio: (0, 0),
             done: false,
@@ -417,6 +419,41 @@ impl CoverageClient for JacocoCoverageClient<'_> {
                 "Could not generate jacoco report, report command of the jacococli.jar failed.",
             );
     }
+
+    fn save_state(&self) -> Result<serde_json::Value, anyhow::Error> {
+        Ok(serde_json::to_value(SavedCoverage {
+            cov_map_total: self.cov_map_total.to_vec(),
+            bit_idx_mapping: self
+                .bit_idx_mapping
+                .iter()
+                .map(|(key, idx)| (*key, *idx))
+                .collect(),
+            first_unused_idx: self.first_unused_idx,
+            max_ratio: self.max_ratio,
+        })?)
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), anyhow::Error> {
+        let saved: SavedCoverage<u64> = serde_json::from_value(state)?;
+        restore_map(&mut self.cov_map_total, &saved.cov_map_total)?;
+        self.bit_idx_mapping = saved.bit_idx_mapping.into_iter().collect();
+        self.first_unused_idx = saved.first_unused_idx;
+        self.max_ratio = saved.max_ratio;
+        Ok(())
+    }
+
+    fn reconnect(&mut self) -> Result<(), anyhow::Error> {
+        self.stream = TeeStream {
+            stream: TcpStream::connect(self.socket_address).map_err(|err| {
+                anyhow!(
+                    "Failed to reconnect to the Jacoco agent at {}: {err}",
+                    self.socket_address
+                )
+            })?,
+            bytes: Vec::new(),
+        };
+        Ok(())
+    }
 }
 
 fn segment_matches_prefix(prefix_filter: &Option<String>, segment: &JacocoCoverageSegment) -> bool {
@@ -18,7 +18,7 @@ use crate::{
     configuration::{Configuration, CoverageConfiguration},
     coverage_clients::{
         read_utilities::{read_byte_vec, read_char},
-        CoverageClient, MAP_SIZE,
+        restore_map, CoverageClient, SavedCoverage, MAP_SIZE,
     },
 };
 extern crate num;
@@ -54,6 +54,7 @@ pub struct LcovCoverageClient {
     bit_idx_mapping: HashMap<SourceFileAndLineNum, usize>,
     first_unused_idx: usize,
 
+    socket_address: SocketAddr,
     stream: TeeStream,
     max_ratio: (u64, u64),
     done: bool,
@@ -113,6 +114,7 @@ impl LcovCoverageClient {
             cov_map_total: [0; MAP_SIZE],
             bit_idx_mapping: HashMap::new(),
             first_unused_idx: 0,
+            socket_address: *socket_address,
             stream: conn,
             max_ratio: (0, 0),
             done: false,
@@ -251,10 +253,8 @@ impl LcovCoverageClient {
                     line,
                     count,
                     checksum: _,
-                } => {
-                    if count != 0 {
-                        self.set_cov_bit(&source_path, line, 1);
-                    }
+                } if count != 0 => {
+                    self.set_cov_bit(&source_path, line, 1);
                 }
                 _ => (),
             }
@@ -373,9 +373,44 @@ impl CoverageClient for LcovCoverageClient {
             log::error!("{err}");
         }
     }
+
+    fn save_state(&self) -> Result<serde_json::Value, anyhow::Error> {
+        Ok(serde_json::to_value(SavedCoverage {
+            cov_map_total: self.cov_map_total.to_vec(),
+            bit_idx_mapping: self
+                .bit_idx_mapping
+                .iter()
+                .map(|(key, idx)| (key.clone(), *idx))
+                .collect(),
+            first_unused_idx: self.first_unused_idx,
+            max_ratio: self.max_ratio,
+        })?)
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), anyhow::Error> {
+        let saved: SavedCoverage<SourceFileAndLineNum> = serde_json::from_value(state)?;
+        restore_map(&mut self.cov_map_total, &saved.cov_map_total)?;
+        self.bit_idx_mapping = saved.bit_idx_mapping.into_iter().collect();
+        self.first_unused_idx = saved.first_unused_idx;
+        self.max_ratio = saved.max_ratio;
+        Ok(())
+    }
+
+    fn reconnect(&mut self) -> Result<(), anyhow::Error> {
+        self.stream = TeeStream {
+            stream: TcpStream::connect(self.socket_address).map_err(|err| {
+                anyhow!(
+                    "Failed to reconnect to the LCOV agent at {}: {err}",
+                    self.socket_address
+                )
+            })?,
+            bytes: Vec::new(),
+        };
+        Ok(())
+    }
 }
 
-#[derive(Eq, PartialEq, Debug, Hash, Clone)]
+#[derive(Eq, PartialEq, Debug, Hash, Clone, serde::Serialize, serde::Deserialize)]
 struct SourceFileAndLineNum {
     file: PathBuf,
     linenum: u32,
@@ -9,6 +9,7 @@ use std::{
 };
 
 use anyhow::Context;
+use serde::{Deserialize, Serialize};
 
 use crate::configuration::{self, Configuration};
 
@@ -47,6 +48,63 @@ pub trait CoverageClient {
 
     /// Write a format-dependent report to disk
     fn generate_coverage_report(&self, report_path: &Path);
+
+    /// Export the cumulative coverage of this client, so that a later run can continue
+    /// from it (see `--resume`). Clients without cumulative state return `Value::Null`.
+    fn save_state(&self) -> Result<serde_json::Value, anyhow::Error> {
+        Ok(serde_json::Value::Null)
+    }
+
+    /// Restore cumulative coverage exported earlier by `save_state`.
+    fn restore_state(&mut self, _state: serde_json::Value) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    /// Connect to the coverage agent again, after the target was restarted. The cumulative
+    /// coverage is kept.
+    fn reconnect(&mut self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
+
+/// Cumulative coverage of a client that maps source locations to spots in its coverage
+/// map. The mapping is stored as a list of pairs, since its keys are not necessarily
+/// strings.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedCoverage<K, T = u8> {
+    pub cov_map_total: Vec<T>,
+    pub bit_idx_mapping: Vec<(K, usize)>,
+    pub first_unused_idx: usize,
+    pub max_ratio: (u64, u64),
+}
+
+/// Copies a saved coverage map into `dst`, checking that the sizes agree.
+pub(crate) fn restore_map<T: Copy>(dst: &mut [T], src: &[T]) -> Result<(), anyhow::Error> {
+    if dst.len() != src.len() {
+        bail!(
+            "Saved coverage map has {} entries, but the current map has {}",
+            src.len(),
+            dst.len()
+        );
+    }
+    dst.copy_from_slice(src);
+    Ok(())
+}
+
+/// Returns the address of the coverage agent: the configured one, or the default for the
+/// coverage format. Returns `None` if only endpoint coverage is used.
+pub fn coverage_host(clargs: &Configuration) -> Option<SocketAddr> {
+    let default_port = match clargs.coverage_configuration {
+        configuration::CoverageConfiguration::Jacoco { .. } => 6300,
+        configuration::CoverageConfiguration::Lcov { .. }
+        | configuration::CoverageConfiguration::Coverband { .. } => 3001,
+        configuration::CoverageConfiguration::Endpoint => return None,
+    };
+    Some(
+        clargs
+            .coverage_host
+            .unwrap_or_else(|| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), default_port)),
+    )
 }
 
 /// Produces a coverage client corresponding to the given configuration
@@ -60,9 +118,7 @@ pub fn get_coverage_client<'c>(
             ..
         } => Box::new(
             jacoco::JacocoCoverageClient::new(
-                &clargs
-                    .coverage_host
-                    .unwrap_or_else(|| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 6300)),
+                &coverage_host(clargs).expect("Code coverage is configured"),
                 report_path
                     .clone()
                     .map(|report_path| report_path.as_path().join("jacoco_exec")),
@@ -72,9 +128,7 @@ pub fn get_coverage_client<'c>(
         ),
         configuration::CoverageConfiguration::Lcov { .. } => Box::new(
             lcov_client::LcovCoverageClient::new(
-                &clargs
-                    .coverage_host
-                    .unwrap_or_else(|| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 3001)),
+                &coverage_host(clargs).expect("Code coverage is configured"),
                 report_path
                     .clone()
                     .map(|report_path| report_path.as_path().join("lcov_exec")),
@@ -82,9 +136,8 @@ pub fn get_coverage_client<'c>(
             .context("Could not construct LcovCoverageClient")?,
         ),
         configuration::CoverageConfiguration::Coverband { .. } => {
-            let mut url = clargs
-                .coverage_host
-                .unwrap_or_else(|| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 3001))
+            let mut url = coverage_host(clargs)
+                .expect("Code coverage is configured")
                 .to_string();
             url.insert_str(0, "https://");
             Box::new(coverband::CoverbandCoverageClient::new(
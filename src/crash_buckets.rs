@@ -0,0 +1,604 @@
+//! Deduplication of crashes by their error signature.
+//!
+//! Many inputs trigger the same bug, so a long campaign saves thousands of crashes for a few
+//! distinct bugs. Every crash gets a signature: the operation, the status, what made it a crash
+//! (a server error, the kind of validation error, a crash rule, ...) and either the stack frames
+//! in the response body or, if there are none, the response body with the parts that vary
+//! between requests (numbers, ids, quoted values, timestamps) left out. Crashes with the same
+//! signature fall into the same bucket, and only the first `--max-crashes-per-bucket` of them are
+//! saved to the crashes directory; the others only count as hits of their bucket.
+//!
+//! Timeouts and resets that are saved as findings (see `--transport-objectives`) are bucketed
+//! the same way, by their operation and kind of failure.
+//!
+//! The buckets are kept in `crash_buckets.json` next to the crashes directory, and the
+//! `triage` subcommand lists them. The metadata of a saved crash names its bucket.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Display,
+    fs::File,
+    hash::{BuildHasher, Hasher},
+    io::BufWriter,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ahash::RandomState;
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use libafl::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    Error, HasMetadata,
+};
+use libafl_bolts::Named;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::input::Method;
+
+/// Name of the file with the crash buckets, next to the crashes directory
+pub const BUCKETS_FILE: &str = "crash_buckets.json";
+/// Number of stack frames from the top of a stack trace that make up a signature
+const MAX_FRAMES: usize = 5;
+/// Length of the normalized response body in a signature
+const MAX_BODY_LENGTH: usize = 200;
+/// How often the buckets are saved when only their hit counts change
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+/// Keys of JSON error responses whose values differ for every request
+const VOLATILE_KEYS: &[&str] = &[
+    "timestamp",
+    "time",
+    "date",
+    "path",
+    "instance",
+    "traceId",
+    "trace_id",
+    "requestId",
+    "request_id",
+];
+
+lazy_static! {
+    /// Stack frames of Java (`at org.example.Foo.bar(Foo.java:12)`), Python
+    /// (`File "app.py", line 12, in bar`), JavaScript (`at bar (/app/foo.js:12:5)`) and Ruby
+    /// (`app/foo.rb:12:in 'bar'`)
+    static ref FRAME: Regex = Regex::new(concat!(
+        r#"\bat ([\w$.<>]+)\([\w$.]*(?::\d+)?\)"#,
+        r#"|File "(?:[^"]*/)?([^"/]+)", line \d+, in (\S+)"#,
+        r#"|\bat ([\w$.<>]+) \((?:[^()]*/)?([^()/:]+):\d+:\d+\)"#,
+        r#"|(?:[\w.-]+/)*([\w.-]+\.rb):\d+:in [`']([^']+)'"#,
+    ))
+    .unwrap();
+    static ref UUID: Regex =
+        Regex::new(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b").unwrap();
+    static ref HEX: Regex = Regex::new(r"(?i)\b(?:0x)?[0-9a-f]{16,}\b").unwrap();
+    static ref NUMBER: Regex = Regex::new(r"\d+(?:\.\d+)?").unwrap();
+    static ref QUOTED: Regex = Regex::new(r"'[^']*'").unwrap();
+    static ref WHITESPACE: Regex = Regex::new(r"\s+").unwrap();
+}
+
+/// What made an input a crash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashKind {
+    /// A response with a 5xx status
+    ServerError,
+    /// A response that does not match the specification, with the kind of mismatch
+    ValidationError(String),
+    /// A response that matched the crash rule with this name
+    CrashRule(String),
+    /// A request that took much longer than usual
+    SlowRequest,
+    /// A response that was much larger than usual
+    LargeResponse,
+    /// The target went down
+    TargetDown,
+    /// A request that timed out
+    Timeout,
+    /// A connection that the target dropped without responding
+    ConnectionReset,
+}
+
+impl Display for CrashKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ServerError => f.write_str("server error"),
+            Self::ValidationError(kind) => write!(f, "validation error {kind}"),
+            Self::CrashRule(rule) => write!(f, "crash rule {rule}"),
+            Self::SlowRequest => f.write_str("slow request"),
+            Self::LargeResponse => f.write_str("large response"),
+            Self::TargetDown => f.write_str("target down"),
+            Self::Timeout => f.write_str("timeout"),
+            Self::ConnectionReset => f.write_str("connection reset"),
+        }
+    }
+}
+
+/// The parts of a crash that identify the bug behind it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashSignature {
+    /// Operation of the request that crashed
+    pub method: Method,
+    pub path: String,
+    /// Status of the response, if there was one
+    pub status: Option<u16>,
+    pub kind: CrashKind,
+    /// The top stack frames in the response body
+    pub frames: Vec<String>,
+    /// The normalized response body, if it contains no stack frames
+    pub body: String,
+}
+
+libafl_bolts::impl_serdeany!(CrashSignature);
+
+impl CrashSignature {
+    pub fn new(
+        method: Method,
+        path: &str,
+        status: Option<u16>,
+        kind: CrashKind,
+        body: &[u8],
+    ) -> Self {
+        let body = String::from_utf8_lossy(body);
+        let json = serde_json::from_str::<serde_json::Value>(&body).ok();
+        // Stack traces in JSON bodies are in (escaped) strings
+        let text = match &json {
+            Some(json) => json_strings(json).join("\n"),
+            None => body.to_string(),
+        };
+        let frames = stack_frames(&text);
+        let body = match (frames.is_empty(), json) {
+            (false, _) => String::new(),
+            (true, Some(mut json)) => {
+                remove_volatile_keys(&mut json);
+                normalize(&json.to_string())
+            }
+            (true, None) => normalize(&body),
+        };
+        Self {
+            method,
+            path: path.to_owned(),
+            status,
+            kind,
+            frames,
+            body,
+        }
+    }
+
+    /// A short identifier of the signature, which is the same in every run.
+    pub fn id(&self) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        hasher.write(serde_json::to_string(self).unwrap_or_default().as_bytes());
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+/// Returns all strings in a JSON value.
+fn json_strings(json: &serde_json::Value) -> Vec<&str> {
+    match json {
+        serde_json::Value::String(string) => vec![string],
+        serde_json::Value::Array(values) => values.iter().flat_map(json_strings).collect(),
+        serde_json::Value::Object(map) => map.values().flat_map(json_strings).collect(),
+        _ => vec![],
+    }
+}
+
+/// Returns the top stack frames in the text, each as its function and (if given) its file.
+fn stack_frames(text: &str) -> Vec<String> {
+    FRAME
+        .captures_iter(text)
+        .map(|captures| {
+            captures
+                .iter()
+                .skip(1)
+                .flatten()
+                .map(|part| part.as_str())
+                .collect::<Vec<_>>()
+                .join(":")
+        })
+        .take(MAX_FRAMES)
+        .collect()
+}
+
+fn remove_volatile_keys(json: &mut serde_json::Value) {
+    match json {
+        serde_json::Value::Array(values) => values.iter_mut().for_each(remove_volatile_keys),
+        serde_json::Value::Object(map) => {
+            map.retain(|key, _| !VOLATILE_KEYS.contains(&key.as_str()));
+            map.values_mut().for_each(remove_volatile_keys);
+        }
+        _ => (),
+    }
+}
+
+/// Replaces the parts of a response body that vary between requests by placeholders.
+fn normalize(body: &str) -> String {
+    let body = UUID.replace_all(body, "<uuid>");
+    let body = HEX.replace_all(&body, "<hex>");
+    let body = NUMBER.replace_all(&body, "<n>");
+    let body = QUOTED.replace_all(&body, "'<s>'");
+    let body = WHITESPACE.replace_all(&body, " ");
+    body.trim().chars().take(MAX_BODY_LENGTH).collect()
+}
+
+/// The crashes with the same signature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashBucket {
+    pub id: String,
+    pub signature: CrashSignature,
+    /// Number of crashes with this signature, including those that were not saved
+    pub hits: u64,
+    /// Number of crashes with this signature that were saved
+    pub saved: usize,
+    /// When the first and the last crash with this signature were found (RFC 3339)
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+/// The crash buckets of a campaign, shared by all workers.
+pub struct CrashBuckets {
+    path: PathBuf,
+    max_saved: NonZeroUsize,
+    buckets: HashMap<String, CrashBucket>,
+    last_saved: Instant,
+}
+
+impl CrashBuckets {
+    /// Loads the buckets from `path` if it exists, so earlier crashes in the same crashes
+    /// directory are counted too.
+    pub fn load(path: &Path, max_saved: NonZeroUsize) -> Result<Self> {
+        let buckets = if path.exists() {
+            read_buckets(path)?
+                .into_iter()
+                .map(|bucket| (bucket.id.clone(), bucket))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            max_saved,
+            buckets,
+            last_saved: Instant::now(),
+        })
+    }
+
+    /// Counts a crash with the given signature. Returns the id of its bucket, and whether the
+    /// crash should be saved.
+    pub fn record(&mut self, signature: &CrashSignature) -> (String, bool) {
+        let id = signature.id();
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        let is_new = !self.buckets.contains_key(&id);
+        let bucket = self
+            .buckets
+            .entry(id.clone())
+            .or_insert_with(|| CrashBucket {
+                id: id.clone(),
+                signature: signature.clone(),
+                hits: 0,
+                saved: 0,
+                first_seen: now.clone(),
+                last_seen: now.clone(),
+            });
+        bucket.hits += 1;
+        bucket.last_seen = now;
+        let save = bucket.saved < self.max_saved.get();
+        if save {
+            bucket.saved += 1;
+        }
+        if is_new || self.last_saved.elapsed() >= SAVE_INTERVAL {
+            if let Err(error) = self.save() {
+                log::warn!("Could not save the crash buckets: {error:#}");
+            }
+        }
+        (id, save)
+    }
+
+    /// The buckets, in the order they were found.
+    pub fn buckets(&self) -> Vec<&CrashBucket> {
+        let mut buckets: Vec<&CrashBucket> = self.buckets.values().collect();
+        buckets.sort_by(|a, b| a.first_seen.cmp(&b.first_seen));
+        buckets
+    }
+
+    /// Continues with the buckets of a saved campaign. A bucket that is known already, because
+    /// the campaign saves its crashes to the same directory again, keeps the most hits.
+    pub fn restore(&mut self, saved: Vec<CrashBucket>) {
+        for bucket in saved {
+            match self.buckets.get(&bucket.id) {
+                Some(known) if known.hits >= bucket.hits => (),
+                _ => {
+                    self.buckets.insert(bucket.id.clone(), bucket);
+                }
+            }
+        }
+    }
+
+    /// Writes the buckets to their file.
+    pub fn save(&mut self) -> Result<()> {
+        self.last_saved = Instant::now();
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = File::create(&self.path)
+            .with_context(|| format!("Could not create {}", self.path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &self.buckets())?;
+        Ok(())
+    }
+}
+
+fn read_buckets(path: &Path) -> Result<Vec<CrashBucket>> {
+    let file = File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+    serde_json::from_reader(file).with_context(|| format!("Could not parse {}", path.display()))
+}
+
+/// Metadata of a saved crash, naming its bucket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashBucketMetadata {
+    pub bucket: String,
+    pub signature: CrashSignature,
+}
+
+libafl_bolts::impl_serdeany!(CrashBucketMetadata);
+
+/// Objective that is only interesting for crashes whose bucket is not full yet. It takes the
+/// signature of the crash from the state, where the executor left it, and counts the crash in
+/// its bucket.
+pub struct CrashBucketFeedback {
+    buckets: Arc<Mutex<CrashBuckets>>,
+    /// Metadata of the crash that is being saved
+    pending: Option<CrashBucketMetadata>,
+}
+
+impl CrashBucketFeedback {
+    pub fn new(buckets: Arc<Mutex<CrashBuckets>>) -> Self {
+        Self {
+            buckets,
+            pending: None,
+        }
+    }
+}
+
+impl Named for CrashBucketFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("CrashBucketFeedback");
+        &NAME
+    }
+}
+
+impl<S> StateInitializer<S> for CrashBucketFeedback {}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for CrashBucketFeedback
+where
+    S: HasMetadata,
+{
+    fn is_interesting(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        let Some(signature) = state.metadata_map_mut().remove::<CrashSignature>() else {
+            // Nothing to deduplicate on
+            return Ok(true);
+        };
+        let (bucket, save) = self.buckets.lock().unwrap().record(&signature);
+        self.pending = save.then_some(CrashBucketMetadata {
+            bucket,
+            signature: *signature,
+        });
+        Ok(save)
+    }
+
+    fn append_metadata(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        if let Some(metadata) = self.pending.take() {
+            testcase.add_metadata(metadata);
+        }
+        Ok(())
+    }
+}
+
+/// Lists the crash buckets in the given file, or in the `crash_buckets.json` file in the given
+/// directory, with the saved crashes of each bucket.
+pub fn triage(path: &Path) -> Result<()> {
+    let path = match path.is_dir() {
+        true => path.join(BUCKETS_FILE),
+        false => path.to_owned(),
+    };
+    let mut buckets = read_buckets(&path)?;
+    buckets.sort_by(|a, b| b.hits.cmp(&a.hits).then(a.first_seen.cmp(&b.first_seen)));
+    // Timeouts and resets are saved next to the crashes
+    let saved_crashes =
+        saved_crashes(&["crashes", "timeouts", "resets"].map(|dir| path.with_file_name(dir)));
+
+    println!(
+        "{} crash buckets, {} crashes",
+        buckets.len(),
+        buckets.iter().map(|bucket| bucket.hits).sum::<u64>()
+    );
+    for bucket in &buckets {
+        let signature = &bucket.signature;
+        let status = signature
+            .status
+            .map_or_else(|| "-".to_owned(), |status| status.to_string());
+        println!();
+        println!(
+            "{}  {} {}  {}  {}",
+            bucket.id, signature.method, signature.path, status, signature.kind
+        );
+        println!(
+            "  hits: {}, first seen: {}, last seen: {}",
+            bucket.hits, bucket.first_seen, bucket.last_seen
+        );
+        match signature.frames.first() {
+            Some(frame) => println!("  top frame: {frame}"),
+            None if !signature.body.is_empty() => println!("  body: {}", signature.body),
+            None => (),
+        }
+        for crash in saved_crashes.get(&bucket.id).into_iter().flatten() {
+            println!("  {}", crash.display());
+        }
+    }
+    Ok(())
+}
+
+/// Finds the saved crashes of every bucket in the given directories, from the metadata files
+/// next to the crashes.
+fn saved_crashes(dirs: &[PathBuf]) -> HashMap<String, Vec<PathBuf>> {
+    let mut saved: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for (crashes, entry) in dirs.iter().flat_map(|crashes| {
+        WalkDir::new(crashes)
+            .max_depth(1)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .map(move |entry| (crashes, entry))
+    }) {
+        let file_name = entry.file_name().to_string_lossy();
+        let Some(name) = file_name
+            .strip_prefix('.')
+            .and_then(|name| name.strip_suffix(".metadata"))
+        else {
+            continue;
+        };
+        let Ok(contents) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        // The metadata map holds (type id, metadata) pairs
+        let Some(bucket) = serde_json::from_str::<serde_json::Value>(&contents)
+            .ok()
+            .and_then(|metadata| {
+                metadata["metadata"]["map"]
+                    .as_object()?
+                    .values()
+                    .find_map(|entry| entry[1]["bucket"].as_str().map(str::to_owned))
+            })
+        else {
+            continue;
+        };
+        saved.entry(bucket).or_default().push(crashes.join(name));
+    }
+    for crashes in saved.values_mut() {
+        crashes.sort();
+    }
+    saved
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::{saved_crashes, CrashBuckets, CrashKind, CrashSignature};
+    use crate::input::Method;
+
+    fn signature(status: u16, body: &str) -> CrashSignature {
+        CrashSignature::new(
+            Method::Get,
+            "/items/{id}",
+            Some(status),
+            CrashKind::ServerError,
+            body.as_bytes(),
+        )
+    }
+
+    #[test]
+    fn test_signatures() {
+        // Spring error responses differ in their timestamp and path only
+        let spring = |timestamp: &str, path: &str| {
+            format!(
+                r#"{{"timestamp":"{timestamp}","status":500,"error":"Internal Server Error","path":"{path}"}}"#
+            )
+        };
+        assert_eq!(
+            signature(500, &spring("2024-01-01T10:00:00Z", "/items/1")),
+            signature(500, &spring("2024-01-02T11:00:00Z", "/items/abcdef"))
+        );
+        assert_ne!(
+            signature(500, &spring("", "/items/1")).id(),
+            signature(502, &spring("", "/items/1")).id()
+        );
+        assert_eq!(
+            signature(500, "Item 'abc' with id 17 not found").body,
+            "Item '<s>' with id <n> not found"
+        );
+
+        let java = "java.lang.NullPointerException: null\n\tat org.example.ItemService.find(ItemService.java:42)\n\tat org.example.ItemController.get(ItemController.java:17)";
+        assert_eq!(
+            signature(500, java).frames,
+            [
+                "org.example.ItemService.find",
+                "org.example.ItemController.get"
+            ]
+        );
+        let python = serde_json::json!({
+            "detail": "Traceback (most recent call last):\n  File \"/app/main.py\", line 12, in get_item\n    return items[id]\nKeyError: 'q'"
+        });
+        let python = signature(500, &python.to_string());
+        assert_eq!(python.frames, ["main.py:get_item"]);
+        assert!(python.body.is_empty());
+        let node = "TypeError: x is undefined\n    at getItem (/app/routes/items.js:10:5)";
+        assert_eq!(signature(500, node).frames, ["getItem:items.js"]);
+        let ruby = "app/controllers/items_controller.rb:8:in 'show'";
+        assert_eq!(signature(500, ruby).frames, ["items_controller.rb:show"]);
+    }
+
+    #[test]
+    fn test_buckets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crash_buckets.json");
+        let mut buckets = CrashBuckets::load(&path, NonZeroUsize::new(2).unwrap()).unwrap();
+        let first = signature(500, "boom 1");
+        let (id, save) = buckets.record(&first);
+        assert!(save);
+        assert!(buckets.record(&signature(500, "boom 2")).1);
+        assert_eq!(
+            buckets.record(&signature(500, "boom 3")),
+            (id.clone(), false)
+        );
+        assert!(buckets.record(&signature(500, "other")).1);
+        buckets.save().unwrap();
+
+        let mut loaded = CrashBuckets::load(&path, NonZeroUsize::new(2).unwrap()).unwrap();
+        assert_eq!(loaded.buckets.len(), 2);
+        assert_eq!(loaded.buckets[&id].hits, 3);
+        assert!(!loaded.record(&first).1);
+    }
+
+    #[test]
+    fn test_saved_crashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let save = |kind: &str, name: &str, bucket: &str| {
+            let kind_dir = dir.path().join(kind);
+            std::fs::create_dir_all(&kind_dir).unwrap();
+            std::fs::write(kind_dir.join(name), "[]").unwrap();
+            let metadata = serde_json::json!({"metadata": {"map": {"1": [1, {"bucket": bucket}]}}});
+            std::fs::write(
+                kind_dir.join(format!(".{name}.metadata")),
+                metadata.to_string(),
+            )
+            .unwrap();
+        };
+        save("crashes", "a", "server");
+        save("crashes", "b", "server");
+        save("timeouts", "c", "timeout");
+
+        // Timeouts and resets are found next to the crashes
+        let dirs = ["crashes", "timeouts", "resets"].map(|kind| dir.path().join(kind));
+        let saved = saved_crashes(&dirs);
+        assert_eq!(saved["server"], [dirs[0].join("a"), dirs[0].join("b")]);
+        assert_eq!(saved["timeout"], [dirs[1].join("c")]);
+    }
+}
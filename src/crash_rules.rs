@@ -0,0 +1,215 @@
+//! Rules that decide which responses are crashes, on top of the crash criterion.
+//!
+//! Rules are given as `crash_rules` in the configuration file, and are checked in order for
+//! every response. The first rule whose statuses and body pattern match the response decides:
+//! the response is a crash (`action: crash`, the default) or it is not (`action: ignore`), and
+//! the crash criterion is not consulted. Responses that no rule matches are judged by the crash
+//! criterion as usual. For example,
+//!
+//! ```yaml
+//! crash_rules:
+//!   - name: unavailable
+//!     status: [503, 501]
+//!     action: ignore
+//!   - name: stack-trace
+//!     status: [2xx]
+//!     body: "Traceback \\(most recent call last\\)|NullPointerException|SQLSTATE"
+//! ```
+//!
+//! ignores 503 and 501 responses, and reports successful responses that contain a stack trace
+//! or a database error. The name of the rule that fired is saved in the metadata of the crash.
+
+use std::str::FromStr;
+
+use anyhow::Context;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::anomaly::AnomalyFeedback;
+
+/// A rule that decides whether matching responses are crashes.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CrashRule {
+    /// Name of the rule, saved in the crashes it causes
+    pub name: String,
+    /// Statuses that the rule applies to, or all statuses if empty
+    #[serde(default)]
+    pub status: Vec<StatusPattern>,
+    /// Regular expression that the response body must contain
+    pub body: Option<BodyPattern>,
+    /// What to do with matching responses
+    #[serde(default)]
+    pub action: RuleAction,
+}
+
+impl CrashRule {
+    fn matches(&self, status: u16, body: &str) -> bool {
+        (self.status.is_empty() || self.status.iter().any(|pattern| pattern.matches(status)))
+            && self
+                .body
+                .as_ref()
+                .is_none_or(|pattern| pattern.0.is_match(body))
+    }
+}
+
+/// What to do with a response that a rule matches.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    /// The response is a crash
+    #[default]
+    Crash,
+    /// The response is not a crash
+    Ignore,
+}
+
+/// A status code such as `500`, or a class of status codes such as `5xx`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "StatusValue")]
+pub enum StatusPattern {
+    Code(u16),
+    Class(u16),
+}
+
+impl StatusPattern {
+    fn matches(self, status: u16) -> bool {
+        match self {
+            Self::Code(code) => status == code,
+            Self::Class(class) => status / 100 == class,
+        }
+    }
+}
+
+impl FromStr for StatusPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let pattern = pattern.trim().to_lowercase();
+        let (status, is_class) = match pattern.strip_suffix("xx") {
+            Some(class) => (class, true),
+            None => (pattern.as_str(), false),
+        };
+        let status: u16 = status
+            .parse()
+            .with_context(|| format!("Invalid status {pattern}, expected e.g. 500 or 5xx"))?;
+        Ok(match is_class {
+            true if (1..=5).contains(&status) => Self::Class(status),
+            false if (100..=599).contains(&status) => Self::Code(status),
+            _ => bail!("Invalid status {pattern}, expected e.g. 500 or 5xx"),
+        })
+    }
+}
+
+/// A status as written in the configuration file: a number or a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StatusValue {
+    Number(u16),
+    Text(String),
+}
+
+impl TryFrom<StatusValue> for StatusPattern {
+    type Error = anyhow::Error;
+
+    fn try_from(value: StatusValue) -> Result<Self, Self::Error> {
+        match value {
+            StatusValue::Number(status) => status.to_string().parse(),
+            StatusValue::Text(pattern) => pattern.parse(),
+        }
+    }
+}
+
+/// A regular expression that a response body should contain.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct BodyPattern(Regex);
+
+impl PartialEq for BodyPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for BodyPattern {}
+
+impl TryFrom<String> for BodyPattern {
+    type Error = regex::Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Regex::new(&pattern).map(Self)
+    }
+}
+
+/// Returns the first rule that matches the status and body of a response, if any.
+pub fn matching_rule<'r>(
+    rules: &'r [CrashRule],
+    status: u16,
+    body: &[u8],
+) -> Option<&'r CrashRule> {
+    if rules.is_empty() {
+        return None;
+    }
+    let body = String::from_utf8_lossy(body);
+    rules.iter().find(|rule| rule.matches(status, &body))
+}
+
+/// Metadata of a crash caused by a crash rule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashRuleMetadata {
+    /// Name of the rule that fired
+    pub rule: String,
+    /// Index of the request in the input whose response matched the rule
+    pub request_index: usize,
+    /// Status of the response
+    pub status: u16,
+}
+
+libafl_bolts::impl_serdeany!(CrashRuleMetadata);
+
+/// Objective that records the crash rule that fired in the crash.
+pub fn crash_rule_objective() -> AnomalyFeedback<CrashRuleMetadata> {
+    AnomalyFeedback::new("CrashRuleFeedback")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matching_rule, CrashRule, RuleAction, StatusPattern};
+
+    #[test]
+    fn test_crash_rules() {
+        let rules: Vec<CrashRule> = serde_yaml::from_str(
+            r#"
+- name: unavailable
+  status: [503, "501"]
+  action: ignore
+- name: stack-trace
+  status: [2xx]
+  body: "Traceback \\(most recent call last\\)|NullPointerException"
+- name: server-error
+  status: [5XX]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            rules[0].status,
+            [StatusPattern::Code(503), StatusPattern::Code(501)]
+        );
+        let rule = |status, body: &str| {
+            matching_rule(&rules, status, body.as_bytes())
+                .map(|rule| (rule.name.as_str(), rule.action))
+        };
+        assert_eq!(rule(503, ""), Some(("unavailable", RuleAction::Ignore)));
+        assert_eq!(rule(500, ""), Some(("server-error", RuleAction::Crash)));
+        assert_eq!(
+            rule(200, "java.lang.NullPointerException at Foo.bar"),
+            Some(("stack-trace", RuleAction::Crash))
+        );
+        assert_eq!(rule(200, "{\"name\": \"Traceback\"}"), None);
+        assert_eq!(rule(404, "NullPointerException"), None);
+
+        assert!("6xx".parse::<StatusPattern>().is_err());
+        assert!("42".parse::<StatusPattern>().is_err());
+        assert!(serde_yaml::from_str::<Vec<CrashRule>>("- name: bad\n  body: '('").is_err());
+    }
+}
@@ -1,8 +1,14 @@
 //! The executor module contains our custom executor, which implements the harness (for sending
 //! OpenAPI-based requests to the target) and statistics tracking (mainly coverage).
+//!
+//! Requests are sent with an asynchronous HTTP client on a single-threaded runtime per worker.
+//! A sequence is sent one request at a time, since later requests may refer to the responses
+//! of earlier ones, but independent sequences can be sent concurrently with
+//! [`SequenceExecutor::run_group`], which share the connection pool of the worker.
 
 use std::{
     borrow::Cow,
+    collections::HashMap,
     marker::PhantomData,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -11,38 +17,54 @@ use std::{
     time::{Duration, Instant},
 };
 
+use futures_util::future::join_all;
 use libafl::{
+    corpus::{Corpus, OnDiskCorpus, Testcase},
     events::{Event, EventFirer, EventProcessor, EventRestarter},
     executors::{Executor, ExitKind, HasObservers},
     monitors::{AggregatorOps, UserStats, UserStatsValue},
     observers::ObserversTuple,
     state::{HasExecutions, Stoppable},
-    Error,
+    Error, HasMetadata,
 };
 use libafl_bolts::prelude::RefIndexable;
 use log::{debug, error};
 use openapiv3::OpenAPI;
 use reqwest::blocking::Client;
 use reqwest_cookie_store::CookieStoreMutex;
+use tokio::runtime::Runtime;
 
 use crate::{
+    anomaly::store_in_state,
     authentication::Authentication,
-    configuration::{Configuration, CrashCriterion},
+    configuration::{Configuration, CrashCriterion, TransportObjective},
     coverage_clients::{endpoint::EndpointCoverageClient, CoverageClient},
-    input::OpenApiInput,
+    crash_buckets::{CrashBucketMetadata, CrashBuckets, CrashKind, CrashSignature},
+    crash_rules::{matching_rule, CrashRuleMetadata, RuleAction},
+    input::{Method, OpenApiInput},
+    latency::{LatencyTracker, SlowRequestMetadata},
     openapi::{
         build_request::build_request_from_input,
         curl_request::CurlRequest,
+        filter::OperationFilter,
         validate_response::{validate_response, Response},
     },
+    output::OutputPaths,
     parameter_feedback::ParameterFeedback,
+    rate_limit::{self, RateLimiter},
     reporting::{sqlite::MySqLite, Reporting},
+    response_size::{self, LargeResponseMetadata, ResponseSizeTracker},
+    target::Target,
+    transcript::{TranscriptEntry, TranscriptMetadata},
+    transport_failure::TransportFailure,
 };
 
 /// How often to print a new log line
 const CLIENT_STATS_TIME_WINDOW_SECS: u64 = 5;
+/// How often a request is sent while the target throttles it
+const MAX_THROTTLED_ATTEMPTS: u32 = 5;
 
-type FuzzerState = crate::state::OpenApiFuzzerState<
+pub(crate) type FuzzerState = crate::state::OpenApiFuzzerState<
     OpenApiInput,
     libafl::corpus::InMemoryOnDiskCorpus<OpenApiInput>,
     libafl_bolts::rands::RomuDuoJrRand,
@@ -59,17 +81,38 @@ where
     observers: OT,
 
     api: &'h OpenAPI,
+    filter: &'h OperationFilter,
     config: &'h Configuration,
     authentication: Authentication,
     cookie_store: Arc<CookieStoreMutex>,
 
+    /// Builds the requests, and sends the requests of the reset hooks
     http_client: Client,
+    /// Sends the requests of the sequences, on the runtime of the worker
+    async_client: reqwest::Client,
+    runtime: Runtime,
 
     coverage_client: Box<dyn CoverageClient>,
     endpoint_client: Arc<Mutex<EndpointCoverageClient>>,
     reporter: Option<MySqLite>,
+    /// Where the inputs of each enabled transport objective are saved
+    transport_solutions: HashMap<TransportObjective, OnDiskCorpus<OpenApiInput>>,
+    /// Buckets of the crashes, which also deduplicate the inputs of the transport objectives
+    crash_buckets: Arc<Mutex<CrashBuckets>>,
 
     manual_interrupt: Arc<AtomicBool>,
+    rate_limiter: Arc<RateLimiter>,
+    target: &'static Target,
+    /// Generation of the target that the HTTP and coverage clients are connected to
+    target_generation: u64,
+    /// Number of requests in a row that got no response
+    transport_failures: u32,
+    /// Whether the last input took the target down
+    target_down: bool,
+    /// Why the target could not be brought back up, if it could not
+    target_lost: Option<anyhow::Error>,
+    latency: LatencyTracker,
+    response_size: ResponseSizeTracker,
     maybe_timeout_secs: Option<Duration>,
     starting_time: Instant,
 
@@ -86,30 +129,61 @@ where
     OT: ObserversTuple<OpenApiInput, FuzzerState>,
 {
     /// Create a new SequenceExecutor.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         observers: OT,
         api: &'h OpenAPI,
+        filter: &'h OperationFilter,
         config: &'h Configuration,
         coverage_client: Box<dyn CoverageClient>,
         endpoint_client: Arc<Mutex<EndpointCoverageClient>>,
+        crash_buckets: Arc<Mutex<CrashBuckets>>,
+        output: &OutputPaths,
     ) -> anyhow::Result<Self> {
-        let (authentication, cookie_store, http_client) = crate::build_http_client()?;
+        let (authentication, cookie_store, http_client, async_client) =
+            crate::build_http_client(config)?;
+        let target = Target::shared()?;
 
         Ok(Self {
             observers,
 
             api,
+            filter,
             config,
 
             http_client,
+            async_client,
+            runtime: tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?,
             authentication,
             cookie_store,
 
             coverage_client,
             endpoint_client,
-            reporter: crate::reporting::sqlite::get_reporter(config)?,
+            reporter: crate::reporting::sqlite::get_reporter(config, &output.report_db)?,
+            transport_solutions: config
+                .transport_objectives
+                .iter()
+                .map(|&objective| {
+                    let dir = match objective {
+                        TransportObjective::Timeout => &output.timeouts,
+                        TransportObjective::Reset => &output.resets,
+                    };
+                    Ok((objective, OnDiskCorpus::new(dir)?))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            crash_buckets,
 
             manual_interrupt: setup_interrupt()?,
+            rate_limiter: RateLimiter::shared(config),
+            target,
+            target_generation: target.generation(),
+            transport_failures: 0,
+            target_down: false,
+            target_lost: None,
+            latency: LatencyTracker::from_config(config),
+            response_size: ResponseSizeTracker::new(config.response_size_multiple),
             maybe_timeout_secs: config.timeout.map(|t| Duration::from_secs(t.get())),
             starting_time: Instant::now(),
 
@@ -121,17 +195,36 @@ where
         })
     }
 
-    /// Executes the given input, tracking and using response parameters and verifying responses.
-    /// Returns the target's performance as ExitKind, and the number of requests successfully
-    /// executed (i.e. before an error occurred).
-    fn harness(&mut self, inputs: &OpenApiInput, state: &mut FuzzerState) -> (ExitKind, u64) {
-        let mut exit_kind = ExitKind::Ok;
-        self.inputs_tested += 1;
-        let mut performed_requests = 0;
+    /// Sends the requests of the given input, tracking and using response parameters and
+    /// verifying responses. Only needs shared access to the executor, so several inputs can be
+    /// sent concurrently; `input_id` identifies the input in the report.
+    async fn run_sequence(
+        &self,
+        inputs: &OpenApiInput,
+        state: &FuzzerState,
+        input_id: usize,
+    ) -> SequenceOutcome {
+        let mut outcome = SequenceOutcome {
+            exit_kind: ExitKind::Ok,
+            performed_requests: 0,
+            responded: false,
+            transport_failure: None,
+            responses: Vec::new(),
+            transcript: Vec::new(),
+            findings: Findings::default(),
+        };
 
         let mut parameter_feedback = ParameterFeedback::new(inputs.0.len());
         log::debug!("Sending {} requests", inputs.0.len());
         'chain: for (request_index, request) in inputs.0.iter().enumerate() {
+            if !self.filter.allows(&request.path, request.method) {
+                // E.g. from the initial corpus
+                debug!(
+                    "Not sending {} {}, which is excluded from fuzzing",
+                    request.method, request.path
+                );
+                continue;
+            }
             let mut request = request.clone();
             log::trace!("OpenAPI request:\n{:#?}", request);
             if let Err(error) = request.resolve_parameter_references(&parameter_feedback) {
@@ -141,37 +234,96 @@ where
                 );
                 break 'chain;
             };
-            let request_builder = match build_request_from_input(
-                &self.http_client,
-                &self.cookie_store,
-                self.api,
-                &request,
-            ) {
-                None => continue,
-                Some(r) => r.timeout(Duration::from_millis(self.config.request_timeout)),
-            };
+            // Throttled requests are sent again after the pause, up to a limit
+            let mut attempts = 0;
+            let mut request_bytes;
+            let (result, curl_request, reporter_request_id, sent) = loop {
+                let request_builder = match build_request_from_input(
+                    &self.http_client,
+                    &self.cookie_store,
+                    self.api,
+                    &request,
+                ) {
+                    None => continue 'chain,
+                    Some(r) => r.timeout(Duration::from_millis(self.config.request_timeout)),
+                };
+
+                let request_built = match request_builder.build() {
+                    Ok(request) => request,
+                    Err(err) => {
+                        // We don't expect errors to occur in the reqwest builder. If one occurs,
+                        // it's not the target's fault, so we don't set ExitKind::Crash or Timeout.
+                        error!("Error building request: {err}");
+                        break 'chain;
+                    }
+                };
 
-            let request_built = match request_builder.build() {
-                Ok(request) => request,
-                Err(err) => {
-                    // We don't expect errors to occur in the reqwest builder. If one occurs,
-                    // it's not the target's fault, so we don't set ExitKind::Crash or Timeout.
-                    error!("Error building request: {err}");
-                    break;
+                let curl_request = CurlRequest(&request_built, &self.authentication);
+                let reporter_request_id =
+                    self.reporter
+                        .report_request(&request, &curl_request, state, input_id);
+                let curl_request = curl_request.to_string();
+                let request_built = to_async_request(&request_built);
+                request_bytes = response_size::request_size(&request_built);
+
+                let permit = loop {
+                    match self.rate_limiter.try_acquire() {
+                        Ok(permit) => break permit,
+                        Err(delay) => tokio::time::sleep(delay).await,
+                    }
+                };
+                let sent = Instant::now();
+                let result = self.async_client.execute(request_built).await;
+                drop(permit);
+                attempts += 1;
+                match result {
+                    Ok(response) if rate_limit::is_throttled(&response) => {
+                        self.rate_limiter
+                            .throttle(response.status(), rate_limit::retry_after(&response));
+                        if attempts == MAX_THROTTLED_ATTEMPTS {
+                            break (Ok(response), curl_request, reporter_request_id, sent);
+                        }
+                        outcome.performed_requests += 1;
+                        self.reporter
+                            .report_response(&Response::read(response).await, reporter_request_id);
+                    }
+                    Ok(response) => {
+                        self.rate_limiter.reset_backoff();
+                        break (Ok(response), curl_request, reporter_request_id, sent);
+                    }
+                    Err(err) => break (Err(err), curl_request, reporter_request_id, sent),
                 }
             };
 
-            let curl_request = CurlRequest(&request_built, &self.authentication);
-            let reporter_request_id =
-                self.reporter
-                    .report_request(&request, &curl_request, state, self.inputs_tested);
-            let curl_request = curl_request.to_string();
-
-            match self.http_client.execute(request_built) {
+            let mut entry = TranscriptEntry::new(request_index, &request, &curl_request);
+            match result {
                 Ok(response) => {
-                    performed_requests += 1;
-                    let response: Response = response.into();
+                    outcome.performed_requests += 1;
+                    outcome.responded = true;
+                    let throttled = rate_limit::is_throttled(&response);
+                    let response = Response::read(response).await;
+                    let latency = sent.elapsed();
+                    self.reporter
+                        .report_response(&response, reporter_request_id);
+                    log::trace!("Got response {}", response.status());
+                    entry.response = Some((&response).into());
+                    entry.latency_ms = Some(latency.as_secs_f64() * 1000.0);
+                    outcome.transcript.push(entry);
 
+                    if throttled {
+                        // Not the fault of the input, and not a real response of the endpoint
+                        debug!("The target kept throttling the request, ignoring rest of request chain.");
+                        break 'chain;
+                    }
+                    outcome.responses.push(ResponseStats {
+                        index: request_index,
+                        method: request.method,
+                        path: request.path.clone(),
+                        parameters: crate::response_size::numeric_parameters(&request),
+                        latency,
+                        request_bytes,
+                        response_bytes: response.content_length(),
+                    });
                     self.endpoint_client.lock().unwrap().cover(
                         request.method,
                         request.path.clone(),
@@ -181,53 +333,223 @@ where
                             String::from("Unable to decode the response to UTF-8")
                         }),
                     );
-                    self.reporter
-                        .report_response(&response, reporter_request_id);
-                    log::trace!("Got response {}", response.status());
 
-                    if response.status() == 429 {
-                        log::warn!("HTTP status 429 'Too Many Requests' encountered!");
-                        log::warn!("Rate limiting is likely active on the program under test.");
-                        log::warn!("This hinders fuzz testing. Consider disabling it.");
-                    }
-
-                    if response.status().is_server_error() {
-                        exit_kind = ExitKind::Crash;
+                    let status = response.status().as_u16();
+                    if let Some(rule) =
+                        matching_rule(&self.config.crash_rules, status, response.body())
+                    {
+                        // The first matching rule overrules the crash criterion
+                        if rule.action == RuleAction::Crash {
+                            log::debug!("OpenAPI-input matched crash rule {}, ignoring rest of request chain.", rule.name);
+                            outcome.exit_kind = ExitKind::Crash;
+                            outcome.findings.crash_rule = Some(CrashRuleMetadata {
+                                rule: rule.name.clone(),
+                                request_index,
+                                status,
+                            });
+                            outcome.findings.signature = Some(CrashSignature::new(
+                                request.method,
+                                &request.path,
+                                Some(status),
+                                CrashKind::CrashRule(rule.name.clone()),
+                                response.body(),
+                            ));
+                            break 'chain;
+                        }
+                    } else if response.status().is_server_error() {
+                        outcome.exit_kind = ExitKind::Crash;
+                        outcome.findings.signature = Some(CrashSignature::new(
+                            request.method,
+                            &request.path,
+                            Some(status),
+                            CrashKind::ServerError,
+                            response.body(),
+                        ));
                         log::debug!("OpenAPI-input resulted in server error response, ignoring rest of request chain.");
                         break 'chain;
-                    } else {
-                        if self.config.crash_criterion == CrashCriterion::AllErrors {
-                            if let Err(validation_err) =
-                                validate_response(self.api, &request, &response)
-                            {
-                                log::debug!("OpenAPI-input resulted in validation error: {validation_err}, ignoring rest of request chain.");
-                                exit_kind = ExitKind::Crash;
-                                break 'chain;
+                    } else if self.config.crash_criterion == CrashCriterion::AllErrors {
+                        if let Err(validation_err) =
+                            validate_response(self.api, &request, &response)
+                        {
+                            log::debug!("OpenAPI-input resulted in validation error: {validation_err}, ignoring rest of request chain.");
+                            outcome.exit_kind = ExitKind::Crash;
+                            if let Some(entry) = outcome.transcript.last_mut() {
+                                entry.validation_error = Some(validation_err.to_string());
                             }
+                            outcome.findings.signature = Some(CrashSignature::new(
+                                request.method,
+                                &request.path,
+                                Some(status),
+                                CrashKind::ValidationError(validation_err.kind().to_owned()),
+                                response.body(),
+                            ));
+                            break 'chain;
                         }
-                        if response.status().is_success() {
-                            parameter_feedback.process_response(request_index, response);
-                        }
+                    }
+                    if response.status().is_success() {
+                        parameter_feedback.process_response(request_index, response);
                     }
                 }
                 Err(transport_error) => {
+                    let failure = TransportFailure::classify(&transport_error);
+                    let message = format!("{failure}: {transport_error}");
                     self.reporter
-                        .report_response_error(&transport_error.to_string(), reporter_request_id);
-                    error!("{}", transport_error);
-                    exit_kind = ExitKind::Timeout;
+                        .report_response_error(&message, reporter_request_id);
+                    error!("{message}");
+                    entry.error = Some(message);
+                    entry.latency_ms = Some(sent.elapsed().as_secs_f64() * 1000.0);
+                    outcome.transcript.push(entry);
+                    outcome.transport_failure = Some(failure);
+                    // In case the target went down
+                    outcome.findings.signature = Some(CrashSignature::new(
+                        request.method,
+                        &request.path,
+                        None,
+                        CrashKind::TargetDown,
+                        &[],
+                    ));
                     break;
                 }
             }
             parameter_feedback.process_post_request(request_index, request);
         }
 
-        (exit_kind, performed_requests)
+        outcome
+    }
+
+    /// Processes the outcome of a sequence: keeps track of requests that got no response, and
+    /// saves the input if its transport failure is an enabled objective. Returns the exit kind,
+    /// and the findings for the objectives, which include the first responses that were slow or
+    /// large compared to the usual responses to their operation, if any.
+    fn process_outcome(
+        &mut self,
+        outcome: SequenceOutcome,
+        input: &OpenApiInput,
+    ) -> (ExitKind, Findings) {
+        self.inputs_tested += 1;
+        self.performed_requests += outcome.performed_requests;
+        let mut findings = outcome.findings;
+        findings.transcript = Some(TranscriptMetadata {
+            seed: self.config.seed,
+            requests: outcome.transcript,
+        });
+        for response in outcome.responses {
+            if self.latency.is_enabled() {
+                let slow = self.latency.observe(
+                    response.index,
+                    response.method,
+                    &response.path,
+                    response.latency,
+                );
+                if let Some(slow) = slow.filter(|_| findings.slow_request.is_none()) {
+                    debug!(
+                        "{} {} took {} ms, while its median latency is {:?} ms",
+                        slow.method, slow.path, slow.latency_ms, slow.median_ms
+                    );
+                    findings.slow_request = Some(slow);
+                }
+            }
+            let large = self.response_size.observe(
+                response.index,
+                response.method,
+                &response.path,
+                &response.parameters,
+                response.request_bytes,
+                response.response_bytes,
+            );
+            if let Some(large) = large.filter(|_| findings.large_response.is_none()) {
+                debug!(
+                    "{} {} returned {} bytes, while its median response size is {} bytes",
+                    large.method, large.path, large.response_bytes, large.median_bytes
+                );
+                findings.large_response = Some(large);
+            }
+        }
+        if findings.signature.is_none() {
+            findings.signature = findings
+                .slow_request
+                .as_ref()
+                .map(|slow| (slow.method, &slow.path, CrashKind::SlowRequest))
+                .or_else(|| {
+                    findings
+                        .large_response
+                        .as_ref()
+                        .map(|large| (large.method, &large.path, CrashKind::LargeResponse))
+                })
+                .map(|(method, path, kind)| CrashSignature::new(method, path, None, kind, &[]));
+        }
+        if outcome.responded {
+            self.transport_failures = 0;
+        }
+        let Some(failure) = outcome.transport_failure else {
+            return (outcome.exit_kind, findings);
+        };
+        self.transport_failures += 1;
+        let exit_kind = if self.transport_failures >= self.config.max_transport_failures.get()
+            || !self.target.is_healthy()
+        {
+            // The target went down, so blame the input that was sent last
+            self.target_down = true;
+            ExitKind::Crash
+        } else {
+            let signature = findings.signature.take();
+            self.save_transport_failure(failure, input, findings.transcript.clone(), signature);
+            ExitKind::Timeout
+        };
+        (exit_kind, findings)
+    }
+
+    /// Sends several independent inputs concurrently, and fetches the coverage once for the
+    /// whole group, so it can not be attributed to a single input. Returns the result of each
+    /// input, and whether the group covered new lines or endpoints.
+    pub fn run_group<EM, FZ>(
+        &mut self,
+        state: &mut FuzzerState,
+        event_manager: &mut EM,
+        inputs: &[OpenApiInput],
+    ) -> Result<(Vec<GroupMember>, bool), Error>
+    where
+        EM: EventFirer<OpenApiInput, FuzzerState>
+            + EventRestarter<FuzzerState>
+            + EventProcessor<EM, FuzzerState, FZ>,
+    {
+        self.pre_exec(state, event_manager)?;
+
+        let first_id = self.inputs_tested + 1;
+        let outcomes = self.runtime.block_on(join_all(
+            inputs
+                .iter()
+                .enumerate()
+                .map(|(index, input)| self.run_sequence(input, state, first_id + index)),
+        ));
+        let members = outcomes
+            .into_iter()
+            .zip(inputs)
+            .map(|(outcome, input)| {
+                let (exit_kind, findings) = self.process_outcome(outcome, input);
+                GroupMember {
+                    exit_kind,
+                    findings,
+                }
+            })
+            .collect();
+        *state.executions_mut() += inputs.len() as u64;
+        Findings::default().store_in_state(state);
+
+        // Inputs that crashed are run again on their own anyway, which decides whom to blame
+        if self.target_down && !self.recover_target(&mut ExitKind::Crash) {
+            return Ok((members, false));
+        }
+        let covered = (self.last_covered, self.last_endpoint_covered);
+        self.post_exec(state, event_manager);
+        let new_coverage = (self.last_covered, self.last_endpoint_covered) != covered;
+        Ok((members, new_coverage))
     }
 
+    /// Prepares sending the next input, or group of inputs.
     fn pre_exec<EM, FZ>(
         &mut self,
         state: &mut FuzzerState,
-        _input: &OpenApiInput,
         event_manager: &mut EM,
     ) -> Result<(), Error>
     where
@@ -238,18 +560,107 @@ where
             event_manager.on_shutdown()?;
             return Err(Error::shutting_down());
         }
+        if let Some(err) = self.target_lost.take() {
+            return Err(Error::unknown(format!(
+                "The target could not be brought back up: {err:#}"
+            )));
+        }
+        // Waits while another worker brings the target back up
+        self.reconnect_if_restarted()
+            .map_err(|err| Error::unknown(format!("{err:#}")))?;
+        // Reset hooks are not combined with concurrent sequences, so a group is never reset
+        if self
+            .inputs_tested
+            .is_multiple_of(self.config.reset_interval.get())
+        {
+            crate::reset::run_hooks(&self.config.reset_hooks, &self.http_client, self.api)
+                .map_err(|err| Error::unknown(format!("{err:#}")))?;
+        }
         Ok(())
     }
 
-    fn post_exec<EM>(
+    /// Saves an input with a request that got no response, along with its transcript, if its
+    /// kind of failure is an enabled objective and its crash bucket is not full yet. The
+    /// `signature` is that of the request that got no response.
+    fn save_transport_failure(
         &mut self,
-        state: &mut FuzzerState,
-        _input: &OpenApiInput,
-        event_manager: &mut EM,
-    ) where
+        failure: TransportFailure,
+        input: &OpenApiInput,
+        transcript: Option<TranscriptMetadata>,
+        signature: Option<CrashSignature>,
+    ) {
+        let (Some(objective), Some(kind)) = (failure.objective(), failure.crash_kind()) else {
+            return;
+        };
+        let Some(solutions) = self.transport_solutions.get_mut(&objective) else {
+            return;
+        };
+        let mut testcase = Testcase::new(input.clone());
+        if let Some(mut signature) = signature {
+            signature.kind = kind;
+            let (bucket, save) = self.crash_buckets.lock().unwrap().record(&signature);
+            if !save {
+                debug!("Not saving the {objective}, since its bucket {bucket} is full");
+                return;
+            }
+            testcase.add_metadata(CrashBucketMetadata { bucket, signature });
+        }
+        if let Some(transcript) = transcript {
+            testcase.add_metadata(transcript);
+        }
+        match solutions.add(testcase) {
+            Ok(_) => log::info!("[Objective] New '{objective}' observed!"),
+            Err(err) => error!("Could not save the {objective}: {err}"),
+        }
+    }
+
+    /// Brings the target back up after the last input took it down. Returns false if it
+    /// could not be brought back up.
+    fn recover_target(&mut self, exit_kind: &mut ExitKind) -> bool {
+        self.target_down = false;
+        self.transport_failures = 0;
+        match self.target.recover(self.target_generation) {
+            Ok(true) => (),
+            // Another worker noticed first, and its input is to blame
+            Ok(false) => *exit_kind = ExitKind::Timeout,
+            Err(err) => {
+                self.target_lost = Some(err);
+                return false;
+            }
+        }
+        if let Err(err) = self.reconnect_if_restarted() {
+            self.target_lost = Some(err);
+            return false;
+        }
+        true
+    }
+
+    /// Authenticates again and reconnects the coverage client if the target was restarted
+    /// since they last connected.
+    fn reconnect_if_restarted(&mut self) -> anyhow::Result<()> {
+        let generation = self.target.generation();
+        if generation != self.target_generation {
+            (
+                self.authentication,
+                self.cookie_store,
+                self.http_client,
+                self.async_client,
+            ) = crate::build_http_client(self.config)?;
+            self.coverage_client.reconnect()?;
+            self.target_generation = generation;
+        }
+        Ok(())
+    }
+
+    fn post_exec<EM>(&mut self, state: &mut FuzzerState, event_manager: &mut EM)
+    where
         EM: EventFirer<OpenApiInput, FuzzerState> + EventRestarter<FuzzerState>,
     {
-        self.coverage_client.fetch_coverage(true);
+        // With several workers sending requests at the same time, coverage can not be attributed
+        // to a single input anyway. Resetting it would lose coverage triggered by other workers,
+        // so new coverage is attributed to whichever worker fetches it first instead.
+        self.coverage_client
+            .fetch_coverage(self.config.workers.get() == 1);
         let (covered, total) = self.coverage_client.max_coverage_ratio();
         self.endpoint_client.fetch_coverage(true);
         let (e_covered, e_total) = self.endpoint_client.max_coverage_ratio();
@@ -297,7 +708,13 @@ where
         if self.manual_interrupt.load(Ordering::Relaxed)
             | self
                 .maybe_timeout_secs
-                .map(|timeout| Instant::now() - self.starting_time > timeout)
+                .map(|timeout| {
+                    // Time spent paused because the target throttled requests or was down
+                    // does not count
+                    (Instant::now() - self.starting_time)
+                        .saturating_sub(self.rate_limiter.paused() + self.target.downtime())
+                        > timeout
+                })
                 .unwrap_or(false)
         {
             if let Err(e) = event_manager.fire(state, Event::Stop) {
@@ -307,11 +724,28 @@ where
         }
     }
 
-    /// Uses the embedded coverage clients to generate a coverage report
-    pub fn generate_coverage_report(&self, report_path: &std::path::Path) {
-        self.endpoint_client.generate_coverage_report(report_path);
+    /// Uses the embedded code coverage client to generate a coverage report. The endpoint
+    /// coverage report is generated separately, after combining the endpoint coverage
+    /// of all workers.
+    pub fn generate_code_coverage_report(&self, report_path: &std::path::Path) {
         self.coverage_client.generate_coverage_report(report_path);
     }
+
+    /// Saves the cumulative coverage of the embedded coverage clients and the latency and
+    /// response size baselines to `resume_dir`, so the campaign can be continued later.
+    pub fn save_progress(&self, resume_dir: &std::path::Path) -> anyhow::Result<()> {
+        crate::resume::save_coverage(
+            resume_dir,
+            &self.endpoint_client,
+            self.coverage_client.as_ref(),
+        )?;
+        crate::resume::save_baselines(resume_dir, &self.latency, &self.response_size)
+    }
+
+    /// Restores the latency and response size baselines that were saved to `resume_dir`.
+    pub fn restore_baselines(&mut self, resume_dir: &std::path::Path) -> anyhow::Result<()> {
+        crate::resume::restore_baselines(resume_dir, &mut self.latency, &mut self.response_size)
+    }
 }
 
 impl<EM, FZ, OT> Executor<EM, OpenApiInput, FuzzerState, FZ> for SequenceExecutor<'_, OT>
@@ -328,21 +762,117 @@ where
         event_manager: &mut EM,
         input: &OpenApiInput,
     ) -> Result<ExitKind, libafl::Error> {
-        if let Err(Error::ShuttingDown) = self.pre_exec(state, input, event_manager) {
-            return Err(Error::ShuttingDown);
-        }
+        self.pre_exec(state, event_manager)?;
 
-        let (ret, performed_requests) = self.harness(input, state);
+        let outcome =
+            self.runtime
+                .block_on(self.run_sequence(input, state, self.inputs_tested + 1));
+        let (mut ret, findings) = self.process_outcome(outcome, input);
         *state.executions_mut() += 1;
-        self.performed_requests += performed_requests;
+        findings.store_in_state(state);
 
-        self.post_exec(state, input, event_manager);
+        if self.target_down && !self.recover_target(&mut ret) {
+            // Keep the crash; the campaign stops before the next input
+            return Ok(ret);
+        }
+        self.post_exec(state, event_manager);
         Ok(ret)
     }
 }
 
+/// What happened when the requests of an input were sent.
+struct SequenceOutcome {
+    exit_kind: ExitKind,
+    /// Number of requests that got a response
+    performed_requests: u64,
+    /// Whether any request got a response
+    responded: bool,
+    /// Why the last request got no response, if it did not
+    transport_failure: Option<TransportFailure>,
+    /// Measurements of the requests that got a response
+    responses: Vec<ResponseStats>,
+    /// The requests that were sent, and what came back
+    transcript: Vec<TranscriptEntry>,
+    /// What the harness found out for the objectives
+    findings: Findings,
+}
+
+/// How long a request of a sequence took, and how large it and its response were.
+struct ResponseStats {
+    /// Index of the request in the sequence
+    index: usize,
+    method: Method,
+    path: String,
+    /// Values of the numeric parameters of the request
+    parameters: Vec<(String, f64)>,
+    latency: Duration,
+    request_bytes: u64,
+    response_bytes: u64,
+}
+
+/// What the objectives need to know about an input, besides its exit kind.
+#[derive(Default)]
+pub struct Findings {
+    /// The first request that was slow compared to the usual latency of its operation
+    slow_request: Option<SlowRequestMetadata>,
+    /// The first response that was large compared to the usual size for its operation
+    large_response: Option<LargeResponseMetadata>,
+    /// The crash rule that made the input crash
+    crash_rule: Option<CrashRuleMetadata>,
+    /// The signature of the crash or anomaly, which decides its bucket
+    signature: Option<CrashSignature>,
+    /// The requests and responses of the input, saved with it if it is a finding
+    transcript: Option<TranscriptMetadata>,
+}
+
+impl Findings {
+    /// Whether a response was slow or large compared to the usual responses to its operation.
+    pub fn is_anomalous(&self) -> bool {
+        self.slow_request.is_some() || self.large_response.is_some()
+    }
+
+    /// Leaves the findings in the state, where the objectives pick them up.
+    pub fn store_in_state(self, state: &mut FuzzerState) {
+        store_in_state(state, self.slow_request);
+        store_in_state(state, self.large_response);
+        store_in_state(state, self.crash_rule);
+        store_in_state(state, self.signature);
+        store_in_state(state, self.transcript);
+    }
+}
+
+/// The result of an input that was sent as part of a group.
+pub struct GroupMember {
+    /// How the target handled the input
+    pub exit_kind: ExitKind,
+    /// What the harness found out for the objectives
+    pub findings: Findings,
+}
+
+/// Converts a request built with the blocking client into one for the asynchronous client.
+/// Requests are built with the blocking client, because the curl representation of requests
+/// in the reports is based on blocking requests.
+fn to_async_request(request: &reqwest::blocking::Request) -> reqwest::Request {
+    let mut converted = reqwest::Request::new(request.method().clone(), request.url().clone());
+    *converted.headers_mut() = request.headers().clone();
+    *converted.timeout_mut() = request.timeout().copied();
+    *converted.version_mut() = request.version();
+    *converted.body_mut() = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .map(|bytes| reqwest::Body::from(bytes.to_vec()));
+    converted
+}
+
 /// Installs the Ctrl-C interrupt handler
 fn setup_interrupt() -> Result<Arc<AtomicBool>, anyhow::Error> {
+    // The ctrl-c handler can only be set once, so all workers share the same flag
+    static MANUAL_INTERRUPT: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+    let mut installed = MANUAL_INTERRUPT.lock().unwrap();
+    if let Some(manual_interrupt) = installed.as_ref() {
+        return Ok(Arc::clone(manual_interrupt));
+    }
+
     let manual_interrupt = Arc::new(AtomicBool::new(false));
     {
         let manual_interrupt = Arc::clone(&manual_interrupt);
@@ -356,6 +886,7 @@ fn setup_interrupt() -> Result<Arc<AtomicBool>, anyhow::Error> {
             }
         })?;
     }
+    *installed = Some(Arc::clone(&manual_interrupt));
     Ok(manual_interrupt)
 }
 
@@ -1,37 +1,40 @@
 #[cfg(windows)]
 use std::ptr::write_volatile;
 use std::{
-    fs::create_dir_all,
     marker::PhantomData,
     ops::DerefMut,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result};
 #[allow(unused_imports)]
 use libafl::Fuzzer; // This may be marked unused, but will make the compiler give you crucial error messages
 use libafl::{
-    corpus::{Corpus, OnDiskCorpus},
+    corpus::{Corpus, InMemoryOnDiskCorpus, OnDiskCorpus},
     events::{Event, EventFirer, SimpleEventManager},
     executors::{Executor, ExitKind, HasObservers},
-    feedback_or,
+    feedback_and_fast, feedback_not, feedback_or,
     feedbacks::{
         CrashFeedback, DifferentIsNovel, Feedback, MapFeedback, MaxMapFeedback, MaxReducer,
         TimeFeedback,
     },
-    fuzzer::StdFuzzer,
+    fuzzer::{Evaluator, StdFuzzer},
+    monitors::Monitor,
     mutators::StdScheduledMutator,
-    observers::{CanTrack, ExplicitTracking, MultiMapObserver, StdMapObserver, TimeObserver},
+    observers::{
+        CanTrack, ExplicitTracking, MultiMapObserver, ObserversTuple, StdMapObserver, TimeObserver,
+    },
     schedulers::{
         powersched::PowerSchedule, IndexesLenTimeMinimizerScheduler, PowerQueueScheduler,
     },
-    stages::{CalibrationStage, StdPowerMutationalStage},
+    stages::CalibrationStage,
     state::{HasCorpus, HasExecutions},
     ExecuteInputResult, ExecutionProcessor, HasNamedMetadata,
 };
 use libafl_bolts::{
-    current_nanos, current_time,
+    current_time,
     prelude::OwnedMutSlice,
     rands::StdRand,
     tuples::{tuple_list, MatchName},
@@ -40,15 +43,32 @@ use log::{error, info};
 use openapiv3::OpenAPI;
 
 use crate::{
+    broker::Broker,
+    concurrent_stage::ConcurrentMutationalStage,
     configuration::Configuration,
     coverage_clients::{endpoint::EndpointCoverageClient, CoverageClient},
-    executor::SequenceExecutor,
+    crash_buckets::{CrashBucketFeedback, CrashBuckets},
+    crash_rules::crash_rule_objective,
+    executor::{FuzzerState, SequenceExecutor},
     input::OpenApiInput,
-    monitors::CoverageMonitor,
+    latency::slow_request_objective,
+    monitors::{CoverageMonitor, WorkerMonitor},
+    openapi::filter::OperationFilter,
     openapi_mutator::havoc_mutations_openapi,
+    output::OutputPaths,
+    response_size::large_response_objective,
     state::OpenApiFuzzerState,
+    target::Target,
+    transcript::TranscriptFeedback,
 };
 
+/// Stack size of the worker threads. The coverage clients keep their (large) coverage maps
+/// inline, so the default stack size for spawned threads is not enough.
+const WORKER_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// How often the campaign state is saved while fuzzing with `--resume`
+const RESUME_SAVE_INTERVAL: Duration = Duration::from_secs(300);
+
 /// Main fuzzer function.
 ///
 /// Sets up the various nuts and bolts required by LibAFL and runs the fuzzer until the configured
@@ -56,63 +76,254 @@ use crate::{
 pub fn fuzz() -> Result<()> {
     let config = &Configuration::get().map_err(anyhow::Error::msg)?;
     crate::setup_logging(config);
-    let report_path = config.report.then(generate_report_path);
+    let output = OutputPaths::new(config)?;
+    let campaign = run_campaign(config, &output);
+    // The manifest lists what the run produced, also when it ended in an error
+    if let Err(err) = output.write_manifest(config) {
+        if campaign.is_ok() {
+            return Err(err);
+        }
+        error!("Could not write the manifest: {err:#}");
+    }
+    campaign
+}
+
+/// Runs the fuzzing campaign, writing its output to `output`.
+fn run_campaign(config: &'static Configuration, output: &OutputPaths) -> Result<()> {
+    let report_path = config.report.then(|| output.reports.clone());
+
+    let mut api = crate::openapi::get_target_api_spec(config)?;
+    let filter = Arc::new(OperationFilter::apply(
+        &mut api,
+        &config.include_operations,
+        &config.exclude_operations,
+    )?);
+    info!(
+        "Using seed {} (pass --seed to replay this run)",
+        config.seed
+    );
+    // Stops the target command, if any, when the campaign ends
+    let _target = Target::start(config, &api)?;
+
+    // The Monitor trait define how the fuzzer stats are reported to the user.
+    // It is shared by all workers, so it can combine their stats.
+    let monitor = Arc::new(Mutex::new(CoverageMonitor::new(|s| info!("{}", s))));
+
+    // The broker shares new corpus entries between workers
+    let broker = Broker::new(config.workers.get());
+
+    // The crash buckets are shared by all workers, so each bug is saved only a few times
+    let mut crash_buckets =
+        CrashBuckets::load(&output.crash_buckets, config.max_crashes_per_bucket)?;
+    if let Some(resume_dir) = &config.resume {
+        crate::resume::restore_crash_buckets(resume_dir, &mut crash_buckets)?;
+    }
+    let crash_buckets = Arc::new(Mutex::new(crash_buckets));
+
+    // The initial corpus is generated once for the workers that do not resume a saved state
+    let fresh_worker = (0..config.workers.get()).any(|worker| {
+        worker_resume_dir(config, worker)
+            .is_none_or(|resume_dir| !crate::resume::has_state(&resume_dir))
+    });
+    let initial_inputs = if fresh_worker {
+        crate::initial_corpus::initial_inputs(
+            &api,
+            config.initial_corpus.as_deref(),
+            &report_path.as_deref(),
+            config.seed,
+        )
+    } else {
+        Vec::new()
+    };
+
+    let endpoint_coverage_clients = if config.workers.get() == 1 {
+        vec![fuzz_worker(
+            0,
+            config,
+            &api,
+            &filter,
+            output,
+            &monitor,
+            &broker,
+            &crash_buckets,
+            &initial_inputs,
+        )?]
+    } else {
+        info!("Starting {} workers", config.workers);
+        std::thread::scope(|scope| {
+            let workers = (0..config.workers.get())
+                .map(|worker| {
+                    let (api, filter, output, monitor, broker, crash_buckets, initial_inputs) = (
+                        &api,
+                        &filter,
+                        output,
+                        &monitor,
+                        &broker,
+                        &crash_buckets,
+                        &initial_inputs,
+                    );
+                    std::thread::Builder::new()
+                        .name(format!("worker_{worker}"))
+                        .stack_size(WORKER_STACK_SIZE)
+                        .spawn_scoped(scope, move || {
+                            fuzz_worker(
+                                worker,
+                                config,
+                                api,
+                                filter,
+                                output,
+                                monitor,
+                                broker,
+                                crash_buckets,
+                                initial_inputs,
+                            )
+                            .with_context(|| format!("Error in worker {worker}"))
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("Worker thread panicked"))
+                .collect::<Result<Vec<_>>>()
+        })?
+    };
+    log::info!("[Fuzzing campaign ended] Thanks for using WuppieFuzz!");
+    crash_buckets.lock().unwrap().save()?;
+
+    if let Some(report_path) = report_path {
+        // Combine the endpoint coverage of all workers into a single report
+        let (first, others) = endpoint_coverage_clients
+            .split_first()
+            .expect("There is always at least one worker");
+        for other in others {
+            first.lock().unwrap().merge(&other.lock().unwrap());
+        }
+        first.generate_coverage_report(&report_path);
+    }
+
+    Ok(())
+}
 
-    let api = crate::openapi::get_api_spec(config.openapi_spec.as_ref().unwrap())?;
+/// The directory the state of a worker is saved to and resumed from, if any.
+fn worker_resume_dir(config: &Configuration, worker: usize) -> Option<PathBuf> {
+    config.resume.as_ref().map(|resume_dir| match worker {
+        0 => resume_dir.clone(),
+        _ => resume_dir.join(format!("worker_{worker}")),
+    })
+}
 
-    // The Monitor trait define how the fuzzer stats are reported to the user
-    let mon = CoverageMonitor::new(|s| info!("{}", s));
+/// Runs a single fuzzing worker until the configured timeout is reached, or until a (ctrl-c)
+/// interrupt is caught. Returns the endpoint coverage client of the worker, so the coverage
+/// of all workers can be combined into a single report.
+///
+/// Each worker has its own state, executor, HTTP client and coverage clients. Only the first
+/// worker writes reports; the broker is used to share new corpus entries with the others.
+/// Without a saved state to resume, the first worker imports the `initial_inputs` into the
+/// queue, while the others only keep the initial inputs that are interesting to them.
+#[allow(clippy::too_many_arguments)]
+fn fuzz_worker<M: Monitor>(
+    worker: usize,
+    config: &'static Configuration,
+    api: &OpenAPI,
+    filter: &Arc<OperationFilter>,
+    output: &OutputPaths,
+    monitor: &Arc<Mutex<M>>,
+    broker: &Broker,
+    crash_buckets: &Arc<Mutex<CrashBuckets>>,
+    initial_inputs: &[OpenApiInput],
+) -> Result<Arc<Mutex<EndpointCoverageClient>>> {
+    let report_path = &(config.report && worker == 0).then(|| output.reports.clone());
+    let resume_dir = worker_resume_dir(config, worker);
 
     // The event manager handle the various events generated during the fuzzing loop
     // such as the notification of the addition of a new item to the corpus
-    let mut mgr = SimpleEventManager::new(mon);
+    let mut mgr = SimpleEventManager::new(WorkerMonitor::new(worker, Arc::clone(monitor)));
 
     // Set up endpoint coverage
     let (mut endpoint_coverage_client, endpoint_coverage_observer, endpoint_coverage_feedback) =
-        setup_endpoint_coverage(*api.clone())?;
+        setup_endpoint_coverage(api.clone())?;
 
     let (mut code_coverage_client, code_coverage_observer, code_coverage_feedback) =
-        setup_line_coverage(config, &report_path)?;
+        setup_line_coverage(config, report_path)?;
 
     // Create an observation channel to keep track of the execution time
     let time_observer = TimeObserver::new("time");
 
     let calibration = CalibrationStage::new(&code_coverage_feedback);
 
-    let mut collective_feedback = feedback_or!(
-        endpoint_coverage_feedback,
-        code_coverage_feedback,
-        TimeFeedback::new(&time_observer), // Time feedback, this one does not need a feedback state
+    let mut collective_feedback = feedback_and_fast!(
+        // Crashes whose bucket is full are not saved, but do not belong in the corpus either
+        feedback_not!(CrashFeedback::new()),
+        feedback_or!(
+            endpoint_coverage_feedback,
+            code_coverage_feedback,
+            TimeFeedback::new(&time_observer), // Time feedback, this one does not need a feedback state
+        )
     );
 
     // A feedback to choose if an input is a solution or not
-    let mut objective = CrashFeedback::new();
-
-    // Initialize corpus normally.
-    let initial_corpus = crate::initial_corpus::initialize_corpus(
-        &api,
-        config.initial_corpus.as_deref(),
-        &report_path.as_deref(),
+    let mut objective = feedback_and_fast!(
+        feedback_or!(
+            CrashFeedback::new(),
+            slow_request_objective(),
+            large_response_objective(),
+            crash_rule_objective()
+        ),
+        CrashBucketFeedback::new(Arc::clone(crash_buckets)),
+        TranscriptFeedback
     );
 
-    // Needed to force load corpus
-    let initial_corpus_cloned = initial_corpus.clone();
-
-    // Create a State from scratch
-    let mut state = OpenApiFuzzerState::new(
-        // RNG
-        StdRand::with_seed(current_nanos()),
-        // Corpus that will be evolved, we keep it in memory for performance
-        initial_corpus,
-        // Corpus in which we store solutions (crashes in this example),
-        // on disk so the user can get them after stopping the fuzzer
-        OnDiskCorpus::new(PathBuf::from("./crashes")).unwrap(),
-        // States of the feedbacks.
-        // They are the data related to the feedbacks that you want to persist in the State.
-        &mut collective_feedback,
-        &mut objective,
-        *api.clone(),
-    )?;
+    // When resuming, the saved state replaces both the initial corpus and the fresh state.
+    // It continues with the specification and the output directories of this run.
+    let resumed_state = match &resume_dir {
+        Some(resume_dir) => {
+            let mut resumed_state = crate::resume::load_state(resume_dir)?;
+            if let Some(state) = &mut resumed_state {
+                info!("Resuming campaign from {}", resume_dir.display());
+                crate::resume::relocate_state(state, api.clone(), &output.queue, &output.crashes)?;
+                crate::resume::restore_coverage(
+                    resume_dir,
+                    &mut endpoint_coverage_client,
+                    code_coverage_client.as_mut(),
+                )?;
+            }
+            resumed_state
+        }
+        None => None,
+    };
+
+    let (mut state, initial_corpus_cloned) = match resumed_state {
+        Some(state) => (state, None),
+        None => {
+            // Only the first worker writes the initial corpus to the queue
+            let initial_corpus = match worker {
+                0 => {
+                    crate::initial_corpus::initialize_corpus(initial_inputs.to_vec(), &output.queue)
+                }
+                _ => InMemoryOnDiskCorpus::new(&output.queue)?,
+            };
+
+            // Needed to force load corpus
+            let initial_corpus_cloned = initial_corpus.clone();
+
+            // Create a State from scratch
+            let state = OpenApiFuzzerState::new(
+                // RNG, seeded differently for each worker so they explore different inputs
+                StdRand::with_seed(config.seed.wrapping_add(worker as u64)),
+                // Corpus that will be evolved, we keep it in memory for performance
+                initial_corpus,
+                // Corpus in which we store solutions (crashes in this example),
+                // on disk so the user can get them after stopping the fuzzer
+                OnDiskCorpus::new(&output.crashes).unwrap(),
+                // States of the feedbacks.
+                // They are the data related to the feedbacks that you want to persist in the State.
+                &mut collective_feedback,
+                &mut objective,
+                api.clone(),
+            )?;
+            (state, Some(initial_corpus_cloned))
+        }
+    };
 
     // Safety: libafl wants to read the coverage map directly that we also update in the harness;
     // this is only possible if it does not touch the map while the harness is running. We must
@@ -147,20 +358,30 @@ pub fn fuzz() -> Result<()> {
         time_observer
     );
 
-    let mutator_openapi = StdScheduledMutator::new(havoc_mutations_openapi());
+    let mutator_openapi = StdScheduledMutator::new(havoc_mutations_openapi(
+        filter.clone(),
+        config.method_mutation_strategy,
+    ));
 
     // The order of the stages matter!
-    let power = StdPowerMutationalStage::new(mutator_openapi);
+    let power = ConcurrentMutationalStage::new(mutator_openapi, config.concurrent_sequences);
     let mut stages = tuple_list!(calibration, power);
 
     // Create the executor for an in-process function with just one observer
     let mut executor = SequenceExecutor::new(
         collective_observer,
-        &api,
+        api,
+        filter,
         config,
         code_coverage_client,
         endpoint_coverage_client.clone(),
+        Arc::clone(crash_buckets),
+        output,
     )?;
+    // A resumed campaign continues with its baselines as well
+    if let (Some(resume_dir), None) = (&resume_dir, &initial_corpus_cloned) {
+        executor.restore_baselines(resume_dir)?;
+    }
 
     // Fire an event to print the initial corpus size
     let corpus_size = state.corpus().count();
@@ -179,53 +400,130 @@ pub fn fuzz() -> Result<()> {
         error!("Err: failed to fire event{:?}", e)
     }
 
-    // Executed every corpus entry at least once for gathering a proper view on the initial coverage as mutations
+    // Executed every corpus entry at least once for gathering a proper view on the initial coverage as mutations.
+    // A resumed campaign has already done so.
     log::debug!("Start initial corpus loop");
-    for input_id in initial_corpus_cloned.ids() {
-        let input = initial_corpus_cloned
-            .cloned_input_for_id(input_id)
-            .expect("Failed to load input");
-        executor.run_target(&mut fuzzer, &mut state, &mut mgr, &input)?;
-        fuzzer.process_execution(
-            &mut state,
-            &mut mgr,
-            &input,
-            &ExecuteInputResult::None,
-            executor.observers_mut().deref_mut(),
-        )?;
+    if let Some(initial_corpus_cloned) = initial_corpus_cloned {
+        for input_id in initial_corpus_cloned.ids() {
+            let input = initial_corpus_cloned
+                .cloned_input_for_id(input_id)
+                .expect("Failed to load input");
+            executor.run_target(&mut fuzzer, &mut state, &mut mgr, &input)?;
+            fuzzer.process_execution(
+                &mut state,
+                &mut mgr,
+                &input,
+                &ExecuteInputResult::None,
+                executor.observers_mut().deref_mut(),
+            )?;
+        }
+        // The other workers start with an empty corpus, and evaluate the initial inputs like
+        // the entries they receive from the broker
+        if worker > 0 {
+            for input in initial_inputs {
+                match fuzzer.evaluate_input(&mut state, &mut executor, &mut mgr, input.clone()) {
+                    Ok(_) => (),
+                    Err(libafl_bolts::Error::ShuttingDown) => break,
+                    Err(err) => return Err(err).context("Error evaluating an initial input"),
+                }
+            }
+            // Fuzzing needs at least one corpus entry
+            if let (0, Some(input)) = (state.corpus().count(), initial_inputs.first()) {
+                fuzzer.add_input(&mut state, &mut executor, &mut mgr, input.clone())?;
+            }
+        }
     }
 
+    // Corpus entries up to here are known to all workers, or were published before a resume
+    let mut shared_corpus_size = state.corpus().count();
+
     log::debug!("Start fuzzing loop");
-    loop {
-        match fuzzer.fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr) {
-            Ok(_) => (),
-            Err(libafl_bolts::Error::ShuttingDown) => {
-                log::info!("[Fuzzing campaign ended] Thanks for using WuppieFuzz!");
-                break;
+    let mut last_save = Instant::now();
+    // The crash buckets are shared, so the first worker saves them in the resume directory of
+    // the campaign
+    let shared_buckets = (worker == 0).then_some(&**crash_buckets);
+    let fuzzing = (|| -> Result<()> {
+        loop {
+            match fuzzer.fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr) {
+                Ok(_) => (),
+                Err(libafl_bolts::Error::ShuttingDown) => return Ok(()),
+                Err(err) => {
+                    return Err(err).context("Error in the fuzz loop");
+                }
+            };
+            if config.workers.get() > 1 {
+                // Share new corpus entries with the other workers
+                for input_id in state.corpus().ids().skip(shared_corpus_size) {
+                    broker.publish(worker, state.corpus().cloned_input_for_id(input_id)?);
+                }
+                // Evaluate the entries the other workers found; they are only added to our own
+                // corpus if they are interesting for this worker as well.
+                for input in broker.receive(worker) {
+                    match fuzzer.evaluate_input(&mut state, &mut executor, &mut mgr, input) {
+                        Ok(_) => (),
+                        Err(libafl_bolts::Error::ShuttingDown) => return Ok(()),
+                        Err(err) => return Err(err).context("Error evaluating a shared input"),
+                    }
+                }
+                shared_corpus_size = state.corpus().count();
             }
-            Err(err) => {
-                return Err(err).context("Error in the fuzz loop");
+            // send update of execution data to the monitor
+            let executions = *state.executions();
+            if let Err(e) = mgr.fire(
+                &mut state,
+                Event::UpdateExecStats {
+                    time: current_time(),
+                    executions,
+                    phantom: PhantomData,
+                },
+            ) {
+                error!("Err: failed to fire event{:?}", e)
+            }
+            // Save now and then, so a campaign that is killed can be resumed as well
+            if let Some(resume_dir) = &resume_dir {
+                if last_save.elapsed() >= RESUME_SAVE_INTERVAL {
+                    save_campaign(resume_dir, &state, &executor, shared_buckets)?;
+                    last_save = Instant::now();
+                }
             }
-        };
-        // send update of execution data to the monitor
-        let executions = *state.executions();
-        if let Err(e) = mgr.fire(
-            &mut state,
-            Event::UpdateExecStats {
-                time: current_time(),
-                executions,
-                phantom: PhantomData,
-            },
-        ) {
-            error!("Err: failed to fire event{:?}", e)
+        }
+    })();
+
+    // The campaign is saved when it ends, also when it ends in an error
+    if let Some(resume_dir) = &resume_dir {
+        if let Err(save_err) = save_campaign(resume_dir, &state, &executor, shared_buckets) {
+            if fuzzing.is_ok() {
+                return Err(save_err);
+            }
+            error!("Could not save the campaign state: {save_err:#}");
         }
     }
+    fuzzing?;
 
     if let Some(report_path) = report_path {
-        executor.generate_coverage_report(&report_path);
+        executor.generate_code_coverage_report(report_path);
     }
 
-    Ok(())
+    Ok(endpoint_coverage_client)
+}
+
+/// Saves the fuzzer state, the cumulative coverage and the baselines of a worker to its
+/// resume directory, along with the crash buckets if given.
+fn save_campaign<OT>(
+    resume_dir: &Path,
+    state: &FuzzerState,
+    executor: &SequenceExecutor<'_, OT>,
+    crash_buckets: Option<&Mutex<CrashBuckets>>,
+) -> Result<()>
+where
+    OT: ObserversTuple<OpenApiInput, FuzzerState>,
+{
+    info!("Saving campaign state to {}", resume_dir.display());
+    crate::resume::save_state(resume_dir, state)?;
+    if let Some(crash_buckets) = crash_buckets {
+        crate::resume::save_crash_buckets(resume_dir, &crash_buckets.lock().unwrap())?;
+    }
+    executor.save_progress(resume_dir)
 }
 
 /// Sets up the endpoint coverage client according to the configuration, and initializes it
@@ -307,15 +605,3 @@ fn setup_line_coverage<'a>(
         code_coverage_feedback,
     ))
 }
-
-/// Creates and returns the report path for this run. It is typically of the form
-/// `reports/2023-06-13T105302.602Z`, the filename being an ISO 8601 timestamp.
-fn generate_report_path() -> PathBuf {
-    let timestamp = format!(
-        "{}",
-        chrono::offset::Utc::now().format("%Y-%m-%dT%H%M%S%.3fZ")
-    );
-    let report_path = PathBuf::from("reports").join(timestamp);
-    create_dir_all(&report_path).expect("unable to make reports directory");
-    report_path
-}
@@ -0,0 +1,751 @@
+//! Conversion between HAR files (HTTP Archives, as recorded by browsers) and inputs.
+//!
+//! `wuppiefuzz import-har` turns the entries of a HAR file into a request sequence, which
+//! is saved in a corpus directory. Each entry is matched to an operation of the specification
+//! by its method and path; entries that match no operation (such as static files) are
+//! skipped. The path, query, header and cookie parameters of the operation and the body of
+//! the request are taken from the entry. A value that an earlier entry returned (in a field of
+//! its JSON body or as a cookie) or posted becomes a reference to that earlier request, so the
+//! fuzzer fills in the value that the target returns when the sequence is sent.
+//!
+//! `wuppiefuzz export-har` does the reverse for a crash or corpus entry. If a transcript was
+//! saved with the input (see [`crate::transcript`]), the requests and responses of the
+//! transcript are exported, with the references filled in. Otherwise the requests of the
+//! input are exported without responses, with a placeholder such as `{request0.id}` for every
+//! reference.
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use libafl::inputs::Input;
+use openapiv3::{OpenAPI, Operation, ParameterData, ParameterSchemaOrContent, SchemaKind, Type};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    configuration::Configuration,
+    input::{
+        media_type::MediaTypeHeaders,
+        parameter::{ParameterKind, SimpleValue},
+        pointer, Body, Method, OpenApiInput, OpenApiRequest, ParameterContents,
+    },
+    openapi::{
+        build_request::build_request_from_input, find_operation, xml::parse_response, XmlContent,
+    },
+    parameter_feedback::ParameterFeedback,
+    transcript::{TranscriptEntry, TranscriptMetadata},
+};
+
+/// A HAR file. Only the parts that WuppieFuzz uses are read, and missing parts are empty.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HarLog {
+    pub version: String,
+    pub creator: HarCreator,
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HarEntry {
+    pub started_date_time: String,
+    /// Total time of the request in milliseconds
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    pub cache: Value,
+    pub timings: HarTimings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub cookies: Vec<HarNameValue>,
+    pub headers: Vec<HarNameValue>,
+    pub query_string: Vec<HarNameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HarResponse {
+    /// Status of the response, or 0 if there was none
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    pub cookies: Vec<HarNameValue>,
+    pub headers: Vec<HarNameValue>,
+    pub content: HarContent,
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HarNameValue {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HarPostData {
+    pub mime_type: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<HarNameValue>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HarContent {
+    pub size: i64,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HarTimings {
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+impl HarNameValue {
+    fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+/// Finds the value with the given name, ignoring case.
+fn find_value<'a>(values: &'a [HarNameValue], name: &str) -> Option<&'a str> {
+    values
+        .iter()
+        .find(|value| value.name.eq_ignore_ascii_case(name))
+        .map(|value| value.value.as_str())
+}
+
+/// Converts a HAR file to an input, and saves it in the corpus directory under the name of
+/// the HAR file.
+pub fn import_to_corpus(api: &OpenAPI, har_file: &Path, corpus_dir: &Path) -> Result<()> {
+    let har: Har = serde_json::from_slice(
+        &fs::read(har_file).with_context(|| format!("Could not read {}", har_file.display()))?,
+    )
+    .with_context(|| format!("{} is not a valid HAR file", har_file.display()))?;
+    let input = import(api, &har);
+    if input.0.is_empty() {
+        bail!(
+            "None of the {} entries of {} match an operation in the specification",
+            har.log.entries.len(),
+            har_file.display()
+        );
+    }
+    fs::create_dir_all(corpus_dir)?;
+    let name = har_file
+        .file_stem()
+        .map_or("har".into(), |stem| stem.to_string_lossy());
+    let input_file = corpus_dir.join(format!("{name}.yaml"));
+    input.to_file(&input_file)?;
+    println!(
+        "Imported {} of the {} entries of {} as {}",
+        input.0.len(),
+        har.log.entries.len(),
+        har_file.display(),
+        input_file.display()
+    );
+    Ok(())
+}
+
+/// Converts the entries of a HAR file to a request sequence. Entries that match no operation
+/// of the specification are skipped.
+pub fn import(api: &OpenAPI, har: &Har) -> OpenApiInput {
+    let mut requests = Vec::new();
+    // The values returned and posted by the requests so far
+    let mut returned_values = ParameterFeedback::new(har.log.entries.len());
+    for entry in &har.log.entries {
+        let Some(mut request) = import_request(api, &entry.request) else {
+            log::debug!(
+                "Skipping {} {}, which matches no operation",
+                entry.request.method,
+                entry.request.url
+            );
+            continue;
+        };
+        let request_index = requests.len();
+        // The posted values are those of the HAR file, not the references to them
+        returned_values.process_post_request(request_index, request.clone());
+        insert_references(&mut request, request_index, &returned_values);
+
+        if let Some(body) = (200..300)
+            .contains(&entry.response.status)
+            .then_some(entry.response.content.text.as_deref())
+            .flatten()
+            .and_then(|text| serde_json::from_str(text).ok())
+        {
+            returned_values.process_json(request_index, body);
+        }
+        for cookie in &entry.response.cookies {
+            returned_values.set(
+                request_index,
+                cookie.name.clone(),
+                Value::String(cookie.value.clone()),
+            );
+        }
+        requests.push(request);
+    }
+    OpenApiInput(requests)
+}
+
+/// Converts a HAR request to a request for the operation it matches, if any.
+fn import_request(api: &OpenAPI, har_request: &HarRequest) -> Option<OpenApiRequest> {
+    let method = Method::try_from(har_request.method.as_str()).ok()?;
+    let url = url::Url::parse(&har_request.url).ok()?;
+    let (path, path_values) = match_operation(api, method, url.path())?;
+    let operation = find_operation(api, path, method)?;
+
+    let mut parameters = IndexMap::new();
+    for parameter in operation
+        .parameters
+        .iter()
+        .filter_map(|ref_or_parameter| ref_or_parameter.resolve(api).ok())
+    {
+        let kind = ParameterKind::from(parameter);
+        let name = &parameter.data.name;
+        let value = match kind {
+            ParameterKind::Path => path_values
+                .iter()
+                .find(|(path_name, _)| path_name == name)
+                .map(|(_, value)| value.clone()),
+            ParameterKind::Query => url
+                .query_pairs()
+                .find(|(query_name, _)| query_name == name)
+                .map(|(_, value)| value.into_owned()),
+            ParameterKind::Header => find_value(&har_request.headers, name).map(str::to_owned),
+            ParameterKind::Cookie => find_value(&har_request.cookies, name)
+                .map(str::to_owned)
+                .or_else(|| request_cookie(har_request, name)),
+            ParameterKind::Body => None,
+        };
+        if let Some(value) = value {
+            parameters.insert(
+                (name.clone(), kind),
+                ParameterContents::from(typed_value(api, &parameter.data, &value)),
+            );
+        }
+    }
+
+    let body = har_request
+        .post_data
+        .as_ref()
+        .map(|post_data| import_body(api, operation, post_data))
+        .map_or(Body::Empty, |contents| {
+            Body::build(api, operation, Some(contents))
+        });
+    Some(OpenApiRequest {
+        method,
+        path: path.to_owned(),
+        body,
+        parameters,
+        media_type_headers: MediaTypeHeaders::default(),
+    })
+}
+
+/// Finds the path of the operation that the given path of a URL belongs to, and the values of
+/// its path parameters. The path of the URL may start with a base path, such as `/api/v1`.
+/// If several operations match, the one with the most fixed segments is chosen.
+fn match_operation<'a>(
+    api: &'a OpenAPI,
+    method: Method,
+    url_path: &str,
+) -> Option<(&'a str, Vec<(String, String)>)> {
+    let segments: Vec<String> = url_path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| urlencoding::decode(segment).map_or(segment.to_owned(), |s| s.into_owned()))
+        .collect();
+    api.operations()
+        .filter(|&(_, operation_method, _, _)| method == operation_method)
+        .filter_map(|(path, _, _, _)| {
+            let template: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+            let base = segments.len().checked_sub(template.len())?;
+            let mut values = Vec::new();
+            let mut fixed = 0;
+            for (pattern, segment) in template.iter().zip(&segments[base..]) {
+                match (pattern.find('{'), pattern.rfind('}')) {
+                    (Some(start), Some(end)) if start < end => {
+                        let (prefix, suffix) = (&pattern[..start], &pattern[end + 1..]);
+                        let value = segment
+                            .strip_prefix(prefix)
+                            .and_then(|rest| rest.strip_suffix(suffix))
+                            .filter(|value| !value.is_empty())?;
+                        values.push((pattern[start + 1..end].to_owned(), value.to_owned()));
+                    }
+                    _ if pattern.eq_ignore_ascii_case(segment) => fixed += 1,
+                    _ => return None,
+                }
+            }
+            Some((fixed, std::cmp::Reverse(base), path, values))
+        })
+        .max_by_key(|(fixed, base, _, _)| (*fixed, *base))
+        .map(|(_, _, path, values)| (path, values))
+}
+
+/// Finds a cookie in the Cookie header of a request.
+fn request_cookie(har_request: &HarRequest, name: &str) -> Option<String> {
+    find_value(&har_request.headers, "cookie")?
+        .split(';')
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(cookie_name, _)| *cookie_name == name)
+        .map(|(_, value)| value.to_owned())
+}
+
+/// Interprets the value of a parameter according to its type in the specification.
+fn typed_value(api: &OpenAPI, data: &ParameterData, value: &str) -> Value {
+    let kind = match &data.format {
+        ParameterSchemaOrContent::Schema(schema) => Some(&schema.resolve(api).kind),
+        ParameterSchemaOrContent::Content(_) => None,
+    };
+    match kind {
+        Some(SchemaKind::Type(Type::Integer(_) | Type::Number(_))) => {
+            value.parse().ok().map(Value::Number)
+        }
+        Some(SchemaKind::Type(Type::Boolean { .. })) => value.parse().ok().map(Value::Bool),
+        _ => None,
+    }
+    .unwrap_or_else(|| Value::String(value.to_owned()))
+}
+
+/// Converts the body of a HAR request to the contents of a request body. The fields of
+/// multipart bodies are taken from the params of the entry, since HAR files leave out the
+/// contents of uploaded files.
+fn import_body(api: &OpenAPI, operation: &Operation, post_data: &HarPostData) -> ParameterContents {
+    let mime_type = &post_data.mime_type;
+    // XML is read back according to the schema of the body
+    if mime_type.contains("xml") {
+        let xml_value = operation
+            .request_body
+            .as_ref()
+            .and_then(|body| body.resolve(api).ok())
+            .and_then(|body| body.content.get_xml_content())
+            .and_then(|media_type| media_type.schema.as_ref())
+            .and_then(|schema| parse_response(api, schema, &post_data.text).ok());
+        if let Some(value) = xml_value {
+            return ParameterContents::from(value);
+        }
+    }
+    if mime_type.starts_with("application/x-www-form-urlencoded")
+        || mime_type.starts_with("multipart/form-data")
+    {
+        let fields: IndexMap<String, ParameterContents> = if post_data.params.is_empty()
+            && mime_type.starts_with("application/x-www-form-urlencoded")
+        {
+            url::form_urlencoded::parse(post_data.text.as_bytes())
+                .map(|(name, value)| (name.into_owned(), value.into_owned().into()))
+                .collect()
+        } else {
+            post_data
+                .params
+                .iter()
+                .map(|param| (param.name.clone(), param.value.clone().into()))
+                .collect()
+        };
+        return ParameterContents::Object(fields);
+    }
+    serde_json::from_str::<Value>(&post_data.text)
+        .map_or_else(|_| post_data.text.clone().into(), ParameterContents::from)
+}
+
+/// Replaces values of a request that earlier requests returned or posted by references.
+/// Like references in the fuzzer, this covers the parameters, and the body or its fields at
+/// any depth.
+fn insert_references(
+    request: &mut OpenApiRequest,
+    request_index: usize,
+    returned_values: &ParameterFeedback,
+) {
+    let insert_reference =
+        |name: &str, target_pointer: Option<String>, contents: &mut ParameterContents| {
+            let ParameterContents::LeafValue(value) = contents else {
+                return;
+            };
+            if matches!(value, SimpleValue::Null | SimpleValue::Bool(_)) {
+                return;
+            }
+            if let Some((index, parameter_name, source_pointer)) =
+                returned_values.find_reference(request_index, name, &value.to_value())
+            {
+                *contents = ParameterContents::Reference {
+                    request_index: index,
+                    parameter_name,
+                    source_pointer,
+                    target_pointer,
+                };
+            }
+        };
+    for ((name, _), contents) in &mut request.parameters {
+        insert_reference(name, None, contents);
+    }
+    // Values in the body are named after the field they are in, at any depth
+    for (location, contents) in request.body.leaves_mut() {
+        insert_reference(
+            &pointer::field_name(&location),
+            Some(location.clone()),
+            contents,
+        );
+    }
+}
+
+/// Exports a crash or corpus entry as a HAR file. Uses the transcript saved with it if there is
+/// one, and the settings of the configuration to build the requests.
+pub fn export(input_file: &Path, har_file: &Path) -> Result<()> {
+    let config = Configuration::get().map_err(anyhow::Error::msg)?;
+    crate::setup_logging(config);
+    let api = crate::openapi::get_target_api_spec(config)?;
+    let input = OpenApiInput::from_file(input_file)
+        .with_context(|| format!("Could not read the input {}", input_file.display()))?;
+
+    let entries = match load_transcript(input_file) {
+        Some(transcript) => {
+            log::info!("Exporting the transcript of {}", input_file.display());
+            transcript
+                .requests
+                .iter()
+                .filter_map(|entry| export_transcript_entry(&api, entry))
+                .collect()
+        }
+        None => {
+            log::info!(
+                "No transcript was saved with {}, exporting its requests without responses",
+                input_file.display()
+            );
+            export_requests(&api, &input)
+        }
+    };
+    let har = Har {
+        log: HarLog {
+            version: "1.2".to_owned(),
+            creator: HarCreator {
+                name: "WuppieFuzz".to_owned(),
+                version: env!("CARGO_PKG_VERSION").to_owned(),
+            },
+            entries,
+        },
+    };
+    fs::write(har_file, serde_json::to_string_pretty(&har)?)
+        .with_context(|| format!("Could not write {}", har_file.display()))?;
+    println!(
+        "Exported {} requests of {} to {}",
+        har.log.entries.len(),
+        input_file.display(),
+        har_file.display()
+    );
+    Ok(())
+}
+
+/// Loads the transcript from the metadata file that LibAFL saved next to an input.
+fn load_transcript(input_file: &Path) -> Option<TranscriptMetadata> {
+    let name = input_file.file_name()?.to_string_lossy();
+    let metadata_file = input_file.with_file_name(format!(".{name}.metadata"));
+    let metadata: Value = serde_json::from_slice(&fs::read(metadata_file).ok()?).ok()?;
+    // Each metadata is saved as [type id, contents]
+    metadata["metadata"]["map"]
+        .as_object()?
+        .values()
+        .find_map(|entry| serde_json::from_value(entry.get(1)?.clone()).ok())
+}
+
+/// Converts a request of a transcript, and its response, to a HAR entry.
+fn export_transcript_entry(api: &OpenAPI, entry: &TranscriptEntry) -> Option<HarEntry> {
+    let request: OpenApiRequest = serde_yaml::from_str(&entry.request)
+        .inspect_err(|err| log::warn!("Skipping request {}: {err}", entry.request_index))
+        .ok()?;
+    let mut har_entry = export_request(api, &request)?;
+    let latency = entry.latency_ms.unwrap_or_default();
+    har_entry.time = latency;
+    har_entry.timings.wait = latency;
+    if let Some(response) = &entry.response {
+        let headers: Vec<_> = response
+            .headers
+            .iter()
+            .map(|(name, value)| HarNameValue::new(name, value))
+            .collect();
+        har_entry.response = HarResponse {
+            status: response.status,
+            status_text: reqwest::StatusCode::from_u16(response.status)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+                .unwrap_or_default()
+                .to_owned(),
+            http_version: "HTTP/1.1".to_owned(),
+            content: HarContent {
+                size: response.body_length as i64,
+                mime_type: find_value(&headers, "content-type")
+                    .unwrap_or_default()
+                    .to_owned(),
+                text: Some(response.body.clone()),
+                encoding: None,
+            },
+            headers,
+            headers_size: -1,
+            body_size: response.body_length as i64,
+            ..Default::default()
+        };
+    }
+    let comments: Vec<_> = [&entry.error, &entry.validation_error]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    har_entry.comment = (!comments.is_empty()).then(|| comments.join("\n"));
+    Some(har_entry)
+}
+
+/// Converts the requests of an input to HAR entries without responses. References are
+/// replaced by placeholders, since the values they refer to are unknown.
+fn export_requests(api: &OpenAPI, input: &OpenApiInput) -> Vec<HarEntry> {
+    let mut placeholders = ParameterFeedback::new(input.0.len());
+    for (_, reference) in input.references() {
+        let ParameterContents::Reference {
+            request_index,
+            parameter_name,
+            source_pointer,
+            ..
+        } = reference
+        else {
+            continue;
+        };
+        let placeholder = format!(
+            "{{request{request_index}.{}}}",
+            source_pointer.as_ref().unwrap_or(parameter_name)
+        );
+        match source_pointer {
+            Some(pointer) => {
+                placeholders.set_pointer(*request_index, pointer.clone(), placeholder.into())
+            }
+            None => placeholders.set(*request_index, parameter_name.clone(), placeholder.into()),
+        };
+    }
+    input
+        .0
+        .iter()
+        .enumerate()
+        .filter_map(|(request_index, request)| {
+            let mut request = request.clone();
+            if let Err(err) = request.resolve_parameter_references(&placeholders) {
+                log::warn!("Skipping request {request_index}: {err}");
+                return None;
+            }
+            export_request(api, &request)
+        })
+        .collect()
+}
+
+/// Builds a request as it would be sent, and converts it to a HAR entry without response.
+/// Authentication and the static headers are not included, since they are added when a
+/// request is sent.
+fn export_request(api: &OpenAPI, request: &OpenApiRequest) -> Option<HarEntry> {
+    let client = reqwest::blocking::Client::new();
+    let cookie_store = std::sync::Arc::default();
+    let built = build_request_from_input(&client, &cookie_store, api, request)?
+        .build()
+        .inspect_err(|err| log::warn!("Could not build {} {}: {err}", request.method, request.path))
+        .ok()?;
+
+    let mut headers: Vec<_> = built
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            HarNameValue::new(name.as_str(), String::from_utf8_lossy(value.as_bytes()))
+        })
+        .collect();
+    let cookies: Vec<_> = cookie_store
+        .lock()
+        .unwrap()
+        .get_request_values(built.url())
+        .map(|(name, value)| HarNameValue::new(name, value))
+        .collect();
+    if !cookies.is_empty() {
+        let header = cookies
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        headers.push(HarNameValue::new("cookie", header));
+    }
+    let body = built.body().and_then(|body| body.as_bytes());
+    let post_data = body.map(|body| HarPostData {
+        mime_type: request.content_type().unwrap_or_default().into_owned(),
+        text: String::from_utf8_lossy(body).into_owned(),
+        params: Vec::new(),
+    });
+    Some(HarEntry {
+        started_date_time: chrono::Utc::now().to_rfc3339(),
+        request: HarRequest {
+            method: built.method().to_string(),
+            url: built.url().to_string(),
+            http_version: "HTTP/1.1".to_owned(),
+            cookies,
+            headers,
+            query_string: built
+                .url()
+                .query_pairs()
+                .map(|(name, value)| HarNameValue::new(name, value))
+                .collect(),
+            post_data,
+            headers_size: -1,
+            body_size: body.map_or(0, |body| body.len() as i64),
+        },
+        cache: Value::Object(Default::default()),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{import, Har, ParameterFeedback};
+    use crate::input::{parameter::ParameterKind, Body, ParameterContents};
+
+    const SPEC: &str = r#"
+openapi: 3.0.0
+info: {title: Items, version: "1"}
+servers: [{url: "http://localhost:8080/api"}]
+paths:
+  /items:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema: {type: object}
+      responses: {"201": {description: Created}}
+  /items/{id}:
+    get:
+      parameters:
+        - {name: id, in: path, required: true, schema: {type: integer}}
+        - {name: verbose, in: query, schema: {type: boolean}}
+      responses: {"200": {description: Item}}
+  /items/new:
+    get:
+      responses: {"200": {description: Template}}
+"#;
+
+    #[test]
+    fn test_import() {
+        let api: openapiv3::OpenAPI = serde_yaml::from_str(SPEC).unwrap();
+        let har: Har = serde_json::from_str(
+            r#"{"log": {"entries": [
+                {"request": {"method": "GET", "url": "http://localhost:8080/static/app.js"}},
+                {"request": {"method": "POST", "url": "http://localhost:8080/api/items",
+                    "postData": {"mimeType": "application/json", "text": "{\"name\": \"pen\"}"}},
+                 "response": {"status": 201,
+                    "content": {"mimeType": "application/json", "text": "{\"id\": 1234}"}}},
+                {"request": {"method": "GET", "url": "http://localhost:8080/api/items/1234?verbose=true"}},
+                {"request": {"method": "GET", "url": "http://localhost:8080/api/items/new"}}
+            ]}}"#,
+        )
+        .unwrap();
+        let input = import(&api, &har);
+        assert_eq!(input.0.len(), 3);
+        assert!(
+            matches!(&input.0[0].body, Body::ApplicationJson(ParameterContents::Object(fields)) if fields.len() == 1)
+        );
+
+        let get = &input.0[1];
+        assert_eq!(get.path, "/items/{id}");
+        assert!(matches!(
+            &get.parameters[&("id".to_owned(), ParameterKind::Path)],
+            ParameterContents::Reference { request_index: 0, parameter_name, source_pointer: None, .. }
+                if parameter_name == "id"
+        ));
+        assert_eq!(
+            get.parameters[&("verbose".to_owned(), ParameterKind::Query)].to_value(),
+            serde_json::Value::Bool(true)
+        );
+        assert_eq!(input.0[2].path, "/items/new");
+    }
+
+    #[test]
+    fn test_import_nested() {
+        let api: openapiv3::OpenAPI = serde_yaml::from_str(SPEC).unwrap();
+        let har: Har = serde_json::from_str(
+            r#"{"log": {"entries": [
+                {"request": {"method": "POST", "url": "http://localhost:8080/api/items",
+                    "postData": {"mimeType": "application/json", "text": "{\"name\": \"pen\"}"}},
+                 "response": {"status": 201, "content": {"mimeType": "application/json",
+                    "text": "{\"data\": {\"items\": [{\"id\": 7}, {\"id\": 1234}]}}"}}},
+                {"request": {"method": "GET", "url": "http://localhost:8080/api/items/1234"}},
+                {"request": {"method": "POST", "url": "http://localhost:8080/api/items",
+                    "postData": {"mimeType": "application/json",
+                        "text": "{\"parent\": {\"id\": 1234}}"}}}
+            ]}}"#,
+        )
+        .unwrap();
+        let mut input = import(&api, &har);
+
+        // Values nested in the response are referred to by a JSON Pointer
+        let nested_reference = |contents: &ParameterContents, target: Option<&str>| {
+            matches!(contents,
+                ParameterContents::Reference { request_index: 0, parameter_name, source_pointer: Some(source), target_pointer }
+                    if parameter_name == "id" && source == "/data/items/1/id" && target_pointer.as_deref() == target)
+        };
+        assert!(nested_reference(
+            &input.0[1].parameters[&("id".to_owned(), ParameterKind::Path)],
+            None
+        ));
+        // .. also from fields nested in the body, which are written to their location
+        let post = &mut input.0[2];
+        assert!(nested_reference(
+            post.body.pointer_mut("/parent/id").unwrap(),
+            Some("/parent/id")
+        ));
+        // .. which is added to the body if it is missing
+        let reference = ParameterContents::Reference {
+            request_index: 0,
+            parameter_name: "id".to_owned(),
+            source_pointer: Some("/data/items/0/id".to_owned()),
+            target_pointer: Some("/owner/id".to_owned()),
+        };
+        assert!(post.body.insert_at("/owner/id", reference));
+
+        let mut feedback = ParameterFeedback::new(3);
+        feedback.process_json(
+            0,
+            serde_json::json!({"data": {"items": [{"id": 7}, {"id": 42}]}}),
+        );
+        post.resolve_parameter_references(&feedback).unwrap();
+        assert_eq!(
+            post.body.pointer_mut("").unwrap().to_value(),
+            serde_json::json!({"parent": {"id": 42}, "owner": {"id": 7}})
+        );
+    }
+}
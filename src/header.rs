@@ -2,32 +2,16 @@
 //! specify headers that should be sent with every request the fuzzer makes,
 //! and this module parses those into a Reqwest HeaderMap.
 
-use std::{collections::HashMap, fs::File, str::FromStr};
+use std::{collections::HashMap, path::Path, str::FromStr};
 
 use anyhow::{Context, Result};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 
-use crate::configuration::Configuration;
+use crate::{configuration::Configuration, interpolation::read_yaml_file};
 
 /// Load default headers from a file specified in configuration and apply
 /// them to the given ClientBuilder
-pub fn get_default_headers() -> Result<HeaderMap> {
-    let clargs = Configuration::must_get();
-
-    // Add custom default headers from file
-    let custom_header: HashMap<String, String> = match clargs.header.as_deref() {
-        Some(header_path) => {
-            serde_yaml::from_reader(File::open(header_path).with_context(|| {
-                format!(
-                    "Failed to open default header file {}",
-                    header_path.to_string_lossy()
-                )
-            })?)
-            .with_context(|| "Failed to parse default header file as YAML")?
-        }
-        None => HashMap::new(),
-    };
-
+pub fn get_default_headers(clargs: &Configuration) -> Result<HeaderMap> {
     // Create the actual map of HeaderKeys and Values
     let mut default_headers = HeaderMap::new();
 
@@ -37,15 +21,27 @@ pub fn get_default_headers() -> Result<HeaderMap> {
         HeaderValue::from_static("wuppiefuzz/0.1.0"),
     );
 
-    // Insert custom headers from file
+    // Add custom default headers from file
+    if let Some(header_path) = clargs.header.as_deref() {
+        default_headers.extend(read_header_file(header_path)?);
+    }
+
+    Ok(default_headers)
+}
+
+/// Reads the custom headers from a yaml file mapping header names to values
+pub fn read_header_file(header_path: &Path) -> Result<HeaderMap> {
+    let custom_header: HashMap<String, String> = read_yaml_file(header_path)
+        .with_context(|| "Failed to read default header file as YAML")?;
+
+    let mut headers = HeaderMap::new();
     for (key, value) in custom_header {
-        default_headers.insert(
+        headers.insert(
             HeaderName::from_str(&key)
                 .with_context(|| format!("Can't parse {key} as header name"))?,
             HeaderValue::from_str(&value)
                 .with_context(|| format!("Can't parse {value} as header value"))?,
         );
     }
-
-    Ok(default_headers)
+    Ok(headers)
 }
@@ -7,7 +7,7 @@ mod toposort;
 /// the same meaning (edges). The dependency graph module attempts to build such a graph.
 use std::{
     cmp::Ordering,
-    collections::{hash_map::DefaultHasher, HashMap},
+    collections::hash_map::DefaultHasher,
     fmt::Display,
     fs::{create_dir_all, File},
     hash::{Hash, Hasher},
@@ -15,6 +15,7 @@ use std::{
     path::Path,
 };
 
+use indexmap::IndexMap;
 use log::warn;
 use openapiv3::{OpenAPI, StatusCode};
 use petgraph::{
@@ -33,15 +34,17 @@ use self::{
 use crate::{
     input::{parameter::ParameterKind, Method, OpenApiInput, ParameterContents},
     openapi::{
-        examples::{example_from_qualified_operation, openapi_inputs_from_ops},
+        examples::{example_from_qualified_operation, openapi_inputs_from_ops, seed_examples},
         QualifiedOperation,
     },
 };
 
 /// Returns OpenApiInputs generated from a dependency graph derived from the OpenAPI
 /// specification. If rigorously generating parameter combinations would result in
-/// too many inputs, it just generates a single example.
-pub fn initial_corpus_from_api(api: &OpenAPI) -> Vec<OpenApiInput> {
+/// too many inputs, it just generates a single example. Example values that are sampled
+/// are drawn from a generator seeded with `seed`, so the same seed gives the same inputs.
+pub fn initial_corpus_from_api(api: &OpenAPI, seed: u64) -> Vec<OpenApiInput> {
+    seed_examples(seed);
     let dependency_graph = DependencyGraph::new(api);
 
     // Turn all subgraphs into sorted lists of node indices
@@ -138,14 +141,25 @@ fn add_references_to_openapi_input(
 
         // Turn the parameter of the edge's target (which at this point got a concrete
         // placeholder Value based on e.g. an example or its type) into a Reference to
-        // the parameter of the same name and kind in the source.
-        if let Some(x) = openapi_input.0[target_index]
-            .get_mut_parameter(edge.weight().name_input, edge.weight().kind_input)
-        {
-            *x = ParameterContents::Reference {
-                request_index: source_index,
-                parameter_name: edge.weight().name_output.to_owned(),
-            };
+        // the parameter of the same name and kind in the source. Fields nested in the
+        // body are written to their location, even if the placeholder body lacks them.
+        let weight = edge.weight();
+        let reference = ParameterContents::Reference {
+            request_index: source_index,
+            parameter_name: weight.name_output.to_owned(),
+            source_pointer: weight.pointer_output.clone(),
+            target_pointer: weight.pointer_input.clone(),
+        };
+        let request = &mut openapi_input.0[target_index];
+        match &weight.pointer_input {
+            Some(target) => {
+                request.body.insert_at(target, reference);
+            }
+            None => {
+                if let Some(x) = request.get_mut_parameter(weight.name_input, weight.kind_input) {
+                    *x = reference;
+                }
+            }
         }
     }
 }
@@ -163,11 +177,13 @@ pub struct DependencyGraph<'a> {
 
 /// A parameter name saved in two variants: the canonical name appearing as the
 /// output parameter in the spec, the canonical name appearing as the input parameter
-/// in the spec.
+/// in the spec. Fields nested in a body or response also have the JSON Pointer to them.
 #[derive(Debug, Clone)]
 pub struct ParameterMatching<'a> {
     name_output: &'a str,
+    pointer_output: Option<String>,
     pub(crate) name_input: &'a str,
+    pointer_input: Option<String>,
     normalized: String,
     pub(crate) kind_input: ParameterKind,
 }
@@ -225,8 +241,9 @@ impl<'a> DependencyGraph<'a> {
 
         // The UnionFind will point you to a representative for each vertex.
         // The representative is a usize (from to_index), so we use that to
-        // keep track of our subgraphs.
-        let mut subgraphs = HashMap::new();
+        // keep track of our subgraphs. An IndexMap keeps the subgraphs in the order
+        // of their first operation, so the initial corpus is the same on every run.
+        let mut subgraphs = IndexMap::new();
         for operation in self.graph.node_indices() {
             let representative = vertex_sets.find_mut(operation);
             subgraphs
@@ -307,7 +324,7 @@ impl<'a> DependencyGraph<'a> {
     pub fn subgraph(
         &self,
         nodes: &[NodeIndex],
-    ) -> DiGraph<QualifiedOperation<'a>, ParameterMatching, DefaultIx> {
+    ) -> DiGraph<QualifiedOperation<'a>, ParameterMatching<'a>, DefaultIx> {
         let mut subgraph = self.graph.clone();
         subgraph.retain_nodes(|_, node| nodes.binary_search(&node).is_ok());
         subgraph
@@ -361,12 +378,14 @@ fn find_links<'a>(
     inputs_to_2
         .iter()
         .filter_map(|input| {
+            let output = outputs_from_1
+                .iter()
+                .find(|output| output.normalized == input.0.normalized)?;
             Some(ParameterMatching {
-                name_output: outputs_from_1
-                    .iter()
-                    .find(|output| output.normalized == input.0.normalized)?
-                    .name,
+                name_output: output.name,
+                pointer_output: output.pointer.clone(),
                 name_input: input.0.name,
+                pointer_input: input.0.pointer.clone(),
                 normalized: input.0.normalized.clone(),
                 kind_input: input.1,
             })
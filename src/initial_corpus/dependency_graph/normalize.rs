@@ -8,19 +8,25 @@
 //! of an artist, and so the normalization is something like 'artist|id'. When an album
 //! later refers to an 'artist_id', there is an opportunity to match it to the 'id' found
 //! earlier.
+//!
+//! Fields nested in a body or response take the name of the field that contains them as
+//! their context instead: the `id` in `{"owner": {"id": 3}}` normalizes to `owner|id`.
 
-use openapiv3::{
-    MediaType, ObjectType, OpenAPI, Operation, Parameter, RequestBody, Response, SchemaKind,
-};
+use openapiv3::{MediaType, OpenAPI, Operation, Parameter, RequestBody, Response};
 use porter_stemmer::stem;
 
-use crate::{input::parameter::ParameterKind, openapi::JsonContent};
+use crate::{
+    input::{parameter::ParameterKind, pointer::schema_fields},
+    openapi::JsonContent,
+};
 
 /// A parameter name saved in two variants: the canonical name appearing in the spec,
-/// and the normalized form used for matching input and output parameters
+/// and the normalized form used for matching input and output parameters. Fields nested
+/// in a body or response also have the JSON Pointer to them.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParameterNormalization<'a> {
     pub name: &'a str,
+    pub pointer: Option<String>,
     pub normalized: String,
 }
 
@@ -50,15 +56,26 @@ impl<'a> ParameterNormalization<'a> {
 
                 Self {
                     name,
+                    pointer: None,
                     normalized: stem(context) + "|" + &stem(no_context_name),
                 }
             }
             None => Self {
                 name,
+                pointer: None,
                 normalized: stem(name),
             },
         }
     }
+
+    /// Creates a new ParameterNormalization for a field nested in a body or response at the
+    /// given JSON Pointer. The context is the name of the field that contains it.
+    pub fn nested(pointer: String, name: &'a str, context: Option<&str>) -> Self {
+        Self {
+            pointer: Some(pointer),
+            ..Self::new(name, context)
+        }
+    }
 }
 
 /// Finds all parameters used in an operation and returns their normalized name.
@@ -145,38 +162,22 @@ pub fn normalize_request_body<'a>(
 }
 
 /// MediaType is the internal type used for objects, both input (POST) and
-/// output (GET). This function normalizes the field names.
+/// output (GET). This function normalizes the field names, including those of nested
+/// objects.
 fn normalize_media_type<'a>(
     api: &'a OpenAPI,
     path: &str,
     media_type: &'a MediaType,
 ) -> Option<Vec<ParameterNormalization<'a>>> {
     let schema = media_type.schema.as_ref()?.resolve(api);
-    match schema.kind {
-        SchemaKind::Type(openapiv3::Type::Object(ref o)) => Some(normalize_object_type(path, o)),
-        SchemaKind::Type(openapiv3::Type::Array(ref a)) => {
-            let inner_schema = a.items.as_ref()?.resolve(api);
-            match inner_schema.kind {
-                SchemaKind::Type(openapiv3::Type::Object(ref o)) => {
-                    Some(normalize_object_type(path, o))
-                }
-                // No support for nested arrays - semantic meaning not obvious
-                _ => None,
-            }
-        }
-        _ => None,
-    }
-}
-
-fn normalize_object_type<'a>(
-    path: &str,
-    object_type: &'a ObjectType,
-) -> Vec<ParameterNormalization<'a>> {
-    object_type
-        .properties
-        .keys()
-        .map(|key| ParameterNormalization::new(key, path_context_component(path)))
-        .collect()
+    let fields: Vec<_> = schema_fields(api, schema)
+        .into_iter()
+        .map(|field| match field.pointer {
+            Some(pointer) => ParameterNormalization::nested(pointer, field.name, field.parent),
+            None => ParameterNormalization::new(field.name, path_context_component(path)),
+        })
+        .collect();
+    (!fields.is_empty()).then_some(fields)
 }
 
 /// Find the context of a request from the path.
@@ -200,6 +201,7 @@ mod tests {
         assert_eq!(
             ParameterNormalization {
                 name: "widget",
+                pointer: None,
                 normalized: "widget".into(),
             },
             ParameterNormalization::new("widget", None)
@@ -207,6 +209,7 @@ mod tests {
         assert_eq!(
             ParameterNormalization {
                 name: "widgets",
+                pointer: None,
                 normalized: "widget".into(),
             },
             ParameterNormalization::new("widgets", None)
@@ -214,6 +217,7 @@ mod tests {
         assert_eq!(
             ParameterNormalization {
                 name: "widget",
+                pointer: None,
                 normalized: "aircraft|widget".into(),
             },
             ParameterNormalization::new("widget", Some("aircraft"))
@@ -221,6 +225,7 @@ mod tests {
         assert_eq!(
             ParameterNormalization {
                 name: "widget",
+                pointer: None,
                 normalized: "aircraft|widget".into(),
             },
             ParameterNormalization::new("widget", Some("aircrafts"))
@@ -228,6 +233,7 @@ mod tests {
         assert_eq!(
             ParameterNormalization {
                 name: "country_id",
+                pointer: None,
                 normalized: "countri|id".into(),
             },
             ParameterNormalization::new("country_id", Some("countries"))
@@ -235,6 +241,7 @@ mod tests {
         assert_eq!(
             ParameterNormalization {
                 name: "id",
+                pointer: None,
                 normalized: "countri|id".into(),
             },
             ParameterNormalization::new("id", Some("countries"))
@@ -242,6 +249,7 @@ mod tests {
         assert_eq!(
             ParameterNormalization {
                 name: "widget_id",
+                pointer: None,
                 normalized: "countri|widget_id".into(),
             },
             ParameterNormalization::new("widget_id", Some("countries"))
@@ -8,7 +8,7 @@ use std::{
     fs::{self, create_dir_all, File},
     hash::{Hash, Hasher},
     io::Write,
-    path::{Path, PathBuf},
+    path::Path,
 };
 
 use libafl::{
@@ -42,9 +42,14 @@ pub fn load_starting_corpus(
 /// Additionally, if `report_path` is specified, the dependency graph (i.e.
 /// the dependencies between parameters of the requests in each series generated
 /// as the initial corpus) used to generate the initial corpus is then written
-/// to the `report_path`.
-pub fn generate_corpus_to_files(api: &OpenAPI, corpus_dir: &Path, report_path: Option<&Path>) {
-    let inputs = initial_corpus_from_api(api);
+/// to the `report_path`. The `seed` makes the generated corpus reproducible.
+pub fn generate_corpus_to_files(
+    api: &OpenAPI,
+    corpus_dir: &Path,
+    report_path: Option<&Path>,
+    seed: u64,
+) {
+    let inputs = initial_corpus_from_api(api, seed);
     log::debug!("Writing corpus to file...");
     if let Err(e) = write_corpus_to_files(&inputs, corpus_dir) {
         log::warn!("Error writing corpus to file: {}", e);
@@ -85,20 +90,37 @@ pub fn print_starting_corpus(filename: &Path) {
     }
 }
 
-pub fn initialize_corpus(
+/// Loads the inputs of the initial corpus from `initial_corpus_path`, or generates them from
+/// the API if no path is given.
+pub fn initial_inputs(
     api: &OpenAPI,
     initial_corpus_path: Option<&Path>,
     report_path: &Option<&Path>,
-) -> InMemoryOnDiskCorpus<OpenApiInput> {
-    let mut corpus = InMemoryOnDiskCorpus::new(PathBuf::from("./queue")).unwrap();
+    seed: u64,
+) -> Vec<OpenApiInput> {
     match initial_corpus_path {
         Some(initial_corpus_path) => {
             log::info!("Filling corpus from file: {initial_corpus_path:?}");
-            fill_corpus_from_file(&mut corpus, initial_corpus_path)
+            inputs_from_file(initial_corpus_path)
         }
         None => {
             log::info!("No corpus supplied, generating one based on the API");
-            fill_corpus_from_api(&mut corpus, api, report_path)
+            inputs_from_api(api, report_path, seed)
+        }
+    }
+}
+
+/// Creates a corpus in `queue_path` that holds the initial `inputs`.
+pub fn initialize_corpus(
+    inputs: Vec<OpenApiInput>,
+    queue_path: &Path,
+) -> InMemoryOnDiskCorpus<OpenApiInput> {
+    let mut corpus = InMemoryOnDiskCorpus::new(queue_path).unwrap();
+    for input in inputs {
+        let mut testcase = Testcase::new(input);
+        testcase.add_metadata(SchedulerTestcaseMetadata::new(0));
+        if let Err(e) = corpus.add(testcase) {
+            log::warn!("Could not add testcase to corpus, omitting. {e:?}");
         }
     }
     corpus
@@ -156,35 +178,24 @@ fn write_corpus_report(input_vector: &[OpenApiInput], report_path: &Path) -> std
     Ok(())
 }
 
-fn fill_corpus_from_file(
-    corpus: &mut InMemoryOnDiskCorpus<OpenApiInput>,
-    initial_corpus_path: &Path,
-) {
+fn inputs_from_file(initial_corpus_path: &Path) -> Vec<OpenApiInput> {
     match load_starting_corpus(initial_corpus_path) {
         Ok(inputs) => {
             print_starting_corpus(initial_corpus_path);
-            for input in inputs {
-                let mut testcase = Testcase::new(input);
-                testcase.add_metadata(SchedulerTestcaseMetadata::new(0));
-                match corpus.add(testcase) {
-                    Ok(_) => (),
-                    Err(e) => log::warn!("Could not add testcase to corpus, omitting. {e:?}"),
-                }
-            }
+            inputs
         }
-        Err(err) => log::warn!(
-            "Error loading initial corpus, will generate random inputs instead: {}",
-            err
-        ),
-    };
+        Err(err) => {
+            log::warn!(
+                "Error loading initial corpus, will generate random inputs instead: {}",
+                err
+            );
+            Vec::new()
+        }
+    }
 }
 
-fn fill_corpus_from_api(
-    corpus: &mut InMemoryOnDiskCorpus<OpenApiInput>,
-    api: &OpenAPI,
-    report_path: &Option<&Path>,
-) {
-    let inputs = initial_corpus_from_api(api);
+fn inputs_from_api(api: &OpenAPI, report_path: &Option<&Path>, seed: u64) -> Vec<OpenApiInput> {
+    let inputs = initial_corpus_from_api(api, seed);
     if let Some(report_path) = report_path {
         // The dependency graph was already generated while creating it from the API
         // but it is cheap to build, so we can afford to do it again for reporting.
@@ -192,9 +203,114 @@ fn fill_corpus_from_api(
         let _ = dependency_graph.write_report(report_path);
         let _ = write_corpus_report(&inputs, report_path);
     }
-    for input in inputs {
-        let mut testcase = Testcase::new(input);
-        testcase.add_metadata(SchedulerTestcaseMetadata::new(0));
-        let _ = corpus.add(testcase);
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use libafl::{
+        corpus::{Corpus, InMemoryOnDiskCorpus, OnDiskCorpus, Testcase},
+        feedbacks::{CrashFeedback, MaxMapFeedback},
+        mutators::{Mutator, StdScheduledMutator},
+        observers::StdMapObserver,
+        state::HasCorpus,
+    };
+    use libafl_bolts::rands::StdRand;
+    use openapiv3::OpenAPI;
+
+    use super::initial_corpus_from_api;
+    use crate::{
+        configuration::MethodMutationStrategy, executor::FuzzerState,
+        openapi::filter::OperationFilter, openapi_mutator::havoc_mutations_openapi,
+        state::OpenApiFuzzerState,
+    };
+
+    const SPEC: &str = "
+openapi: 3.0.0
+info:
+  title: Seeded
+  version: 1.0.0
+paths:
+  /items/{id}:
+    get:
+      parameters:
+        - name: id
+          in: path
+          required: true
+          schema:
+            type: string
+            pattern: '^[a-z]{12}$'
+      responses:
+        '200':
+          description: OK
+  /items:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: object
+              properties:
+                name:
+                  type: string
+                  pattern: '[A-Z][a-z]{10}'
+                count:
+                  type: integer
+      responses:
+        '200':
+          description: OK
+";
+
+    /// Generates a corpus and mutates its first input a number of times, returning the
+    /// serialized corpus and every mutated input.
+    fn seeded_run(seed: u64) -> (String, Vec<String>) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut api: OpenAPI = serde_yaml::from_str(SPEC).unwrap();
+        let filter = Arc::new(OperationFilter::apply(&mut api, &[], &[]).unwrap());
+        let inputs = initial_corpus_from_api(&api, seed);
+        let corpus = serde_yaml::to_string(&inputs).unwrap();
+
+        let mut map = [0u8; 16];
+        let observer = unsafe { StdMapObserver::from_mut_ptr("map", map.as_mut_ptr(), map.len()) };
+        let mut state: FuzzerState = OpenApiFuzzerState::new(
+            StdRand::with_seed(seed),
+            InMemoryOnDiskCorpus::new(dir.path().join("queue")).unwrap(),
+            OnDiskCorpus::new(dir.path().join("crashes")).unwrap(),
+            &mut MaxMapFeedback::new(&observer),
+            &mut CrashFeedback::new(),
+            api,
+        )
+        .unwrap();
+        for input in &inputs {
+            state
+                .corpus_mut()
+                .add(Testcase::new(input.clone()))
+                .unwrap();
+        }
+        let mut mutator = StdScheduledMutator::new(havoc_mutations_openapi(
+            filter,
+            MethodMutationStrategy::FollowSpec,
+        ));
+        let mut input = inputs[0].clone();
+        let mutations = (0..100)
+            .map(|_| {
+                mutator.mutate(&mut state, &mut input).unwrap();
+                serde_yaml::to_string(&input).unwrap()
+            })
+            .collect();
+        (corpus, mutations)
+    }
+
+    #[test]
+    fn test_seeded_runs_are_deterministic() {
+        let (corpus, mutations) = seeded_run(7);
+        assert!(corpus.contains("Contents"), "{corpus}");
+        assert_eq!(seeded_run(7), (corpus.clone(), mutations.clone()));
+        // .. and the seed is actually used
+        let (other_corpus, other_mutations) = seeded_run(8);
+        assert_ne!(other_corpus, corpus);
+        assert_ne!(other_mutations, mutations);
     }
 }
@@ -0,0 +1,55 @@
+//! The headers that declare the media types of a request: `Content-Type` and `Accept`.
+//!
+//! By default a request declares the content type its body is encoded as, and accepts JSON.
+//! Targets that pick a parser or a response format from these headers can get confused when
+//! they do not match the body, so the
+//! [`crate::openapi_mutator::media_type::MediaTypeMutator`] declares other media types
+//! (or leaves the headers out). The declared media types are part of the input, so findings
+//! reproduce with the same headers.
+
+use serde::{Deserialize, Serialize};
+
+/// `Accept` header of requests that declare no other
+pub const DEFAULT_ACCEPT: &str = "application/json";
+
+/// A header that is sent instead of the default one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeclaredHeader {
+    /// The header is sent with this value
+    Value(String),
+    /// The header is left out
+    Omitted,
+}
+
+/// The media type headers of a request that differ from the defaults.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MediaTypeHeaders {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<DeclaredHeader>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accept: Option<DeclaredHeader>,
+}
+
+impl MediaTypeHeaders {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl std::fmt::Display for MediaTypeHeaders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let headers = [
+            ("Content-Type", &self.content_type),
+            ("Accept", &self.accept),
+        ];
+        for (name, header) in headers {
+            match header {
+                Some(DeclaredHeader::Value(value)) => write!(f, "\n  {name}: {value}")?,
+                Some(DeclaredHeader::Omitted) => write!(f, "\n  {name} left out")?,
+                None => (),
+            }
+        }
+        Ok(())
+    }
+}
@@ -17,8 +17,9 @@
 //!   - method: POST
 //!     path: "/path/{name_of_parameter_in_path}/something"
 //!     body:
-//!       # The body can be a submitted form (as below), but also TextPlain or
-//!       # ApplicationJson, or it can be omitted.
+//!       # The body can be a submitted form (as below), but also TextPlain,
+//!       # ApplicationJson, ApplicationXml, MultipartFormData or Raw, or it
+//!       # can be omitted.
 //!       XWwwFormUrlencoded:
 //!         # The contents of any parameter can be a leaf_value, shown below,
 //!         # or an object or array containing values of its own (again, leaf
@@ -59,25 +60,36 @@ use std::{
 };
 
 use ahash::RandomState;
-use indexmap::{
-    map::{Iter, ValuesMut},
-    IndexMap,
-};
+use indexmap::{map::ValuesMut, IndexMap};
 use libafl::{corpus::CorpusId, inputs::Input, Error};
 use libafl_bolts::{fs::write_file_atomic, rands::Rand, HasLen};
-use openapiv3::{OpenAPI, Operation, SchemaKind, Type};
+use openapiv3::{OpenAPI, Operation};
 
-use self::parameter::ParameterKind;
+use self::{
+    media_type::{DeclaredHeader, MediaTypeHeaders, DEFAULT_ACCEPT},
+    multipart::FormPart,
+    parameter::{ParameterKind, SimpleValue},
+    xml::XmlBody,
+};
 pub use self::{method::Method, parameter::ParameterContents};
 use crate::{
-    openapi::{find_operation, JsonContent, TextPlain, WwwForm},
+    openapi::{
+        find_operation, JsonContent, MultipartForm, RawContent, TextPlain, WwwForm, XmlContent,
+    },
     parameter_feedback::ParameterFeedback,
     state::HasRandAndOpenAPI,
 };
 
+pub mod media_type;
 pub mod method;
+pub mod multipart;
 pub mod parameter;
+pub mod pointer;
 mod serde_helpers;
+pub mod xml;
+
+/// Content type of raw bodies whose media type in the specification is a wildcard
+const DEFAULT_RAW_CONTENT_TYPE: &str = "application/octet-stream";
 
 /// The main representation of an HTTP request in WuppieFuzz.
 ///
@@ -96,6 +108,8 @@ pub struct OpenApiRequest {
 
     pub body: Body,
     pub parameters: IndexMap<(String, ParameterKind), ParameterContents>,
+    /// `Content-Type` and `Accept` headers that are declared instead of the defaults
+    pub media_type_headers: MediaTypeHeaders,
 }
 
 #[derive(Default, Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -105,6 +119,17 @@ pub enum Body {
     TextPlain(ParameterContents),
     ApplicationJson(ParameterContents),
     XWwwFormUrlencoded(ParameterContents),
+    /// The parts of a `multipart/form-data` body, by name
+    MultipartFormData(IndexMap<String, FormPart>),
+    /// An XML document, with the contents in the shape given by the schema
+    ApplicationXml(XmlBody),
+    /// A body of any other media type, which is sent as it is
+    Raw {
+        content_type: String,
+        /// The contents, sent as their bytes: raw bytes as they are, and structured contents
+        /// (of vendor `+json` types) as JSON
+        bytes: ParameterContents,
+    },
 }
 
 impl Body {
@@ -130,6 +155,34 @@ impl Body {
                 if body.content.has_text_plain() {
                     return Body::TextPlain(param_contents.to_string().into());
                 }
+                if let Some(media_type) = body.content.get_multipart_form_content() {
+                    return multipart::build_parts(api, media_type, param_contents)
+                        .map_or(Body::Empty, Body::MultipartFormData);
+                }
+                if let Some((content_type, media_type)) = body.content.get_xml_content_with_type() {
+                    return Body::ApplicationXml(XmlBody::build(
+                        api,
+                        content_type,
+                        media_type,
+                        param_contents,
+                    ));
+                }
+                if let Some((content_type, _)) = body.content.get_raw_content() {
+                    return Body::Raw {
+                        // Wildcards such as `image/*` can not be sent
+                        content_type: if content_type.contains('*') {
+                            DEFAULT_RAW_CONTENT_TYPE.to_owned()
+                        } else {
+                            content_type.to_owned()
+                        },
+                        bytes: match param_contents {
+                            ParameterContents::LeafValue(SimpleValue::String(text)) => {
+                                ParameterContents::Bytes(text.into_bytes())
+                            }
+                            contents => contents,
+                        },
+                    };
+                }
                 Body::Empty
             }
             Err(reference) => {
@@ -141,10 +194,91 @@ impl Body {
     pub fn is_empty(&self) -> bool {
         matches!(self, Body::Empty)
     }
+
+    /// Returns the values in the body that are not objects or arrays, with the JSON Pointer to
+    /// each of them. The parts of a multipart body are the fields at its top level.
+    pub fn leaves(&self) -> Vec<(String, &ParameterContents)> {
+        match self {
+            Body::Empty => Vec::new(),
+            Body::TextPlain(contents)
+            | Body::ApplicationJson(contents)
+            | Body::XWwwFormUrlencoded(contents)
+            | Body::ApplicationXml(XmlBody { contents, .. })
+            | Body::Raw {
+                bytes: contents, ..
+            } => contents.leaves(String::new()),
+            Body::MultipartFormData(parts) => parts
+                .iter()
+                .flat_map(|(name, part)| part.contents().leaves(pointer::push("", name)))
+                .collect(),
+        }
+    }
+
+    /// Like `leaves`, but returns mutable references.
+    pub fn leaves_mut(&mut self) -> Vec<(String, &mut ParameterContents)> {
+        match self {
+            Body::Empty => Vec::new(),
+            Body::TextPlain(contents)
+            | Body::ApplicationJson(contents)
+            | Body::XWwwFormUrlencoded(contents)
+            | Body::ApplicationXml(XmlBody { contents, .. })
+            | Body::Raw {
+                bytes: contents, ..
+            } => contents.leaves_mut(String::new()),
+            Body::MultipartFormData(parts) => parts
+                .iter_mut()
+                .flat_map(|(name, part)| part.contents_mut().leaves_mut(pointer::push("", name)))
+                .collect(),
+        }
+    }
+
+    /// Returns the value a JSON Pointer into the body points to. The parts of a multipart
+    /// body are at its top level.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut ParameterContents> {
+        match self {
+            Body::Empty => None,
+            Body::TextPlain(contents)
+            | Body::ApplicationJson(contents)
+            | Body::XWwwFormUrlencoded(contents)
+            | Body::ApplicationXml(XmlBody { contents, .. })
+            | Body::Raw {
+                bytes: contents, ..
+            } => contents.pointer_mut(pointer),
+            Body::MultipartFormData(parts) => {
+                let (part, rest) = pointer::split_first(pointer)?;
+                parts.get_mut(&part)?.contents_mut().pointer_mut(rest)
+            }
+        }
+    }
+
+    /// Writes a value to the location a JSON Pointer into the body points to, as
+    /// `ParameterContents::insert_at` does. Returns whether the location could be reached.
+    pub fn insert_at(&mut self, pointer: &str, value: ParameterContents) -> bool {
+        match self {
+            Body::Empty => false,
+            Body::TextPlain(contents)
+            | Body::ApplicationJson(contents)
+            | Body::XWwwFormUrlencoded(contents)
+            | Body::ApplicationXml(XmlBody { contents, .. })
+            | Body::Raw {
+                bytes: contents, ..
+            } => contents.insert_at(pointer, value),
+            Body::MultipartFormData(parts) => {
+                let Some((part, rest)) = pointer::split_first(pointer) else {
+                    return false;
+                };
+                parts
+                    .get_mut(&part)
+                    .is_some_and(|part| part.contents_mut().insert_at(rest, value))
+            }
+        }
+    }
 }
 
 impl OpenApiRequest {
     /// Replaces all references in the parameters IndexMap by values collected in earlier requests.
+    /// References in the body are replaced as well, and their value is also written to their
+    /// target pointer if they are not at that location.
     pub fn resolve_parameter_references(
         &mut self,
         parameter_values: &ParameterFeedback,
@@ -156,13 +290,16 @@ impl OpenApiRequest {
             if let ParameterContents::Reference {
                 request_index,
                 parameter_name,
+                source_pointer,
+                ..
             } = parameter
             {
                 let resolved_backref = parameter_values
-                    .get(*request_index, parameter_name)
+                    .get(*request_index, parameter_name, source_pointer.as_deref())
                     .ok_or_else(|| {
                         libafl::Error::unknown(format!(
-                            "invalid backreference to {request_index}:{parameter_name}"
+                            "invalid backreference to {request_index}:{}",
+                            source_pointer.as_ref().unwrap_or(parameter_name)
                         ))
                     })?;
                 *parameter = ParameterContents::from(resolved_backref.clone());
@@ -171,27 +308,25 @@ impl OpenApiRequest {
             Ok(())
         }
 
-        // Resolve body parameters
-        match &mut self.body {
-            Body::Empty => (), // No (reference) parameters in body, so nothing to resolve here!
-            Body::TextPlain(body)
-            | Body::ApplicationJson(body)
-            | Body::XWwwFormUrlencoded(body) => match body {
-                ParameterContents::Reference { .. } => {
-                    resolve_single_parameter(body, parameter_values)?;
-                }
-                ParameterContents::Object(obj_contents) => {
-                    for (_key, nested_parameter) in obj_contents {
-                        resolve_single_parameter(nested_parameter, parameter_values)?;
-                    }
-                }
-                ParameterContents::Array(arr) => {
-                    for nested_parameter in arr {
-                        resolve_single_parameter(nested_parameter, parameter_values)?;
-                    }
-                }
-                ParameterContents::LeafValue(_) | ParameterContents::Bytes(_) => (),
-            },
+        // Resolve body parameters, at any depth
+        let mut moved = Vec::new();
+        for (location, parameter) in self.body.leaves_mut() {
+            let target = match parameter {
+                ParameterContents::Reference {
+                    target_pointer: Some(target),
+                    ..
+                } if *target != location => Some(target.clone()),
+                _ => None,
+            };
+            resolve_single_parameter(parameter, parameter_values)?;
+            if let Some(target) = target {
+                moved.push((target, parameter.clone()));
+            }
+        }
+        for (target, value) in moved {
+            if !self.body.insert_at(&target, value) {
+                log::warn!("Could not write a referenced value to {target} in the body");
+            }
         }
 
         // Resolve URL-parameters
@@ -254,29 +389,61 @@ impl OpenApiRequest {
                 }
                 Some(reqwest::blocking::Body::from(encoded.finish()))
             }
+            Body::MultipartFormData(parts) => Some(reqwest::blocking::Body::from(
+                multipart::encode(parts, &multipart::boundary(parts)),
+            )),
+            Body::ApplicationXml(body) => Some(reqwest::blocking::Body::from(body.encode())),
+            Body::Raw { bytes, .. } => bytes
+                .bytes()
+                .map(|bytes| reqwest::blocking::Body::from(bytes.into_owned())),
         }
     }
 
-    pub fn body_content_type(&self) -> &'static str {
-        match self.body {
-            Body::Empty => "",
-            Body::TextPlain(_) => "text/plain",
-            Body::ApplicationJson(_) => "application/json",
-            Body::XWwwFormUrlencoded(_) => "application/x-www-form-urlencoded",
+    pub fn body_content_type(&self) -> Cow<'static, str> {
+        match &self.body {
+            Body::Empty => "".into(),
+            Body::TextPlain(_) => "text/plain".into(),
+            Body::ApplicationJson(_) => "application/json".into(),
+            Body::XWwwFormUrlencoded(_) => "application/x-www-form-urlencoded".into(),
+            // The same boundary as in the body
+            Body::MultipartFormData(parts) => format!(
+                "multipart/form-data; boundary={}",
+                multipart::boundary(parts)
+            )
+            .into(),
+            Body::ApplicationXml(body) => body.content_type.clone().into(),
+            Body::Raw { content_type, .. } => content_type.clone().into(),
+        }
+    }
+
+    /// The `Content-Type` header of the request: the content type of the body, unless the
+    /// input declares another one. Returns `None` if the header is left out.
+    pub fn content_type(&self) -> Option<Cow<'_, str>> {
+        match &self.media_type_headers.content_type {
+            Some(DeclaredHeader::Value(content_type)) => Some(content_type.into()),
+            Some(DeclaredHeader::Omitted) => None,
+            None => (!self.body.is_empty()).then(|| self.body_content_type()),
+        }
+    }
+
+    /// The `Accept` header of the request, or `None` if it is left out.
+    pub fn accept(&self) -> Option<&str> {
+        match &self.media_type_headers.accept {
+            Some(DeclaredHeader::Value(accept)) => Some(accept),
+            Some(DeclaredHeader::Omitted) => None,
+            None => Some(DEFAULT_ACCEPT),
         }
     }
 
     /// Finds a parameter of ParameterKind (Query, Path, Cookie, etc.)
     /// with the given name and returns a mutable reference to it.
-    /// If none exists, checks the body for a field with the given name.
+    /// Body parameters are the fields at the top level of the body, or the parts of a
+    /// multipart body. Use `Body::pointer_mut` for values nested deeper.
     pub fn get_mut_parameter<'a>(
         &'a mut self,
         name: &str,
         kind: ParameterKind,
     ) -> Option<&'a mut ParameterContents> {
-        // Can't use or_else with a closure because you'd have to move self
-        // which is not possible
-        #[allow(clippy::or_fun_call)]
         match kind {
             ParameterKind::Path
             | ParameterKind::Query
@@ -285,15 +452,19 @@ impl OpenApiRequest {
             ParameterKind::Body => match &mut self.body {
                 Body::Empty => None,
                 Body::TextPlain(text) => Some(text),
-                // For getting named parameters, we consider only first-level parameters in object values
-                // TODO: implement a way to address nested parameters and non-object parameters.
-                Body::ApplicationJson(parameters) | Body::XWwwFormUrlencoded(parameters) => {
-                    if let ParameterContents::Object(obj_param) = parameters {
-                        obj_param.get_mut(name)
-                    } else {
-                        None
-                    }
-                }
+                Body::ApplicationJson(parameters)
+                | Body::XWwwFormUrlencoded(parameters)
+                | Body::ApplicationXml(XmlBody {
+                    contents: parameters,
+                    ..
+                })
+                | Body::Raw {
+                    bytes: parameters, ..
+                } => match parameters {
+                    ParameterContents::Object(obj_param) => obj_param.get_mut(name),
+                    _ => None,
+                },
+                Body::MultipartFormData(parts) => parts.get_mut(name).map(FormPart::contents_mut),
             },
         }
     }
@@ -312,12 +483,27 @@ impl std::fmt::Display for OpenApiRequest {
         for ((name, kind), contents) in &self.parameters {
             write!(fmt, "\n  {name} in {kind:?}: {contents}")?;
         }
+        write!(fmt, "{}", self.media_type_headers)?;
         match &self.body {
             Body::Empty => (),
             Body::TextPlain(text) => write!(fmt, "\n text body: {text}")?,
-            Body::ApplicationJson(body_content) | Body::XWwwFormUrlencoded(body_content) => {
+            Body::ApplicationJson(body_content)
+            | Body::XWwwFormUrlencoded(body_content)
+            | Body::ApplicationXml(XmlBody {
+                contents: body_content,
+                ..
+            })
+            | Body::Raw {
+                bytes: body_content,
+                ..
+            } => {
                 write!(fmt, "Contents in body: {body_content}")?;
             }
+            Body::MultipartFormData(parts) => {
+                for (name, part) in parts {
+                    write!(fmt, "\n  {name} in body: {part}")?;
+                }
+            }
         }
         Ok(())
     }
@@ -328,26 +514,11 @@ impl std::fmt::Display for OpenApiRequest {
 #[derive(Clone, serde::Serialize, serde::Deserialize, Debug)]
 pub struct OpenApiInput(pub Vec<OpenApiRequest>);
 
-pub enum IterWrapper<'a> {
-    WithOption(Option<&'a ParameterContents>),
-    WithIter(Iter<'a, String, ParameterContents>),
-}
-
-impl<'a> Iterator for IterWrapper<'a> {
-    type Item = (Cow<'a, String>, &'a ParameterContents);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match self {
-            IterWrapper::WithOption(o) => o.take().map(|c| (Cow::Owned(String::new()), c)),
-            IterWrapper::WithIter(i) => i.next().map(|(s, c)| (Cow::Borrowed(s), c)),
-        }
-    }
-}
-
 pub enum ParamContentsAtLevel0Wrapper<'a> {
     SimpleOption(Option<&'a mut ParameterContents>),
     InObject(ValuesMut<'a, String, ParameterContents>),
     InArray(std::slice::IterMut<'a, ParameterContents>),
+    InParts(ValuesMut<'a, String, FormPart>),
 }
 
 impl<'a> Iterator for ParamContentsAtLevel0Wrapper<'a> {
@@ -358,6 +529,7 @@ impl<'a> Iterator for ParamContentsAtLevel0Wrapper<'a> {
             ParamContentsAtLevel0Wrapper::SimpleOption(o) => o.take(),
             ParamContentsAtLevel0Wrapper::InObject(i) => i.next(),
             ParamContentsAtLevel0Wrapper::InArray(i) => i.next(),
+            ParamContentsAtLevel0Wrapper::InParts(i) => i.next().map(FormPart::contents_mut),
         }
     }
 }
@@ -389,7 +561,14 @@ impl OpenApiInput {
                             ParamContentsAtLevel0Wrapper::SimpleOption(Some(text))
                         }
                         Body::ApplicationJson(parameters)
-                        | Body::XWwwFormUrlencoded(parameters) => match parameters {
+                        | Body::XWwwFormUrlencoded(parameters)
+                        | Body::ApplicationXml(XmlBody {
+                            contents: parameters,
+                            ..
+                        })
+                        | Body::Raw {
+                            bytes: parameters, ..
+                        } => match parameters {
                             ParameterContents::Object(obj_param) => {
                                 ParamContentsAtLevel0Wrapper::InObject(obj_param.values_mut())
                             }
@@ -398,6 +577,9 @@ impl OpenApiInput {
                             }
                             _ => ParamContentsAtLevel0Wrapper::SimpleOption(Some(parameters)),
                         },
+                        Body::MultipartFormData(parts) => {
+                            ParamContentsAtLevel0Wrapper::InParts(parts.values_mut())
+                        }
                     })
                     // .. then only return filtered ones with the request index
                     .filter(filter)
@@ -405,54 +587,42 @@ impl OpenApiInput {
             })
     }
 
-    /// This returns all reference parameters as follows:
-    /// (request idx, param name, param location, target request idx, target name)
-    pub fn reference_parameters(
-        &self,
-    ) -> impl Iterator<Item = (usize, String, ParameterKind, usize, String)> + '_ {
+    /// Returns an iterator that yields all references in the parameters and (at any depth)
+    /// in the bodies of all requests, along with the index of the request they appear in.
+    pub fn references_mut(&mut self) -> impl Iterator<Item = (usize, &mut ParameterContents)> {
+        self.0
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(request_idx, openapi_request)| {
+                let parameters = openapi_request.parameters.values_mut();
+                let body_leaves = openapi_request.body.leaves_mut().into_iter();
+                parameters
+                    .chain(body_leaves.map(|(_, leaf)| leaf))
+                    .filter(|contents| contents.is_reference())
+                    .map(move |contents| (request_idx, contents))
+            })
+    }
+
+    /// Returns an iterator that yields all references in the parameters and (at any depth)
+    /// in the bodies of all requests, along with the index of the request they appear in.
+    pub fn references(&self) -> impl Iterator<Item = (usize, &ParameterContents)> {
         self.0
             .iter()
             .enumerate()
-            .flat_map(move |(request_idx, openapi_request)| {
-                // For each input, collect any named parameters ...
-                openapi_request
-                    .parameters
-                    .iter()
-                    .map(|((n, k), v)| (Cow::Borrowed(n), *k, v))
-                    //.. then add any fields from the body as well ..
-                    .chain(
-                        match &openapi_request.body {
-                            Body::Empty => IterWrapper::WithOption(None),
-                            Body::TextPlain(text) => IterWrapper::WithOption(Some(text)),
-                            Body::ApplicationJson(contents)
-                            | Body::XWwwFormUrlencoded(contents) => match contents {
-                                ParameterContents::Object(obj_params) => {
-                                    IterWrapper::WithIter(obj_params.iter())
-                                }
-                                ParameterContents::Reference { .. } => {
-                                    IterWrapper::WithOption(Some(contents))
-                                }
-                                _ => IterWrapper::WithOption(None),
-                            },
-                        }
-                        .map(|(n, v)| (n, ParameterKind::Body, v)),
-                    )
-                    .filter_map(|(n, k, v)| match v {
-                        ParameterContents::Reference {
-                            request_index,
-                            parameter_name,
-                        } => Some(((n, k), (request_index, parameter_name))),
-                        _ => None,
-                    })
-                    .map(move |((n, k), (ti, tn))| {
-                        (request_idx, n.into_owned(), k, *ti, tn.to_owned())
-                    })
+            .flat_map(|(request_idx, openapi_request)| {
+                let parameters = openapi_request.parameters.values();
+                let body_leaves = openapi_request.body.leaves().into_iter();
+                parameters
+                    .chain(body_leaves.map(|(_, leaf)| leaf))
+                    .filter(|contents| contents.is_reference())
+                    .map(move |contents| (request_idx, contents))
             })
     }
 
-    /// Returns an iterator that yields all named return values from all
-    /// requests, along with the index of the request they appear in.
-    pub fn return_values<'a>(&self, api: &'a OpenAPI) -> Vec<(usize, &'a str)> {
+    /// Returns all named return values from all requests, along with the index of the
+    /// request they appear in and, for values nested deeper than the top level of the
+    /// response, the JSON Pointer to them.
+    pub fn return_values(&self, api: &OpenAPI) -> Vec<(usize, String, Option<String>)> {
         self.0
             .iter()
             .enumerate()
@@ -471,13 +641,9 @@ impl OpenApiInput {
                             .get_json_content()
                             .and_then(|media| media.schema.as_ref())
                     })
-                    // Finally if the schema is an object, extract its field names
-                    .filter_map(|schema| match schema.resolve(api).kind {
-                        SchemaKind::Type(Type::Object(ref obj)) => Some(obj.properties.keys()),
-                        _ => None,
-                    })
-                    .flatten()
-                    .map(move |resps| (i, resps.as_ref()))
+                    // Finally extract the fields of the schema
+                    .flat_map(|schema| pointer::schema_fields(api, schema.resolve(api)))
+                    .map(move |field| (i, field.name.to_owned(), field.pointer))
             })
             .collect()
     }
@@ -488,70 +654,38 @@ impl OpenApiInput {
     where
         R: Rand,
     {
-        let to_replace: Vec<_> = self
-            .reference_parameters()
-            .filter(
-                // Select broken references: target request does not exist or does not
-                // contain the referenced parameter name
-                |(_, _, _, target_idx, target_name)| match self.0.get(*target_idx) {
-                    None => true,
-                    Some(request) => !request.contains_parameter(target_name),
-                },
-            )
-            // Reference is broken - replace (later... borrow checker forbids doing it here
-            // since it can't verify we don't mess up the loop from reference_parameters)
-            .map(|(source_idx, source_name, source_kind, _, _)| {
-                (source_idx, source_name, source_kind)
+        // Select broken references: target request does not exist or does not contain the
+        // referenced parameter name. Values nested in the response are only known once it
+        // arrives.
+        let parameter_names: Vec<Vec<String>> = self
+            .0
+            .iter()
+            .map(|request| {
+                request
+                    .parameters
+                    .keys()
+                    .map(|(name, _)| name.clone())
+                    .collect()
             })
             .collect();
-
-        for (idx, name, kind) in to_replace {
-            match kind {
-                ParameterKind::Body => match &mut self.0[idx].body {
-                    Body::Empty | Body::TextPlain(_) => {
-                        log::warn!("Marked body parameter in request {idx} with name {name} for replacement,
-                                    but the body is Empty or TextPlain!");
-                        continue
-                    },
-                    Body::ApplicationJson(contents) | Body::XWwwFormUrlencoded(contents) => {
-                        match contents {
-                            ParameterContents::Object(obj_param) => &mut obj_param[&name],
-                            // Note that a Reference parameter is not by itself named, but must be the value in an Object parameter.
-                            // The parameter_name-field in a Reference only identifies the target of the Reference.
-                            ParameterContents::Reference { parameter_name, request_index } => {
-                                log::warn!("Marked body parameter in request {idx} with name {name} for replacement.
-                                        The body's immediate contents are however an (unnamed) reference, pointing to a parameter
-                                        with name {parameter_name} in request {request_index}.");
-                                continue
-                            }
-                            // Note that Array fields currently cannot be addressed as variable parameters.
-                            // Therefore, Arrays currently should not contain any references.
-                            // If they do, we do not resolve them here.
-                            ParameterContents::Array(arr_param) => {
-                                for elem in arr_param.iter() {
-                                    if let ParameterContents::Reference { .. } = elem {
-                                        log::warn!("Array contains reference, but we cannot identify this with
-                                        request_idx, name, parameter_kind triplet. Therefore we cannot resolve the reference.");
-                                    }
-                                }
-                                continue
-                            }
-                            ParameterContents::LeafValue(_) => {
-                                log::warn!("Marked body parameter in request {idx} with name {name} for replacement,
-                                            but the body is a LeafValue: {contents}");
-                                continue
-                            },
-                            ParameterContents::Bytes(_) => {
-                                log::warn!("Marked body parameter in request {idx} with name {name} for replacement,
-                                            but the body is of type Bytes: {contents}");
-                                continue
-                            },
-                        }
-                    }
-                },
-                _ => &mut self.0[idx].parameters[&(name, kind)],
+        for (_, reference) in self.references_mut() {
+            let ParameterContents::Reference {
+                request_index,
+                parameter_name,
+                source_pointer,
+                ..
+            } = reference
+            else {
+                continue;
+            };
+            let broken = match parameter_names.get(*request_index) {
+                None => true,
+                Some(_) if source_pointer.is_some() => false,
+                Some(names) => !names.contains(parameter_name),
+            };
+            if broken {
+                reference.break_reference_if_target(rand, |_| true);
             }
-            .break_reference_if_target(rand, |_| true);
         }
     }
 
@@ -559,7 +693,7 @@ impl OpenApiInput {
     /// Panics if not.
     #[cfg(debug_assertions)]
     pub fn assert_valid(&mut self, message: &str) {
-        for (appears_in, param) in self.parameter_filter(&|v| v.is_reference()) {
+        for (appears_in, param) in self.references_mut() {
             let refers_to = *param.reference_index().unwrap();
             if refers_to >= appears_in {
                 panic!(
@@ -593,12 +727,31 @@ impl Input for OpenApiInput {
                 hasher.write(&[name.1 as u8]);
                 hasher.write(value.to_string().as_bytes());
             }
+            if !request.media_type_headers.is_default() {
+                hasher.write(request.media_type_headers.to_string().as_bytes());
+            }
             match &request.body {
                 Body::Empty => (),
                 Body::TextPlain(value) => hasher.write(value.to_string().as_bytes()),
                 Body::ApplicationJson(content) | Body::XWwwFormUrlencoded(content) => {
                     hasher.write(content.to_string().as_bytes());
                 }
+                Body::ApplicationXml(body) => {
+                    hasher.write(body.encode().as_bytes());
+                }
+                Body::Raw {
+                    content_type,
+                    bytes,
+                } => {
+                    hasher.write(content_type.as_bytes());
+                    hasher.write(bytes.to_string().as_bytes());
+                }
+                Body::MultipartFormData(parts) => {
+                    for (name, part) in parts {
+                        hasher.write(name.as_bytes());
+                        hasher.write(part.to_string().as_bytes());
+                    }
+                }
             }
         }
         format!("{:016x}", hasher.finish())
@@ -684,7 +837,10 @@ mod tests {
     use indexmap::IndexMap;
     use serde_json::json;
 
-    use super::{Body, Method, OpenApiRequest, ParameterContents};
+    use super::{
+        media_type::DeclaredHeader, Body, MediaTypeHeaders, Method, OpenApiRequest,
+        ParameterContents,
+    };
 
     #[test]
     fn test_reqwest_body() {
@@ -704,6 +860,7 @@ mod tests {
             path: "/".to_owned(),
             body: form_body,
             parameters: IndexMap::new(),
+            media_type_headers: MediaTypeHeaders::default(),
         };
         let bodified = openapi_request
             .reqwest_body()
@@ -722,4 +879,85 @@ mod tests {
         assert!(query_pairs.contains(&&b"field1=2"[..]));
         assert!(query_pairs.contains(&&b"Field2=false"[..]));
     }
+
+    #[test]
+    fn test_raw_body() {
+        let api: openapiv3::OpenAPI = serde_yaml::from_str(
+            r#"
+openapi: 3.0.0
+info: {title: Upload, version: "1"}
+paths: {}
+"#,
+        )
+        .unwrap();
+        let operation: openapiv3::Operation = serde_yaml::from_str(
+            r#"
+requestBody:
+  content:
+    image/*: {}
+    application/vnd.api+json: {}
+responses: {}
+"#,
+        )
+        .unwrap();
+
+        // The first media type of the specification is used, without its wildcard
+        let body = Body::build(&api, &operation, Some(json!("GIF89a").into()));
+        let Body::Raw {
+            content_type,
+            bytes,
+        } = &body
+        else {
+            panic!("not a raw body: {body:?}");
+        };
+        assert_eq!(content_type, "application/octet-stream");
+        assert!(matches!(bytes, ParameterContents::Bytes(_)));
+
+        // Raw bytes are sent as they are
+        let request = OpenApiRequest {
+            method: Method::Post,
+            path: "/".to_owned(),
+            body: Body::Raw {
+                content_type: "application/x-protobuf".to_owned(),
+                bytes: ParameterContents::Bytes(vec![0x08, 0x96, 0x01, 0xff]),
+            },
+            parameters: IndexMap::new(),
+            media_type_headers: MediaTypeHeaders::default(),
+        };
+        let request: OpenApiRequest =
+            serde_yaml::from_str(&serde_yaml::to_string(&request).unwrap()).unwrap();
+        assert_eq!(request.body_content_type(), "application/x-protobuf");
+        assert_eq!(
+            request.reqwest_body().unwrap().as_bytes(),
+            Some(&[0x08, 0x96, 0x01, 0xff][..])
+        );
+    }
+
+    #[test]
+    fn test_media_type_headers() {
+        let mut request = OpenApiRequest {
+            method: Method::Post,
+            path: "/".to_owned(),
+            body: Body::ApplicationJson(json!({"name": "Rex"}).into()),
+            parameters: IndexMap::new(),
+            media_type_headers: MediaTypeHeaders::default(),
+        };
+        assert_eq!(request.content_type().as_deref(), Some("application/json"));
+        assert_eq!(request.accept(), Some("application/json"));
+
+        request.media_type_headers = MediaTypeHeaders {
+            content_type: Some(DeclaredHeader::Value(
+                "text/plain; charset=utf-7".to_owned(),
+            )),
+            accept: Some(DeclaredHeader::Omitted),
+        };
+        // The declared headers are kept with the input, so findings reproduce with them
+        let yaml = serde_yaml::to_string(&request).unwrap();
+        let request: OpenApiRequest = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(
+            request.content_type().as_deref(),
+            Some("text/plain; charset=utf-7")
+        );
+        assert_eq!(request.accept(), None);
+    }
 }
@@ -0,0 +1,264 @@
+//! Parts of `multipart/form-data` request bodies, as used to upload files.
+//!
+//! Each part of a multipart body is a form field or a file. Fields are encoded like the fields
+//! of a form-urlencoded body. Files have a filename and a content type of their own, which are
+//! mutated separately from their contents (see
+//! [`crate::openapi_mutator::form_part::FormPartMutator`]). Properties of the schema of the body
+//! that are strings with `format: binary` become files, as the specification prescribes.
+
+use std::fmt::{Display, Formatter};
+
+use indexmap::IndexMap;
+use openapiv3::{MediaType, OpenAPI, SchemaKind, StringFormat, Type, VariantOrUnknownOrEmpty};
+
+use super::parameter::{ParameterContents, SimpleValue};
+
+/// Content type of file parts for which the specification gives none.
+const DEFAULT_FILE_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// A part of a `multipart/form-data` body.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub enum FormPart {
+    /// A form field
+    Field(ParameterContents),
+    /// An uploaded file
+    File {
+        filename: String,
+        content_type: String,
+        contents: ParameterContents,
+    },
+}
+
+impl FormPart {
+    pub fn contents(&self) -> &ParameterContents {
+        match self {
+            FormPart::Field(contents) | FormPart::File { contents, .. } => contents,
+        }
+    }
+
+    pub fn contents_mut(&mut self) -> &mut ParameterContents {
+        match self {
+            FormPart::Field(contents) | FormPart::File { contents, .. } => contents,
+        }
+    }
+
+    /// The bytes of the part as they are sent.
+    fn bytes(&self) -> Vec<u8> {
+        match self.contents() {
+            // Strings are sent without the quotes of their JSON representation
+            ParameterContents::LeafValue(SimpleValue::String(text)) => text.as_bytes().to_vec(),
+            contents => contents
+                .bytes()
+                .map(|bytes| bytes.into_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Display for FormPart {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormPart::Field(contents) => write!(f, "{contents}"),
+            FormPart::File {
+                filename,
+                content_type,
+                contents,
+            } => write!(f, "file {filename:?} ({content_type}): {contents}"),
+        }
+    }
+}
+
+/// Builds the parts of a multipart body from the fields of `contents`. Fields that the schema
+/// of the media type describes as binary strings become files; the others become form fields.
+/// Returns `None` if the contents are not an object.
+pub fn build_parts(
+    api: &OpenAPI,
+    media_type: &MediaType,
+    contents: ParameterContents,
+) -> Option<IndexMap<String, FormPart>> {
+    let ParameterContents::Object(fields) = contents else {
+        return None;
+    };
+    let properties = media_type
+        .schema
+        .as_ref()
+        .map(|schema| &schema.resolve(api).kind)
+        .and_then(|kind| match kind {
+            SchemaKind::Type(Type::Object(object)) => Some(&object.properties),
+            _ => None,
+        });
+    let parts = fields
+        .into_iter()
+        .map(|(name, contents)| {
+            let is_file = properties
+                .and_then(|properties| properties.get(&name))
+                .is_some_and(|schema| {
+                    matches!(
+                        &schema.resolve(api).kind,
+                        SchemaKind::Type(Type::String(string))
+                            if string.format == VariantOrUnknownOrEmpty::Item(StringFormat::Binary)
+                    )
+                });
+            if !is_file {
+                return (name, FormPart::Field(contents));
+            }
+            // The content type of a file may be a list, or contain wildcards
+            let content_type = media_type
+                .encoding
+                .get(&name)
+                .and_then(|encoding| encoding.content_type.as_deref())
+                .and_then(|content_type| content_type.split(',').next())
+                .map(str::trim)
+                .filter(|content_type| !content_type.is_empty() && !content_type.contains('*'))
+                .unwrap_or(DEFAULT_FILE_CONTENT_TYPE)
+                .to_owned();
+            let part = FormPart::File {
+                filename: format!("{name}.{}", extension(&content_type)),
+                content_type,
+                contents: match contents {
+                    ParameterContents::LeafValue(SimpleValue::String(text)) => {
+                        ParameterContents::Bytes(text.into_bytes())
+                    }
+                    contents => contents,
+                },
+            };
+            (name, part)
+        })
+        .collect();
+    Some(parts)
+}
+
+/// A file extension that matches the content type, for the names of generated files.
+fn extension(content_type: &str) -> &str {
+    let subtype = content_type
+        .split(['/', '+', ';'])
+        .nth(1)
+        .unwrap_or_default()
+        .trim();
+    match subtype {
+        "" | "octet-stream" => "bin",
+        "plain" => "txt",
+        "jpeg" => "jpg",
+        subtype => subtype,
+    }
+}
+
+/// Returns a boundary that does not occur in any of the parts.
+pub fn boundary(parts: &IndexMap<String, FormPart>) -> String {
+    let contents: Vec<Vec<u8>> = parts.values().map(FormPart::bytes).collect();
+    (0u32..)
+        .map(|attempt| format!("WuppieFuzzBoundary{attempt:08x}"))
+        .find(|boundary| {
+            contents.iter().all(|bytes| {
+                !bytes
+                    .windows(boundary.len())
+                    .any(|window| window == boundary.as_bytes())
+            })
+        })
+        .expect("the parts are finite")
+}
+
+/// Encodes the parts of a multipart body, separated by the given boundary. Quotes and line
+/// breaks in names and filenames are escaped as browsers do, so that mutated filenames can not
+/// break the structure of the body.
+pub fn encode(parts: &IndexMap<String, FormPart>, boundary: &str) -> Vec<u8> {
+    fn escape(text: &str) -> String {
+        text.replace('"', "%22")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A")
+    }
+
+    let mut body = Vec::new();
+    for (name, part) in parts {
+        body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+        let mut disposition = format!("Content-Disposition: form-data; name=\"{}\"", escape(name));
+        if let FormPart::File {
+            filename,
+            content_type,
+            ..
+        } = part
+        {
+            disposition += &format!("; filename=\"{}\"", escape(filename));
+            if !content_type.is_empty() {
+                disposition += &format!(
+                    "\r\nContent-Type: {}",
+                    content_type.replace(['\r', '\n'], "")
+                );
+            }
+        }
+        body.extend_from_slice(disposition.as_bytes());
+        body.extend_from_slice(b"\r\n\r\n");
+        body.extend_from_slice(&part.bytes());
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::{boundary, build_parts, encode, FormPart};
+    use crate::input::ParameterContents;
+
+    #[test]
+    fn test_multipart() {
+        let api: openapiv3::OpenAPI = serde_yaml::from_str(
+            r#"
+openapi: 3.0.0
+info: {title: Upload, version: "1"}
+paths: {}
+"#,
+        )
+        .unwrap();
+        let media_type: openapiv3::MediaType = serde_yaml::from_str(
+            r#"
+schema:
+  type: object
+  properties:
+    description: {type: string}
+    avatar: {type: string, format: binary}
+encoding:
+  avatar: {contentType: "image/png, image/jpeg"}
+"#,
+        )
+        .unwrap();
+        let contents = ParameterContents::from(serde_json::json!({
+            "description": "A \"quoted\" picture",
+            "avatar": "WuppieFuzzBoundary00000000",
+        }));
+        let parts = build_parts(&api, &media_type, contents).unwrap();
+        assert!(matches!(&parts["description"], FormPart::Field(_)));
+        let FormPart::File {
+            filename,
+            content_type,
+            contents,
+        } = &parts["avatar"]
+        else {
+            panic!("avatar is not a file part");
+        };
+        assert_eq!(filename, "avatar.png");
+        assert_eq!(content_type, "image/png");
+        assert!(matches!(contents, ParameterContents::Bytes(_)));
+
+        // The fields are in alphabetical order, and the first boundary occurs in the file
+        let boundary = boundary(&parts);
+        assert_eq!(boundary, "WuppieFuzzBoundary00000001");
+        let body = String::from_utf8(encode(&parts, &boundary)).unwrap();
+        assert_eq!(
+            body,
+            "--WuppieFuzzBoundary00000001\r\n\
+             Content-Disposition: form-data; name=\"avatar\"; filename=\"avatar.png\"\r\n\
+             Content-Type: image/png\r\n\r\n\
+             WuppieFuzzBoundary00000000\r\n\
+             --WuppieFuzzBoundary00000001\r\n\
+             Content-Disposition: form-data; name=\"description\"\r\n\r\n\
+             A \"quoted\" picture\r\n\
+             --WuppieFuzzBoundary00000001--\r\n"
+        );
+
+        let empty: IndexMap<String, FormPart> = IndexMap::new();
+        assert_eq!(encode(&empty, "b"), b"--b--\r\n");
+    }
+}
@@ -94,8 +94,17 @@ pub enum ParameterContents {
     Reference {
         #[serde(rename = "request")]
         request_index: usize,
+        /// Name of the value, such as a field at the top level of the response or a cookie
         #[serde(rename = "parameter_name")]
         parameter_name: String,
+        /// JSON Pointer to the value in the response (or posted body) of the earlier request,
+        /// if it is nested deeper than the top level (see `super::pointer`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source_pointer: Option<String>,
+        /// JSON Pointer to the location in the body of this request that the value is
+        /// written to, for references in the body
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target_pointer: Option<String>,
     },
 }
 
@@ -110,7 +119,7 @@ impl ParameterContents {
     /// If this is the `contents` variant, the contained object is serialized into
     /// a new `Vec<u8>`.
     /// If this is an unresolved reference, `None` is returned.
-    pub fn bytes(&self) -> Option<Cow<[u8]>> {
+    pub fn bytes(&self) -> Option<Cow<'_, [u8]>> {
         match self {
             ParameterContents::Object(v) => {
                 let mut json_map = Map::new();
@@ -179,7 +188,7 @@ impl ParameterContents {
     }
 
     /// Returns the parameter value for use in a URL.
-    pub fn to_url_encoding(&self) -> Cow<str> {
+    pub fn to_url_encoding(&self) -> Cow<'_, str> {
         match self {
             ParameterContents::Bytes(bytes) => urlencoding::encode_binary(bytes),
             ParameterContents::LeafValue(SimpleValue::String(string)) => {
@@ -224,6 +233,16 @@ impl Display for ParameterContents {
             ParameterContents::Reference {
                 request_index,
                 parameter_name,
+                source_pointer: Some(pointer),
+                ..
+            } => write!(
+                f,
+                "parameter {parameter_name} at {pointer} from request {request_index}"
+            ),
+            ParameterContents::Reference {
+                request_index,
+                parameter_name,
+                ..
             } => write!(f, "parameter {parameter_name} from request {request_index}"),
         }
     }
@@ -0,0 +1,311 @@
+//! JSON Pointers (RFC 6901) to values nested in request bodies and responses.
+//!
+//! A reference to an earlier response names the value it uses. Values at the top level of
+//! the response (the fields of an object, or of the first object in an array) are found
+//! by their field name, as are cookies and the fields of posted bodies. Values nested
+//! deeper are found by a JSON Pointer into the response, such as `/data/items/3/id`, that
+//! the reference carries next to the name. References in a request body carry a second
+//! pointer: the location in the body their value is written to.
+
+use std::borrow::Cow;
+
+use openapiv3::{OpenAPI, Schema, SchemaKind, Type};
+use serde_json::Value;
+
+use super::parameter::ParameterContents;
+
+/// Depth up to which fields of schemas and values of responses are collected, as schemas
+/// can be recursive and responses large
+pub const MAX_POINTER_DEPTH: usize = 4;
+
+/// Returns whether a string is a JSON Pointer. The empty pointer points to the whole
+/// document.
+pub fn is_pointer(pointer: &str) -> bool {
+    pointer.is_empty() || pointer.starts_with('/')
+}
+
+/// Returns the pointer to a field or array index of the value `pointer` points to.
+pub fn push(pointer: &str, token: &str) -> String {
+    format!("{pointer}/{}", token.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Returns the unescaped tokens of a pointer.
+pub fn tokens(pointer: &str) -> impl Iterator<Item = String> + '_ {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+}
+
+/// Splits a pointer into its first token and the pointer to the rest.
+pub fn split_first(pointer: &str) -> Option<(String, &str)> {
+    let rest = pointer.strip_prefix('/')?;
+    let (first, rest) = match rest.find('/') {
+        Some(end) => rest.split_at(end),
+        None => (rest, ""),
+    };
+    Some((first.replace("~1", "/").replace("~0", "~"), rest))
+}
+
+/// The name of the field a pointer points to: its last token.
+pub fn field_name(pointer: &str) -> Cow<'_, str> {
+    match pointer.rsplit_once('/') {
+        Some((_, last)) => last.replace("~1", "/").replace("~0", "~").into(),
+        None => pointer.into(),
+    }
+}
+
+/// Returns the strings and numbers in a response with the pointer to each of them, up to
+/// `MAX_POINTER_DEPTH`.
+pub fn value_leaves(value: &Value) -> Vec<(String, &Value)> {
+    fn collect<'a>(value: &'a Value, pointer: String, leaves: &mut Vec<(String, &'a Value)>) {
+        let depth = pointer.matches('/').count();
+        match value {
+            Value::Object(fields) if depth < MAX_POINTER_DEPTH => {
+                for (field, value) in fields {
+                    collect(value, push(&pointer, field), leaves);
+                }
+            }
+            Value::Array(items) if depth < MAX_POINTER_DEPTH => {
+                for (index, item) in items.iter().enumerate() {
+                    collect(item, push(&pointer, &index.to_string()), leaves);
+                }
+            }
+            Value::String(_) | Value::Number(_) => leaves.push((pointer, value)),
+            _ => (),
+        }
+    }
+    let mut leaves = Vec::new();
+    collect(value, String::new(), &mut leaves);
+    leaves
+}
+
+/// A field of the values of a schema, such as a field of a request body or a response.
+pub struct SchemaField<'a> {
+    /// Name of the field
+    pub name: &'a str,
+    /// Pointer to the field if it is nested in another field, or `None` at the top level
+    pub pointer: Option<String>,
+    /// Name of the field that contains the field (or the array of objects it is in)
+    pub parent: Option<&'a str>,
+}
+
+/// Returns the fields of the values of a schema. Fields of an object, or of the items of an
+/// array, are at the top level. Fields of objects in those fields (or in arrays in those
+/// fields) are nested, up to `MAX_POINTER_DEPTH`. Pointers to fields in an array point into
+/// its first item.
+pub fn schema_fields<'a>(api: &'a OpenAPI, schema: &'a Schema) -> Vec<SchemaField<'a>> {
+    fn collect<'a>(
+        api: &'a OpenAPI,
+        schema: &'a Schema,
+        pointer: &str,
+        parent: Option<&'a str>,
+        fields: &mut Vec<SchemaField<'a>>,
+    ) {
+        if tokens(pointer).count() >= MAX_POINTER_DEPTH {
+            return;
+        }
+        for (name, property) in schema.properties_iter(api) {
+            let property = property.resolve(api);
+            let field_pointer = push(pointer, name);
+            fields.push(SchemaField {
+                name,
+                pointer: parent.map(|_| field_pointer.clone()),
+                parent,
+            });
+            match &property.kind {
+                SchemaKind::Type(Type::Array(array)) => {
+                    if let Some(items) = &array.items {
+                        let item_pointer = push(&field_pointer, "0");
+                        collect(api, items.resolve(api), &item_pointer, Some(name), fields)
+                    }
+                }
+                _ => collect(api, property, &field_pointer, Some(name), fields),
+            }
+        }
+    }
+
+    let mut fields = Vec::new();
+    match &schema.kind {
+        SchemaKind::Type(Type::Array(array)) => {
+            if let Some(items) = &array.items {
+                collect(api, items.resolve(api), "/0", None, &mut fields)
+            }
+        }
+        _ => collect(api, schema, "", None, &mut fields),
+    }
+    fields
+}
+
+impl ParameterContents {
+    /// Returns the value the pointer points to, if these contents have it.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut ParameterContents> {
+        if !is_pointer(pointer) {
+            return None;
+        }
+        tokens(pointer).try_fold(self, |contents, token| match contents {
+            ParameterContents::Object(fields) => fields.get_mut(&token),
+            ParameterContents::Array(items) => items.get_mut(token.parse::<usize>().ok()?),
+            _ => None,
+        })
+    }
+
+    /// Writes a value to the location the pointer points to. Fields that are missing on the
+    /// way are added as objects, but array items are not. Returns whether the location
+    /// could be reached.
+    pub fn insert_at(&mut self, pointer: &str, value: ParameterContents) -> bool {
+        let Some((token, rest)) = split_first(pointer) else {
+            if pointer.is_empty() {
+                *self = value;
+            }
+            return pointer.is_empty();
+        };
+        let next = match self {
+            ParameterContents::Object(fields) => fields
+                .entry(token)
+                .or_insert_with(|| ParameterContents::Object(Default::default())),
+            ParameterContents::Array(items) => match token.parse::<usize>() {
+                Ok(index) if index < items.len() => &mut items[index],
+                _ => return false,
+            },
+            _ => return false,
+        };
+        next.insert_at(rest, value)
+    }
+
+    /// Returns the values in these contents that are not objects or arrays, with the
+    /// pointer to each of them from `pointer`.
+    pub fn leaves(&self, pointer: String) -> Vec<(String, &ParameterContents)> {
+        fn collect<'a>(
+            contents: &'a ParameterContents,
+            pointer: String,
+            leaves: &mut Vec<(String, &'a ParameterContents)>,
+        ) {
+            match contents {
+                ParameterContents::Object(fields) => {
+                    for (field, value) in fields {
+                        collect(value, push(&pointer, field), leaves);
+                    }
+                }
+                ParameterContents::Array(items) => {
+                    for (index, item) in items.iter().enumerate() {
+                        collect(item, push(&pointer, &index.to_string()), leaves);
+                    }
+                }
+                leaf => leaves.push((pointer, leaf)),
+            }
+        }
+        let mut leaves = Vec::new();
+        collect(self, pointer, &mut leaves);
+        leaves
+    }
+
+    /// Like `leaves`, but returns mutable references.
+    pub fn leaves_mut(&mut self, pointer: String) -> Vec<(String, &mut ParameterContents)> {
+        fn collect<'a>(
+            contents: &'a mut ParameterContents,
+            pointer: String,
+            leaves: &mut Vec<(String, &'a mut ParameterContents)>,
+        ) {
+            match contents {
+                ParameterContents::Object(fields) => {
+                    for (field, value) in fields {
+                        collect(value, push(&pointer, field), leaves);
+                    }
+                }
+                ParameterContents::Array(items) => {
+                    for (index, item) in items.iter_mut().enumerate() {
+                        collect(item, push(&pointer, &index.to_string()), leaves);
+                    }
+                }
+                leaf => leaves.push((pointer, leaf)),
+            }
+        }
+        let mut leaves = Vec::new();
+        collect(self, pointer, &mut leaves);
+        leaves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{field_name, push, schema_fields, split_first, value_leaves};
+    use crate::{input::ParameterContents, parameter_feedback::ParameterFeedback};
+
+    #[test]
+    fn test_pointers() {
+        let pointer = push(&push("/data", "a/b"), "~id");
+        assert_eq!(pointer, "/data/a~1b/~0id");
+        assert_eq!(field_name(&pointer), "~id");
+        assert_eq!(
+            split_first(&pointer),
+            Some(("data".to_owned(), "/a~1b/~0id"))
+        );
+
+        let response = json!({"data": {"items": [{"id": 1}, {"id": 2, "tags": [true]}]}});
+        let leaves: Vec<_> = value_leaves(&response)
+            .into_iter()
+            .map(|(pointer, _)| pointer)
+            .collect();
+        assert_eq!(leaves, ["/data/items/0/id", "/data/items/1/id"]);
+
+        let mut body = ParameterContents::from(response);
+        let id = body.pointer_mut("/data/items/1/id").unwrap();
+        assert_eq!(id.to_value(), json!(2));
+        assert!(body.pointer_mut("/data/items/2/id").is_none());
+        assert!(body.pointer_mut("data").is_none());
+        assert_eq!(body.leaves(String::new()).len(), 3);
+
+        // Missing fields are added on the way, missing array items are not
+        assert!(body.insert_at("/data/owner/id", json!(5).into()));
+        assert_eq!(body.to_value()["data"]["owner"], json!({"id": 5}));
+        assert!(!body.insert_at("/data/items/2/id", json!(5).into()));
+        assert!(!body.insert_at("/data/owner/id/value", json!(5).into()));
+    }
+
+    #[test]
+    fn test_array_response_fields() {
+        let api: openapiv3::OpenAPI = serde_yaml::from_str(
+            r#"
+openapi: 3.0.0
+info: {title: Pets, version: "1"}
+paths: {}
+"#,
+        )
+        .unwrap();
+        let schema: openapiv3::Schema = serde_yaml::from_str(
+            r#"
+type: array
+items:
+  type: object
+  properties:
+    name: {type: string}
+    owner:
+      type: object
+      properties:
+        id: {type: integer}
+"#,
+        )
+        .unwrap();
+
+        // Fields of the items are at the top level, nested fields point into the first item
+        let fields: Vec<_> = schema_fields(&api, &schema)
+            .into_iter()
+            .map(|field| (field.name, field.pointer))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("name", None),
+                ("owner", None),
+                ("id", Some("/0/owner/id".to_owned()))
+            ]
+        );
+
+        let mut feedback = ParameterFeedback::new(1);
+        feedback.process_json(0, json!([{"name": "Tom", "owner": {"id": 5}}]));
+        assert_eq!(feedback.get(0, "id", Some("/0/owner/id")), Some(&json!(5)));
+    }
+}
@@ -11,7 +11,10 @@ use serde::{
     ser::{Serialize, Serializer},
 };
 
-use super::{parameter::ParameterKind, Body, Method, OpenApiRequest, ParameterContents};
+use super::{
+    media_type::MediaTypeHeaders, parameter::ParameterKind, Body, Method, OpenApiRequest,
+    ParameterContents,
+};
 
 pub(crate) fn serialize_bytes_to_b64<S>(bi: &[u8], serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -38,6 +41,8 @@ pub struct SerializableOpenApiRequest {
     body: Body,
     #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
     parameters: IndexMap<(String, ParameterKind), ParameterContents>,
+    #[serde(default, skip_serializing_if = "MediaTypeHeaders::is_default")]
+    media_type_headers: MediaTypeHeaders,
 }
 
 impl From<OpenApiRequest> for SerializableOpenApiRequest {
@@ -47,6 +52,7 @@ impl From<OpenApiRequest> for SerializableOpenApiRequest {
             path: request.path,
             body: request.body,
             parameters: request.parameters,
+            media_type_headers: request.media_type_headers,
         }
     }
 }
@@ -58,6 +64,7 @@ impl From<SerializableOpenApiRequest> for OpenApiRequest {
             path: request.path,
             body: request.body,
             parameters: request.parameters,
+            media_type_headers: request.media_type_headers,
         }
     }
 }
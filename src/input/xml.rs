@@ -0,0 +1,374 @@
+//! XML request bodies.
+//!
+//! The contents of an XML body are kept as [`ParameterContents`], like those of a JSON body,
+//! so they are mutated in the same way. How they are written as XML (the names of elements,
+//! namespaces, which properties are attributes and which arrays are wrapped) follows from the
+//! `xml` objects in the schema of the body, and is kept with the contents as an [`XmlShape`].
+//! A body can also carry a document type declaration and a reference to one of its entities,
+//! which the [`crate::openapi_mutator::xml_attack::XmlAttackMutator`] uses to attack the XML
+//! parser of the target.
+
+use std::borrow::Cow;
+
+use indexmap::IndexMap;
+use openapiv3::{MediaType, OpenAPI, Schema};
+
+use super::parameter::{ParameterContents, SimpleValue};
+use crate::openapi::xml::{array_items, reference_name, XmlObject};
+
+/// Name of the root element if the schema does not give one
+const DEFAULT_ROOT_NAME: &str = "root";
+/// Content type of bodies for which the specification gives no exact XML media type
+const DEFAULT_CONTENT_TYPE: &str = "application/xml";
+
+/// Depth up to which shapes are built, as schemas can be recursive
+const MAX_SHAPE_DEPTH: usize = 8;
+
+/// How a value and its fields are written as XML, as described by the `xml` objects of its
+/// schema. Fields that have no shape are written as elements named after the field.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct XmlShape {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub attribute: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub wrapped: bool,
+    /// Shapes of the fields of an object
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub properties: IndexMap<String, XmlShape>,
+    /// Shape of the items of an array
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Box<XmlShape>>,
+}
+
+impl XmlShape {
+    /// Builds the shape of values of the schema.
+    pub fn from_schema(api: &OpenAPI, schema: &Schema, depth: usize) -> Self {
+        let xml = XmlObject::of(schema);
+        let mut shape = Self {
+            name: xml.name,
+            namespace: xml.namespace,
+            prefix: xml.prefix,
+            attribute: xml.attribute,
+            wrapped: xml.wrapped,
+            ..Self::default()
+        };
+        if depth < MAX_SHAPE_DEPTH {
+            shape.properties = schema
+                .properties_iter(api)
+                .map(|(name, property)| {
+                    (
+                        name.clone(),
+                        Self::from_schema(api, property.resolve(api), depth + 1),
+                    )
+                })
+                .filter(|(_, shape)| !shape.is_default())
+                .collect();
+            shape.items = array_items(schema)
+                .map(|items| Self::from_schema(api, items.resolve(api), depth + 1))
+                .filter(|shape| !shape.is_default())
+                .map(Box::new);
+        }
+        shape
+    }
+
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The name of the element, qualified with the prefix of its namespace.
+    fn qualified_name<'a>(&'a self, default: &'a str) -> Cow<'a, str> {
+        let name = self.name.as_deref().unwrap_or(default);
+        match &self.prefix {
+            Some(prefix) => format!("{prefix}:{name}").into(),
+            None => name.into(),
+        }
+    }
+
+    /// The declaration of the namespace, if there is one.
+    fn namespace_declaration(&self) -> String {
+        match (&self.namespace, &self.prefix) {
+            (Some(namespace), Some(prefix)) => {
+                format!(" xmlns:{prefix}=\"{}\"", escape(namespace))
+            }
+            (Some(namespace), None) => format!(" xmlns=\"{}\"", escape(namespace)),
+            (None, _) => String::new(),
+        }
+    }
+}
+
+/// An XML body, of `application/xml`, `text/xml` or a `+xml` media type.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct XmlBody {
+    /// Media type the body is sent as, taken from the specification. Corpora from before it
+    /// was kept are sent as `application/xml`.
+    #[serde(default = "default_content_type")]
+    pub content_type: String,
+    /// The shape of the root element, which always has a name
+    pub shape: Box<XmlShape>,
+    pub contents: ParameterContents,
+    /// Document type declaration, written before the root element
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doctype: Option<String>,
+    /// Entity of the document type declaration that is referenced instead of the first text
+    /// of the body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<String>,
+}
+
+impl XmlBody {
+    /// Builds a body with the given contents, in the shape of the schema of the media type,
+    /// which is sent as `content_type`.
+    pub fn build(
+        api: &OpenAPI,
+        content_type: &str,
+        media_type: &MediaType,
+        contents: ParameterContents,
+    ) -> Self {
+        let mut shape = XmlShape::default();
+        if let Some(schema) = &media_type.schema {
+            let resolved = schema.resolve(api);
+            shape = XmlShape::from_schema(api, resolved, 0);
+            shape.name = shape
+                .name
+                .or_else(|| reference_name(schema).map(str::to_owned));
+            // Items of an array at the root are named after their schema too
+            if let Some(items) = array_items(resolved) {
+                if let Some(name) = reference_name(items) {
+                    let item_shape = shape.items.get_or_insert_with(Box::default);
+                    item_shape.name.get_or_insert_with(|| name.to_owned());
+                }
+            }
+        }
+        shape
+            .name
+            .get_or_insert_with(|| DEFAULT_ROOT_NAME.to_owned());
+        Self {
+            // Wildcards such as `application/*+xml` can not be sent
+            content_type: if content_type.contains('*') {
+                default_content_type()
+            } else {
+                content_type.to_owned()
+            },
+            shape: Box::new(shape),
+            contents,
+            doctype: None,
+            entity: None,
+        }
+    }
+
+    /// The qualified name of the root element.
+    pub fn root_name(&self) -> Cow<'_, str> {
+        self.shape.qualified_name(DEFAULT_ROOT_NAME)
+    }
+
+    /// Writes the body as an XML document.
+    pub fn encode(&self) -> String {
+        let mut writer = Writer {
+            xml: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"),
+            entity: self.entity.as_deref(),
+            depth: 0,
+        };
+        if let Some(doctype) = &self.doctype {
+            writer.xml += doctype;
+            writer.xml.push('\n');
+        }
+        writer.element(DEFAULT_ROOT_NAME, &self.shape, &self.contents);
+        writer.xml
+    }
+}
+
+fn default_content_type() -> String {
+    DEFAULT_CONTENT_TYPE.to_owned()
+}
+
+impl std::fmt::Display for XmlBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.contents)
+    }
+}
+
+struct Writer<'a> {
+    xml: String,
+    /// Entity that is still to be referenced
+    entity: Option<&'a str>,
+    /// Number of elements that are open
+    depth: usize,
+}
+
+impl Writer<'_> {
+    /// Writes an element for a value. `property` is the name of the property the value
+    /// belongs to, which is the default name of the element and of the items of an array.
+    fn element(&mut self, property: &str, shape: &XmlShape, contents: &ParameterContents) {
+        let name = shape.qualified_name(property);
+        self.xml += &format!("<{name}{}", shape.namespace_declaration());
+        self.depth += 1;
+        match contents {
+            ParameterContents::Object(fields) => {
+                let no_shape = XmlShape::default();
+                let field_shape = |field: &str| shape.properties.get(field).unwrap_or(&no_shape);
+                let is_attribute = |(field, value): &(&String, &ParameterContents)| {
+                    field_shape(field).attribute
+                        && matches!(
+                            value,
+                            ParameterContents::LeafValue(_) | ParameterContents::Bytes(_)
+                        )
+                };
+                for (field, value) in fields.iter().filter(is_attribute) {
+                    let field_shape = field_shape(field);
+                    self.xml += &format!(
+                        "{} {}=\"{}\"",
+                        field_shape.namespace_declaration(),
+                        field_shape.qualified_name(field),
+                        escape(&text(value))
+                    );
+                }
+                self.xml.push('>');
+                for (field, value) in fields.iter().filter(|field| !is_attribute(field)) {
+                    self.property(field, field_shape(field), value);
+                }
+            }
+            ParameterContents::Array(items) => {
+                self.xml.push('>');
+                let no_shape = XmlShape::default();
+                let item_shape = shape.items.as_deref().unwrap_or(&no_shape);
+                for item in items {
+                    self.element(property, item_shape, item);
+                }
+            }
+            ParameterContents::LeafValue(SimpleValue::String(_)) if self.entity.is_some() => {
+                self.xml += &format!(">&{};", self.entity.take().unwrap_or_default());
+            }
+            leaf => self.xml += &format!(">{}", escape(&text(leaf))),
+        }
+        // Without text in the body, the entity is referenced in the root element
+        self.depth -= 1;
+        if self.depth == 0 {
+            if let Some(entity) = self.entity.take() {
+                self.xml += &format!("&{entity};");
+            }
+        }
+        self.xml += &format!("</{name}>");
+    }
+
+    /// Writes a field of an object. The items of an array that is not wrapped are written
+    /// as elements of the object itself.
+    fn property(&mut self, property: &str, shape: &XmlShape, contents: &ParameterContents) {
+        match contents {
+            ParameterContents::Array(items) if !shape.wrapped => {
+                let no_shape = XmlShape::default();
+                let item_shape = shape.items.as_deref().unwrap_or(&no_shape);
+                for item in items {
+                    self.element(property, item_shape, item);
+                }
+            }
+            _ => self.element(property, shape, contents),
+        }
+    }
+}
+
+/// The text of a value in an element or attribute.
+fn text(contents: &ParameterContents) -> Cow<'_, str> {
+    match contents {
+        ParameterContents::LeafValue(SimpleValue::String(text)) => text.into(),
+        ParameterContents::LeafValue(SimpleValue::Null) => "".into(),
+        ParameterContents::Bytes(bytes) => String::from_utf8_lossy(bytes),
+        contents => contents.to_string().into(),
+    }
+}
+
+/// Escapes the characters that have a meaning in XML text and attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::XmlBody;
+    use crate::{
+        input::ParameterContents,
+        openapi::xml::{parse_response, preserve_xml_objects},
+    };
+
+    #[test]
+    fn test_xml_body() {
+        let mut document: serde_yaml::Value = serde_yaml::from_str(
+            r#"
+openapi: 3.0.0
+info: {title: Pets, version: "1"}
+paths: {}
+components:
+  schemas:
+    Pet:
+      type: object
+      xml: {namespace: "https://example.com/pets", prefix: p}
+      properties:
+        id: {type: integer, xml: {attribute: true}}
+        name: {type: string, xml: {name: petName}}
+        tags:
+          type: array
+          items: {type: string, xml: {name: tag}}
+        photos:
+          type: array
+          xml: {wrapped: true}
+          items: {type: string, xml: {name: url}}
+"#,
+        )
+        .unwrap();
+        preserve_xml_objects(&mut document);
+        let api: openapiv3::OpenAPI = serde_yaml::from_value(document).unwrap();
+        let media_type: openapiv3::MediaType =
+            serde_yaml::from_str("schema: {$ref: '#/components/schemas/Pet'}").unwrap();
+        // The fields are in alphabetical order
+        let value = json!({
+            "id": 7,
+            "name": "R&D <dog>",
+            "tags": ["good", "dog"],
+            "photos": ["a.png"],
+        });
+        let mut body = XmlBody::build(
+            &api,
+            "application/soap+xml",
+            &media_type,
+            ParameterContents::from(value.clone()),
+        );
+        let xml = body.encode();
+        assert!(xml.ends_with(
+            "<p:Pet xmlns:p=\"https://example.com/pets\" id=\"7\">\
+             <petName>R&amp;D &lt;dog&gt;</petName>\
+             <photos><url>a.png</url></photos>\
+             <tag>good</tag><tag>dog</tag>\
+             </p:Pet>"
+        ));
+        // The body reads back as the value it was built from
+        let schema = media_type.schema.as_ref().unwrap();
+        assert_eq!(parse_response(&api, schema, &xml).unwrap(), value);
+
+        // The shape and the attack survive serialization
+        body.doctype =
+            Some("<!DOCTYPE p:Pet [<!ENTITY xxe SYSTEM \"file:///etc/passwd\">]>".to_owned());
+        body.entity = Some("xxe".to_owned());
+        let body: XmlBody = serde_yaml::from_str(&serde_yaml::to_string(&body).unwrap()).unwrap();
+        let xml = body.encode();
+        assert!(xml.contains("]>\n<p:Pet"));
+        assert!(xml.contains("<petName>&xxe;</petName>"));
+        assert_eq!(body.content_type, "application/soap+xml");
+
+        // Bodies saved without a content type are sent as `application/xml`
+        let mut saved = serde_yaml::to_value(&body).unwrap();
+        saved.as_mapping_mut().unwrap().remove("content_type");
+        let body: XmlBody = serde_yaml::from_value(saved).unwrap();
+        assert_eq!(body.content_type, "application/xml");
+    }
+}
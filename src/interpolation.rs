@@ -0,0 +1,101 @@
+//! Environment variable interpolation in the YAML files given to the fuzzer (configuration,
+//! authentication and headers), so secrets and hosts that differ between environments do
+//! not have to be written into the files themselves.
+//!
+//! `${VAR}` is replaced by the value of the environment variable `VAR`, and it is an error if
+//! that variable is not set. `${VAR:-default}` is replaced by `default` if `VAR` is unset or
+//! empty. `$${` produces a literal `${`. Lines that only contain a comment are left alone.
+//!
+//! Substitution happens before the YAML is parsed, so a value that may contain characters
+//! with a special meaning in YAML (such as `: ` or ` #`) should be quoted: `"${PASSWORD}"`.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use serde::de::DeserializeOwned;
+
+lazy_static! {
+    static ref VARIABLE: Regex =
+        Regex::new(r"\$\$\{|\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").unwrap();
+}
+
+/// Reads the YAML file at `path`, interpolating environment variables.
+pub fn read_yaml_file<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    let contents = interpolate_env(&contents, |name| std::env::var(name).ok())
+        .with_context(|| format!("Could not interpolate {}", path.display()))?;
+    serde_yaml::from_str(&contents).with_context(|| format!("Could not parse {}", path.display()))
+}
+
+/// Replaces the variable references in `text`, looking up their values with `lookup`.
+fn interpolate_env(text: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        if line.trim_start().starts_with('#') {
+            result.push_str(line);
+            continue;
+        }
+        let mut last = 0;
+        for captures in VARIABLE.captures_iter(line) {
+            let whole = captures.get(0).unwrap();
+            result.push_str(&line[last..whole.start()]);
+            result.push_str(&substitute(&captures, &lookup)?);
+            last = whole.end();
+        }
+        result.push_str(&line[last..]);
+    }
+    Ok(result)
+}
+
+/// Produces the replacement for a single match of `VARIABLE`.
+fn substitute(captures: &Captures, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let Some(name) = captures.get(1) else {
+        // Escaped `$${`
+        return Ok("${".to_owned());
+    };
+    match (lookup(name.as_str()), captures.get(2)) {
+        (Some(value), Some(_)) if !value.is_empty() => Ok(value),
+        (_, Some(default)) => Ok(default.as_str().to_owned()),
+        (Some(value), None) => Ok(value),
+        (None, None) => Err(anyhow!(
+            "Environment variable {} is not set, and has no default",
+            name.as_str()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::interpolate_env;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOST" => Some("localhost".to_owned()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate_env() {
+        assert_eq!(
+            interpolate_env("host: ${HOST}:8080\n", lookup).unwrap(),
+            "host: localhost:8080\n"
+        );
+        assert_eq!(
+            interpolate_env("a: ${MISSING:-x}\nb: ${EMPTY:-y}\nc: ${HOST:-z}", lookup).unwrap(),
+            "a: x\nb: y\nc: localhost"
+        );
+        assert_eq!(
+            interpolate_env("literal: $${HOST}", lookup).unwrap(),
+            "literal: ${HOST}"
+        );
+        assert_eq!(
+            interpolate_env("# password: ${MISSING}\n", lookup).unwrap(),
+            "# password: ${MISSING}\n"
+        );
+        assert!(interpolate_env("password: ${MISSING}", lookup).is_err());
+    }
+}
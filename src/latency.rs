@@ -0,0 +1,147 @@
+//! Detection of requests that take much longer than usual.
+//!
+//! Requests that make the target evaluate an expensive regular expression (ReDoS) or run a
+//! query per item (N+1 queries) still get a response, but take much longer than other requests
+//! to the same operation. Each worker keeps a baseline of the latency of every operation (see
+//! [`crate::anomaly`]). A request is slow when it takes more than `--latency-multiple` times the
+//! median of its operation and more than that many MADs above it, or longer than
+//! `--latency-threshold`. The input is then saved as a crash, with the slow request and its
+//! timing in the metadata of the crash.
+
+use std::{num::NonZeroU32, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    anomaly::{AnomalyFeedback, OperationBaselines},
+    configuration::Configuration,
+    input::Method,
+};
+
+/// Metadata of a crash caused by a slow request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowRequestMetadata {
+    /// Index of the slow request in the input
+    pub request_index: usize,
+    /// Operation of the slow request
+    pub method: Method,
+    pub path: String,
+    /// Time until the response was received, in milliseconds
+    pub latency_ms: f64,
+    /// Median latency of the operation before this request, if it was known
+    pub median_ms: Option<f64>,
+    /// Median absolute deviation of the latency of the operation, if it was known
+    pub mad_ms: Option<f64>,
+}
+
+libafl_bolts::impl_serdeany!(SlowRequestMetadata);
+
+/// The latency baselines of all operations.
+pub struct LatencyTracker {
+    multiple: Option<NonZeroU32>,
+    threshold: Option<Duration>,
+    /// Latencies in microseconds
+    baselines: OperationBaselines,
+}
+
+impl LatencyTracker {
+    pub fn new(multiple: Option<NonZeroU32>, threshold: Option<Duration>) -> Self {
+        Self {
+            multiple,
+            threshold,
+            baselines: OperationBaselines::default(),
+        }
+    }
+
+    pub fn from_config(config: &Configuration) -> Self {
+        Self::new(
+            config.latency_multiple,
+            config
+                .latency_threshold
+                .map(|threshold| Duration::from_millis(threshold.get())),
+        )
+    }
+
+    /// The latency baselines, to save them with the campaign state.
+    pub fn baselines(&self) -> &OperationBaselines {
+        &self.baselines
+    }
+
+    /// Continues with the baselines of a saved campaign.
+    pub fn restore_baselines(&mut self, baselines: OperationBaselines) {
+        self.baselines = baselines;
+    }
+
+    /// Whether slow requests are reported at all.
+    pub fn is_enabled(&self) -> bool {
+        self.multiple.is_some() || self.threshold.is_some()
+    }
+
+    /// Adds the latency of a request to the baseline of its operation. Returns the details of
+    /// the request if it was slow compared to the baseline before it, or to the threshold.
+    pub fn observe(
+        &mut self,
+        request_index: usize,
+        method: Method,
+        path: &str,
+        latency: Duration,
+    ) -> Option<SlowRequestMetadata> {
+        let micros = latency.as_micros() as u64;
+        let baseline = self.baselines.observe(method, path, micros);
+        let above_baseline = self
+            .multiple
+            .zip(baseline)
+            .is_some_and(|(multiple, baseline)| baseline.is_exceeded(micros, multiple));
+        let above_threshold = self.threshold.is_some_and(|threshold| latency > threshold);
+        (above_baseline || above_threshold).then(|| SlowRequestMetadata {
+            request_index,
+            method,
+            path: path.to_owned(),
+            latency_ms: as_millis(micros),
+            median_ms: baseline.map(|baseline| as_millis(baseline.median)),
+            mad_ms: baseline.map(|baseline| as_millis(baseline.mad)),
+        })
+    }
+}
+
+fn as_millis(micros: u64) -> f64 {
+    micros as f64 / 1000.0
+}
+
+/// Objective for inputs with a slow request.
+pub fn slow_request_objective() -> AnomalyFeedback<SlowRequestMetadata> {
+    AnomalyFeedback::new("SlowRequestFeedback")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroU32, time::Duration};
+
+    use super::LatencyTracker;
+    use crate::input::Method;
+
+    #[test]
+    fn test_slow_requests() {
+        let mut tracker = LatencyTracker::new(NonZeroU32::new(20), None);
+        let observe = |tracker: &mut LatencyTracker, millis| {
+            tracker.observe(0, Method::Get, "/items", Duration::from_millis(millis))
+        };
+        // Not slow before there is a baseline
+        assert!(observe(&mut tracker, 500).is_none());
+        for millis in [10, 12, 9, 11, 10, 10, 13, 8, 10] {
+            assert!(observe(&mut tracker, millis).is_none());
+        }
+        assert!(observe(&mut tracker, 150).is_none());
+        let slow = observe(&mut tracker, 300).unwrap();
+        assert_eq!(slow.latency_ms, 300.0);
+        assert_eq!(slow.median_ms, Some(10.0));
+        // Other operations have their own baseline
+        assert!(tracker
+            .observe(0, Method::Post, "/items", Duration::from_millis(300))
+            .is_none());
+
+        let mut tracker = LatencyTracker::new(None, Some(Duration::from_millis(100)));
+        assert!(observe(&mut tracker, 100).is_none());
+        assert!(observe(&mut tracker, 101).unwrap().median_ms.is_none());
+    }
+}
@@ -41,25 +41,46 @@ use env_logger::{Builder, Env};
 use libafl::Fuzzer; // This may be marked unused, but will make the compiler give you crucial error messages
 use log::warn;
 
+mod anomaly;
 mod authentication;
+mod broker;
+mod concurrent_stage;
 mod configuration;
 pub mod coverage_clients;
+mod crash_buckets;
+mod crash_rules;
+#[allow(dead_code)]
 mod debug_writer;
 pub mod executor;
 mod fuzzer;
+mod har;
 pub mod header;
 mod initial_corpus;
 mod input;
+mod interpolation;
+mod latency;
 pub mod monitors;
 mod openapi;
 pub mod openapi_mutator;
+mod output;
 mod parameter_feedback;
+mod rate_limit;
 mod reporting;
 mod reproducer;
+mod reset;
+mod response_size;
+mod resume;
 mod state;
+mod target;
+mod transcript;
+mod transport_failure;
+mod validate_config;
 mod wuppie_version;
 
-use crate::{configuration::Configuration, openapi::get_api_spec};
+use crate::{
+    configuration::Configuration,
+    openapi::{get_api_spec, get_target_api_spec},
+};
 
 /// The entry point. Dispatches to other modules based on the CLI command.
 #[allow(clippy::unit_arg)]
@@ -72,19 +93,38 @@ pub fn main() -> Result<()> {
         Commands::VerifyAuth { .. } => {
             let config = &Configuration::get().map_err(anyhow::Error::msg)?;
             setup_logging(config);
-            let api = get_api_spec(config.openapi_spec.as_ref().unwrap())?;
+            let api = get_target_api_spec(config)?;
             authentication::verify_authentication(*api)
         }
+        Commands::ValidateConfig { .. } => validate_config::validate_config(),
         Commands::OutputCorpus {
             corpus_directory,
             openapi_spec,
             report_path,
-        } => Ok(initial_corpus::generate_corpus_to_files(
-            &*get_api_spec(openapi_spec)?,
-            corpus_directory,
-            report_path.as_deref(),
-        )),
+            seed,
+        } => {
+            let seed = seed.unwrap_or_else(libafl_bolts::current_nanos);
+            log::info!("Generating the corpus with seed {seed}");
+            initial_corpus::generate_corpus_to_files(
+                &*get_api_spec(openapi_spec)?,
+                corpus_directory,
+                report_path.as_deref(),
+                seed,
+            );
+            Ok(())
+        }
         Commands::Reproduce { crash_file, .. } => reproducer::reproduce(crash_file),
+        Commands::Triage { crash_buckets } => crash_buckets::triage(crash_buckets),
+        Commands::ImportHar {
+            har_file,
+            corpus_directory,
+            openapi_spec,
+        } => har::import_to_corpus(&*get_api_spec(openapi_spec)?, har_file, corpus_directory),
+        Commands::ExportHar {
+            input_file,
+            har_file,
+            ..
+        } => har::export(input_file, har_file),
         Commands::Fuzz { .. } => fuzzer::fuzz(),
     }
 }
@@ -105,27 +145,37 @@ pub fn setup_logging(clargs: &Configuration) {
     }
 }
 
-/// Initializes the authentication module and cookie store and builds a Reqwest HTTP client
-fn build_http_client() -> Result<
+/// Initializes the authentication module and cookie store and builds a blocking and an
+/// asynchronous Reqwest HTTP client, which share the cookie store and default headers
+fn build_http_client(
+    config: &Configuration,
+) -> Result<
     (
         authentication::Authentication,
         Arc<reqwest_cookie_store::CookieStoreMutex>,
         reqwest::blocking::Client,
+        reqwest::Client,
     ),
     anyhow::Error,
 > {
     // Load auth information from the configuration
-    let mut authentication = authentication::initialize()?;
+    let mut authentication =
+        authentication::initialize_from_config(config.authentication.as_deref())?;
     // Make a cookie jar for our client
     let cookie_store = std::sync::Arc::new(reqwest_cookie_store::CookieStoreMutex::new(
         reqwest_cookie_store::CookieStore::default(),
     ));
-    // Construct a client with the authentication and static headers
-    let client_builder =
-        reqwest::blocking::Client::builder().cookie_provider(std::sync::Arc::clone(&cookie_store));
+    // Construct the clients with the authentication and static headers
     let mut default_headers = authentication.generate_headers();
-    default_headers.extend(header::get_default_headers()?);
-    let client = client_builder.default_headers(default_headers).build()?;
+    default_headers.extend(header::get_default_headers(config)?);
+    let client = reqwest::blocking::Client::builder()
+        .cookie_provider(std::sync::Arc::clone(&cookie_store))
+        .default_headers(default_headers.clone())
+        .build()?;
+    let async_client = reqwest::Client::builder()
+        .cookie_provider(std::sync::Arc::clone(&cookie_store))
+        .default_headers(default_headers)
+        .build()?;
 
-    Ok((authentication, cookie_store, client))
+    Ok((authentication, cookie_store, client, async_client))
 }
@@ -2,7 +2,11 @@
 //! while fuzzing, and tracks the time consumed.
 
 use core::{time, time::Duration};
-use std::{borrow::Cow, fmt};
+use std::{
+    borrow::Cow,
+    fmt,
+    sync::{Arc, Mutex},
+};
 
 use libafl::{
     alloc::fmt::Debug,
@@ -72,10 +76,10 @@ where
                 "objectives": self.objective_size(),
                 "executed_sequences": self.total_execs(),
                 "sequences_per_sec": self.req_execs_per_sec(self.total_execs()),
-                "requests": Self::req_stats(&self.client_stats()[0], &UserStats::new(UserStatsValue::String(Cow::Borrowed("unknown")), AggregatorOps::None)),
-                "requests_per_sec": Self::req_sec_stats(&self.client_stats()[0], &UserStats::new(UserStatsValue::Number(0), AggregatorOps::None), total_time.as_secs().try_into().unwrap()),
-                "coverage": Self::cov_stats(&self.client_stats()[0], &UserStats::new(UserStatsValue::String(Cow::Borrowed("unknown")), AggregatorOps::None)),
-                "endpoint_coverage": Self::end_cov_stats(&self.client_stats()[0], &UserStats::new(UserStatsValue::String(Cow::Borrowed("unknown")), AggregatorOps::None)),
+                "requests": Self::req_stats(self.client_stats()),
+                "requests_per_sec": Self::req_sec_stats(self.client_stats(), total_time.as_secs().try_into().unwrap()),
+                "coverage": Self::cov_stats(self.client_stats()),
+                "endpoint_coverage": Self::end_cov_stats(self.client_stats()),
             })
             .to_string(),
             OutputFormat::HumanReadable => {
@@ -109,10 +113,10 @@ where
                     self.objective_size(),
                     self.total_execs(),
                     self.req_execs_per_sec(self.total_execs()),
-                    Self::req_stats(&self.client_stats()[0], &UserStats::new(UserStatsValue::Number(0), AggregatorOps::None)),
-                    Self::req_sec_stats(&self.client_stats()[0], &UserStats::new(UserStatsValue::Number(0), AggregatorOps::None), total_time.as_secs().try_into().unwrap()),
-                    Self::cov_stats(&self.client_stats()[0], &UserStats::new(UserStatsValue::String(Cow::Borrowed("unknown")), AggregatorOps::None)),
-                    Self::end_cov_stats(&self.client_stats()[0], &UserStats::new(UserStatsValue::String(Cow::Borrowed("unknown")), AggregatorOps::None)),
+                    Self::req_stats(self.client_stats()),
+                    Self::req_sec_stats(self.client_stats(), total_time.as_secs().try_into().unwrap()),
+                    Self::cov_stats(self.client_stats()),
+                    Self::end_cov_stats(self.client_stats()),
                 )
             }
         }};
@@ -146,8 +150,16 @@ where
         }
     }
 
-    fn req_stats<'a>(client_stats: &'a ClientStats, default: &'a UserStats) -> &'a UserStats {
-        client_stats.get_user_stats("requests").unwrap_or(default)
+    /// The number of requests performed, summed over all workers.
+    fn req_stats(client_stats: &[ClientStats]) -> UserStats {
+        let requests = client_stats
+            .iter()
+            .filter_map(|client| match client.get_user_stats("requests")?.value() {
+                UserStatsValue::Number(requests) => Some(*requests),
+                _ => None,
+            })
+            .sum();
+        UserStats::new(UserStatsValue::Number(requests), AggregatorOps::Sum)
     }
 
     fn req_execs_per_sec(&mut self, execs: u64) -> String {
@@ -158,15 +170,9 @@ where
         self.execs_per_sec.clone()
     }
 
-    fn req_sec_stats<'a>(
-        client_stats: &'a ClientStats,
-        default: &'a UserStats,
-        secs: usize,
-    ) -> UserStats {
+    fn req_sec_stats(client_stats: &[ClientStats], secs: usize) -> UserStats {
         UserStats::new(
-            client_stats
-                .get_user_stats("requests")
-                .unwrap_or(default)
+            Self::req_stats(client_stats)
                 .value()
                 .clone()
                 .stats_div(secs)
@@ -175,15 +181,97 @@ where
         )
     }
 
-    fn cov_stats<'a>(client_stats: &'a ClientStats, default: &'a UserStats) -> &'a UserStats {
-        client_stats
-            .get_user_stats("wuppiefuzz_code_coverage")
-            .unwrap_or(default)
+    fn cov_stats(client_stats: &[ClientStats]) -> UserStats {
+        Self::best_ratio(client_stats, "wuppiefuzz_code_coverage")
     }
 
-    fn end_cov_stats<'a>(client_stats: &'a ClientStats, default: &'a UserStats) -> &'a UserStats {
+    fn end_cov_stats(client_stats: &[ClientStats]) -> UserStats {
+        Self::best_ratio(client_stats, "wuppiefuzz_endpoint_coverage")
+    }
+
+    /// The highest coverage ratio called `name` that any of the workers reached.
+    fn best_ratio(client_stats: &[ClientStats], name: &str) -> UserStats {
         client_stats
-            .get_user_stats("wuppiefuzz_endpoint_coverage")
-            .unwrap_or(default)
+            .iter()
+            .filter_map(|client| client.get_user_stats(name))
+            .max_by_key(|stats| match stats.value() {
+                UserStatsValue::Ratio(covered, _) => *covered,
+                _ => 0,
+            })
+            .cloned()
+            .unwrap_or_else(|| {
+                UserStats::new(
+                    UserStatsValue::String(Cow::Borrowed("unknown")),
+                    AggregatorOps::None,
+                )
+            })
+    }
+}
+
+/// Monitor for a single worker when fuzzing with several workers in parallel.
+///
+/// Each worker has its own event manager, which only knows about that worker. This monitor
+/// keeps the stats of its worker and forwards them to the monitor shared by all workers,
+/// which then displays the stats combined over all workers.
+pub struct WorkerMonitor<M> {
+    worker: ClientId,
+    shared: Arc<Mutex<M>>,
+    client_stats: Vec<ClientStats>,
+    start_time: Duration,
+}
+
+impl<M> WorkerMonitor<M>
+where
+    M: Monitor,
+{
+    /// Creates the monitor for worker number `worker`, forwarding to `shared`.
+    pub fn new(worker: usize, shared: Arc<Mutex<M>>) -> Self {
+        let start_time = shared.lock().unwrap().start_time();
+        Self {
+            worker: ClientId(worker as u32),
+            shared,
+            client_stats: vec![],
+            start_time,
+        }
+    }
+}
+
+impl<M> Debug for WorkerMonitor<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorkerMonitor")
+            .field("worker", &self.worker)
+            .field("client_stats", &self.client_stats)
+            .finish()
+    }
+}
+
+impl<M> Monitor for WorkerMonitor<M>
+where
+    M: Monitor,
+{
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        &mut self.client_stats
+    }
+
+    fn client_stats(&self) -> &[ClientStats] {
+        &self.client_stats
+    }
+
+    fn start_time(&self) -> time::Duration {
+        self.start_time
+    }
+
+    fn set_start_time(&mut self, time: time::Duration) {
+        self.start_time = time
+    }
+
+    fn display(&mut self, event_msg: &str, _sender_id: ClientId) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.client_stats_insert(self.worker);
+        // The event manager of a worker always reports as client 0
+        if let Some(client_stats) = self.client_stats.first() {
+            *shared.client_stats_mut_for(self.worker) = client_stats.clone();
+        }
+        shared.display(event_msg, self.worker);
     }
 }
@@ -11,15 +11,23 @@ pub fn build_request_from_input(
     api: &OpenAPI,
     input: &OpenApiRequest,
 ) -> Option<reqwest::blocking::RequestBuilder> {
+    // The server to send requests to is selected when loading the specification
     let server = &api
-        .servers.first()
-        .expect("API specification contains no usable servers. If you did specify any, consult logs for attempts to connect to them.");
+        .servers
+        .first()
+        .expect("The server is selected when loading the specification");
     let mut path = server.url.to_owned() + &input.path;
     let mut header_params = HeaderMap::new();
-    header_params.insert(
-        reqwest::header::ACCEPT,
-        HeaderValue::from_static("application/json"),
-    );
+    // The input may declare other media types than those of its body, or leave them out
+    if let Some(Ok(accept)) = input.accept().map(HeaderValue::from_str) {
+        header_params.insert(reqwest::header::ACCEPT, accept);
+    }
+    if let Some(Ok(content_type)) = input
+        .content_type()
+        .map(|content_type| HeaderValue::from_str(&content_type))
+    {
+        header_params.insert(reqwest::header::CONTENT_TYPE, content_type);
+    }
     let mut query_params = Vec::new();
     let mut cookie_params = Vec::new();
     for ((name, kind), value) in input // voor elke parameter in openapirequest
@@ -64,9 +72,7 @@ pub fn build_request_from_input(
         .request(input.method.into(), path_with_query_params)
         .headers(header_params);
     if let Some(contents) = input.reqwest_body() {
-        builder = builder
-            .body(contents)
-            .header(reqwest::header::CONTENT_TYPE, input.body_content_type());
+        builder = builder.body(contents);
     }
     Some(builder)
 }
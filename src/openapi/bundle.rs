@@ -0,0 +1,430 @@
+//! Loading of specifications that are split over several files.
+//!
+//! References to other files (such as `./schemas/pet.yaml#/Pet`) are resolved relative to
+//! the file they appear in, and the specification is bundled into a single document before
+//! it is parsed. Referenced schemas are added to the schemas in the components of the
+//! specification, so schemas that refer to each other keep working. Everything else (path
+//! items, parameters, responses, ...) is inlined where it is referenced.
+//!
+//! Files whose name ends in `.json` are parsed as JSON, all others as YAML.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use openapiv3::{OpenAPI, VersionedOpenAPI};
+use serde_yaml::{Mapping, Value};
+use url::Url;
+
+/// Loads the specification at `path` (or http(s) URL), including the files it refers to.
+pub fn load_spec(path: &Path) -> Result<OpenAPI> {
+    let root = spec_location(path)?;
+    let mut document = read_document(&root)?;
+    let mut bundler = Bundler::new(&root, &document);
+    bundler.walk(&mut document, &root, false)?;
+    bundler.add_schemas(&mut document);
+    super::xml::preserve_xml_objects(&mut document);
+    let open_api: VersionedOpenAPI = serde_yaml::from_value(document)?;
+    Ok(open_api.upgrade())
+}
+
+/// Resolves a JSON pointer such as `/components/schemas/Pet` in the document.
+pub fn resolve_pointer<'a>(document: &'a Value, pointer: &str) -> Option<&'a Value> {
+    if pointer.is_empty() {
+        return Some(document);
+    }
+    pointer
+        .strip_prefix('/')?
+        .split('/')
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .try_fold(document, |value, segment| match value {
+            // Keys need not be strings, e.g. status codes of responses
+            Value::Mapping(mapping) => mapping.iter().find_map(|(key, value)| {
+                let matches = match key {
+                    Value::String(key) => *key == segment,
+                    Value::Number(key) => key.to_string() == segment,
+                    _ => false,
+                };
+                matches.then_some(value)
+            }),
+            Value::Sequence(sequence) => sequence.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+/// Parses the text of a specification file, as JSON or YAML depending on its name.
+pub fn parse_document(text: &str, url: &Url) -> Result<Value> {
+    if url.path().ends_with(".json") {
+        Ok(serde_json::from_str(text)?)
+    } else {
+        Ok(serde_yaml::from_str(text)?)
+    }
+}
+
+/// Reads and parses the file (or http(s) resource) at `url`.
+fn read_document(url: &Url) -> Result<Value> {
+    let text = read_url(url).with_context(|| format!("Could not read {}", describe(url)))?;
+    parse_document(&text, url).with_context(|| format!("Could not parse {}", describe(url)))
+}
+
+/// Returns the URL of the location the specification is loaded from, against which
+/// relative references and server URLs are resolved.
+pub fn spec_location(path: &Path) -> Result<Url> {
+    if let Some(url) = remote_spec_url(path) {
+        return Ok(url);
+    }
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Could not find {}", path.display()))?;
+    Url::from_file_path(&path).map_err(|()| anyhow!("Invalid path {}", path.display()))
+}
+
+fn remote_spec_url(path: &Path) -> Option<Url> {
+    let url = Url::parse(path.to_str()?).ok()?;
+    matches!(url.scheme(), "http" | "https").then_some(url)
+}
+
+/// Reads the text of the file or http(s) resource at `url`.
+pub fn read_url(url: &Url) -> Result<String> {
+    match url.scheme() {
+        "http" | "https" => Ok(reqwest::blocking::get(url.clone())?
+            .error_for_status()?
+            .text()?),
+        "file" => {
+            let path = url
+                .to_file_path()
+                .map_err(|()| anyhow!("Invalid file URL {url}"))?;
+            Ok(std::fs::read_to_string(path)?)
+        }
+        scheme => bail!("Unsupported URL scheme {scheme} in {url}"),
+    }
+}
+
+/// Formats the location of a file for messages: a path for local files, the URL otherwise.
+fn describe(url: &Url) -> String {
+    match url.to_file_path() {
+        Ok(path) if url.scheme() == "file" => path.display().to_string(),
+        _ => url.to_string(),
+    }
+}
+
+/// Splits a reference into the URL of the file it refers to and a JSON pointer.
+fn split_reference(base: &Url, reference: &str) -> Result<(Url, String)> {
+    let (file, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+    let url = if file.is_empty() {
+        base.clone()
+    } else {
+        base.join(file)
+            .with_context(|| format!("Invalid reference {reference}"))?
+    };
+    Ok((url, pointer.to_owned()))
+}
+
+/// Creates a `$ref` to a location in the bundled document.
+fn local_reference(pointer: &str) -> Value {
+    let mut mapping = Mapping::new();
+    mapping.insert("$ref".into(), format!("#{pointer}").into());
+    Value::Mapping(mapping)
+}
+
+/// The location a reference eventually refers to.
+enum Target {
+    /// A location in the root document, which stays where it is
+    Root(String),
+    /// A location in another file
+    External(Url, String),
+}
+
+struct Bundler {
+    root: Url,
+    /// Path of the schemas in the root document: `/components/schemas` for OpenAPI 3 and
+    /// `/definitions` for Swagger 2
+    schemas_pointer: &'static str,
+    /// Files read so far
+    documents: HashMap<Url, Value>,
+    /// Names of the schemas taken from other files, by their location
+    schema_names: HashMap<(Url, String), String>,
+    /// Schemas taken from other files, to be added to the root document
+    schemas: Vec<(String, Value)>,
+    /// Schema names in use, in the root document or by schemas taken from other files
+    taken_names: HashSet<String>,
+    /// Locations that are being inlined, to detect cycles
+    inlining: Vec<(Url, String)>,
+}
+
+impl Bundler {
+    fn new(root: &Url, document: &Value) -> Self {
+        let schemas_pointer = if document.get("swagger").is_some() {
+            "/definitions"
+        } else {
+            "/components/schemas"
+        };
+        let taken_names = resolve_pointer(document, schemas_pointer)
+            .and_then(Value::as_mapping)
+            .into_iter()
+            .flat_map(|schemas| schemas.keys().filter_map(Value::as_str))
+            .map(str::to_owned)
+            .collect();
+        Self {
+            root: root.clone(),
+            schemas_pointer,
+            documents: HashMap::new(),
+            schema_names: HashMap::new(),
+            schemas: vec![],
+            taken_names,
+            inlining: vec![],
+        }
+    }
+
+    /// Replaces all references to other files in `value`, which comes from the file at
+    /// `base`. `in_schema` tells whether `value` is (part of) a schema.
+    fn walk(&mut self, value: &mut Value, base: &Url, in_schema: bool) -> Result<()> {
+        match value {
+            Value::Mapping(mapping) => {
+                if let Some(reference) = mapping.get("$ref").and_then(Value::as_str) {
+                    let reference = reference.to_owned();
+                    let bundled = self
+                        .bundle_reference(&reference, base, in_schema)
+                        .with_context(|| {
+                            format!("Could not resolve {reference} in {}", describe(base))
+                        })?;
+                    if let Some(bundled) = bundled {
+                        *value = bundled;
+                    }
+                    return Ok(());
+                }
+                for (key, child) in mapping.iter_mut() {
+                    // `schemas` and `definitions` hold the schemas in the components
+                    let child_in_schema = in_schema
+                        || matches!(key.as_str(), Some("schema" | "schemas" | "definitions"));
+                    self.walk(child, base, child_in_schema)?;
+                }
+            }
+            Value::Sequence(sequence) => {
+                for child in sequence {
+                    self.walk(child, base, in_schema)?;
+                }
+            }
+            Value::Tagged(tagged) => self.walk(&mut tagged.value, base, in_schema)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Produces the value that replaces a reference, or `None` if the reference can stay
+    /// as it is.
+    fn bundle_reference(
+        &mut self,
+        reference: &str,
+        base: &Url,
+        in_schema: bool,
+    ) -> Result<Option<Value>> {
+        let (url, pointer) = split_reference(base, reference)?;
+        if url == self.root && *base == self.root {
+            return Ok(None);
+        }
+        let (url, pointer) = match self.follow(url, pointer)? {
+            Target::Root(pointer) => return Ok(Some(local_reference(&pointer))),
+            Target::External(url, pointer) => (url, pointer),
+        };
+        let location = (url, pointer);
+
+        if in_schema {
+            let name = match self.schema_names.get(&location) {
+                Some(name) => name.clone(),
+                None => {
+                    let name = self.schema_name(&location.0, &location.1);
+                    // Registered before descending, so schemas can refer to themselves
+                    self.schema_names.insert(location.clone(), name.clone());
+                    let mut schema = self.lookup(&location.0, &location.1)?;
+                    self.walk(&mut schema, &location.0, true)?;
+                    self.schemas.push((name.clone(), schema));
+                    name
+                }
+            };
+            return Ok(Some(local_reference(&format!(
+                "{}/{name}",
+                self.schemas_pointer
+            ))));
+        }
+
+        if self.inlining.contains(&location) {
+            bail!(
+                "Circular reference to {}#{}",
+                describe(&location.0),
+                location.1
+            );
+        }
+        let mut value = self.lookup(&location.0, &location.1)?;
+        let url = location.0.clone();
+        self.inlining.push(location);
+        self.walk(&mut value, &url, false)?;
+        self.inlining.pop();
+        Ok(Some(value))
+    }
+
+    /// Follows references to references, to the location of the value they refer to.
+    fn follow(&mut self, mut url: Url, mut pointer: String) -> Result<Target> {
+        let mut seen = HashSet::new();
+        loop {
+            if url == self.root {
+                return Ok(Target::Root(pointer));
+            }
+            if !seen.insert((url.clone(), pointer.clone())) {
+                bail!("Circular reference to {}#{pointer}", describe(&url));
+            }
+            let value = self.lookup(&url, &pointer)?;
+            match value.get("$ref").and_then(Value::as_str) {
+                Some(reference) => (url, pointer) = split_reference(&url, reference)?,
+                None => return Ok(Target::External(url, pointer)),
+            }
+        }
+    }
+
+    /// Returns (a copy of) the value at `pointer` in the file at `url`.
+    fn lookup(&mut self, url: &Url, pointer: &str) -> Result<Value> {
+        if !self.documents.contains_key(url) {
+            let document = read_document(url)?;
+            self.documents.insert(url.clone(), document);
+        }
+        resolve_pointer(&self.documents[url], pointer)
+            .cloned()
+            .ok_or_else(|| anyhow!("JSON pointer {pointer} not found in {}", describe(url)))
+    }
+
+    /// Picks an unused name for the schema at `pointer` in the file at `url`: the last
+    /// segment of the pointer, or the name of the file if the pointer is empty.
+    fn schema_name(&mut self, url: &Url, pointer: &str) -> String {
+        let name = match pointer.rsplit('/').next().filter(|name| !name.is_empty()) {
+            Some(name) => name.replace("~1", "/").replace("~0", "~"),
+            None => Path::new(url.path())
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "schema".to_owned()),
+        };
+        // Component names may only contain these characters
+        let name: String = name
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+                _ => '_',
+            })
+            .collect();
+        let mut candidate = name.clone();
+        let mut suffix = 2;
+        while !self.taken_names.insert(candidate.clone()) {
+            candidate = format!("{name}_{suffix}");
+            suffix += 1;
+        }
+        candidate
+    }
+
+    /// Adds the schemas taken from other files to the root document.
+    fn add_schemas(self, document: &mut Value) {
+        if self.schemas.is_empty() {
+            return;
+        }
+        let mut parent = document;
+        for segment in self.schemas_pointer.split('/').skip(1) {
+            if !parent.get(segment).is_some_and(Value::is_mapping) {
+                parent[segment] = Value::Mapping(Mapping::new());
+            }
+            parent = &mut parent[segment];
+        }
+        for (name, schema) in self.schemas {
+            parent[name.as_str()] = schema;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use openapiv3::RefOr;
+
+    use super::load_spec;
+
+    #[test]
+    fn test_load_spec() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("schemas")).unwrap();
+        fs::write(
+            dir.path().join("openapi.yaml"),
+            "
+openapi: 3.0.0
+info:
+  title: Pets
+  version: 1.0.0
+paths:
+  /pets:
+    $ref: './paths.yaml#/pets'
+",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("paths.yaml"),
+            "
+pets:
+  get:
+    parameters:
+      - $ref: '#/limit'
+    responses:
+      200:
+        description: A pet
+        content:
+          application/json:
+            schema:
+              $ref: './schemas/pet.yaml#/Pet'
+limit:
+  name: limit
+  in: query
+  schema:
+    type: integer
+",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("schemas/pet.yaml"),
+            "
+Pet:
+  type: object
+  properties:
+    owner:
+      $ref: './owner.json'
+",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("schemas/owner.json"),
+            r##"{"type": "object", "properties": {"pets": {"type": "array", "items": {"$ref": "pet.yaml#/Pet"}}}}"##,
+        )
+        .unwrap();
+
+        let api = load_spec(&dir.path().join("openapi.yaml")).unwrap();
+        let operation = api.paths.paths["/pets"]
+            .as_item()
+            .unwrap()
+            .get
+            .as_ref()
+            .unwrap();
+        assert!(operation.parameters[0].as_item().is_some());
+        let pet = &api.schemas["Pet"];
+        assert!(pet.as_item().is_some());
+        let owner = pet.as_item().unwrap().properties()["owner"].clone();
+        assert!(
+            matches!(owner, RefOr::Reference { reference } if reference == "#/components/schemas/owner")
+        );
+        assert!(api.schemas.contains_key("owner"));
+
+        fs::write(
+            dir.path().join("schemas/owner.json"),
+            r#"{"$ref": "pet.yaml#/Pet/properties/owner"}"#,
+        )
+        .unwrap();
+        let err = load_spec(&dir.path().join("openapi.yaml")).unwrap_err();
+        assert!(format!("{err:#}").contains("Circular reference"));
+    }
+}
@@ -48,7 +48,7 @@ impl std::fmt::Display for CurlRequest<'_> {
             }
         }
         if self.0.body().is_some() {
-            write!(fmt, " \\\n    --data @-")?;
+            write!(fmt, " \\\n    --data-binary @-")?;
         }
         Ok(())
     }
@@ -2,24 +2,42 @@
 //! fuzzing target during normal fuzzing operation. These functions need an OpenAPI struct
 //! to generate realistic requests for the given target.
 
-use std::{borrow::Cow, collections::VecDeque, f64::consts::PI};
+use std::{borrow::Cow, cell::RefCell, collections::VecDeque, f64::consts::PI};
 
 use indexmap::IndexMap;
 use openapiv3::{
     OpenAPI, Operation, Parameter, ParameterData, RefOr, Schema, SchemaKind, StringFormat, Type,
 };
 use petgraph::{csr::DefaultIx, graph::DiGraph, prelude::NodeIndex, visit::EdgeRef};
-use rand::{prelude::Distribution, Rng};
+use rand::{prelude::Distribution, rngs::StdRng, Rng, SeedableRng};
 use regex::Regex;
 use serde_json::Value;
 use unicode_truncate::UnicodeTruncateStr;
 
-use super::{JsonContent, QualifiedOperation, WwwForm};
+use super::{JsonContent, MultipartForm, QualifiedOperation, RawContent, WwwForm, XmlContent};
 use crate::{
     initial_corpus::dependency_graph::ParameterMatching,
-    input::{parameter::ParameterKind, Body, OpenApiInput, OpenApiRequest, ParameterContents},
+    input::{
+        media_type::MediaTypeHeaders, parameter::ParameterKind, Body, OpenApiInput, OpenApiRequest,
+        ParameterContents,
+    },
 };
 
+/// Contents of raw bodies for which the specification gives no example
+const DEFAULT_RAW_BODY: &[u8] = b"WuppieFuzz\n";
+
+thread_local! {
+    /// Random number generator for example values that are sampled, like strings matching a
+    /// pattern. It is reseeded by [`seed_examples`] before a corpus is generated, so the
+    /// initial corpus is reproducible.
+    static EXAMPLE_RNG: RefCell<StdRng> = RefCell::new(StdRng::seed_from_u64(0));
+}
+
+/// Seeds the random number generator for sampled example values on the current thread.
+pub fn seed_examples(seed: u64) {
+    EXAMPLE_RNG.set(StdRng::seed_from_u64(seed));
+}
+
 /// Takes a (path, method, operation) tuple and produces an OpenApiRequest
 /// filled with example values from the API specification, and default values
 /// for parameters with no explicit examples.
@@ -36,6 +54,7 @@ pub fn example_from_qualified_operation(
             example_body_contents(api, operation.operation),
         ),
         parameters: example_parameters(api, operation.operation),
+        media_type_headers: MediaTypeHeaders::default(),
     }
 }
 
@@ -45,10 +64,16 @@ pub fn example_from_qualified_operation(
 fn example_body_contents(api: &OpenAPI, operation: &Operation) -> Option<ParameterContents> {
     let body = operation.request_body.as_ref()?.resolve(api).ok()?;
 
-    // Get either application/json or form content, if neither is present this function will return an empty body.
-    let media_type = None
+    // Get application/json, form, multipart or XML content. Other content is sent as raw bytes.
+    let Some(media_type) = None
         .or_else(|| body.content.get_json_content())
-        .or_else(|| body.content.get_www_form_content())?;
+        .or_else(|| body.content.get_www_form_content())
+        .or_else(|| body.content.get_multipart_form_content())
+        .or_else(|| body.content.get_xml_content())
+    else {
+        let (_, media_type) = body.content.get_raw_content()?;
+        return Some(example_raw_contents(api, media_type));
+    };
 
     let schema = media_type.schema.as_ref()?.resolve(api);
 
@@ -95,10 +120,16 @@ fn all_interesting_body_contents(
 ) -> Option<Vec<ParameterContents>> {
     let body = operation.request_body.as_ref()?.resolve(api).ok()?;
 
-    // Get either application/json or form content, if neither is present this function will return an empty body.
-    let media_type = None
+    // Get application/json, form, multipart or XML content. Other content is sent as raw bytes.
+    let Some(media_type) = None
         .or_else(|| body.content.get_json_content())
-        .or_else(|| body.content.get_www_form_content())?;
+        .or_else(|| body.content.get_www_form_content())
+        .or_else(|| body.content.get_multipart_form_content())
+        .or_else(|| body.content.get_xml_content())
+    else {
+        let (_, media_type) = body.content.get_raw_content()?;
+        return Some(vec![example_raw_contents(api, media_type)]);
+    };
 
     Some(
         interesting_params_from_media_type(api, media_type)
@@ -263,6 +294,16 @@ fn example_from_media_type(api: &OpenAPI, contents: &openapiv3::MediaType) -> Op
     })
 }
 
+/// Generates the contents of a raw body: the example of the media type or its schema, or some
+/// bytes if there is none. Strings (such as those of binary schemas) become bytes.
+fn example_raw_contents(api: &OpenAPI, media_type: &openapiv3::MediaType) -> ParameterContents {
+    match example_from_media_type(api, media_type) {
+        Some(Value::String(text)) => ParameterContents::Bytes(text.into_bytes()),
+        Some(value) => ParameterContents::from(value),
+        None => ParameterContents::Bytes(DEFAULT_RAW_BODY.to_vec()),
+    }
+}
+
 fn interesting_params_from_media_type(
     api: &OpenAPI,
     contents: &openapiv3::MediaType,
@@ -499,6 +540,8 @@ fn strings_from_format(str_format: &openapiv3::VariantOrUnknownOrEmpty<StringFor
             "2016-12-31T23:59:60Z",    // Valid leap second
         ],
         openapiv3::VariantOrUnknownOrEmpty::Item(StringFormat::Byte) => &["V3VwcGllRnV6elROTyE=="],
+        // The contents of an uploaded file: a small text file, and an empty one
+        openapiv3::VariantOrUnknownOrEmpty::Item(StringFormat::Binary) => &["WuppieFuzz\n", ""],
         // Though the specification allows for other StringFormats, like email,
         // the openapi crate does not. Just in case, we default to an email-like
         // value.
@@ -525,7 +568,7 @@ fn enforce_length_bounds(
     string: &str,
     min_length: Option<usize>,
     max_length: Option<usize>,
-) -> Cow<str> {
+) -> Cow<'_, str> {
     let mut result = Cow::from(string);
     if let Some(min) = min_length {
         *result.to_mut() += &"A".repeat(min);
@@ -782,7 +825,7 @@ fn interesting_params_from_string_type(string: &openapiv3::StringType) -> Vec<se
     if let Some(pattern) = &string.pattern {
         if let Ok(compiled_regex) = rand_regex::Regex::compile(pattern, 100) {
             return vec![serde_json::Value::String(
-                compiled_regex.sample(&mut rand::thread_rng()),
+                EXAMPLE_RNG.with_borrow_mut(|rng| compiled_regex.sample(rng)),
             )];
         }
 
@@ -797,11 +840,11 @@ fn interesting_params_from_string_type(string: &openapiv3::StringType) -> Vec<se
 
                 // Generate 1000 sample strings from the regex pattern without anchors
                 // and test if one matches the regex with the anchors
-                if let Some(sample) = rand::thread_rng()
-                    .sample_iter::<String, _>(&compiled_regex)
-                    .take(1000)
-                    .find(|s| filter_regex.is_match(s))
-                {
+                if let Some(sample) = EXAMPLE_RNG.with_borrow_mut(|rng| {
+                    rng.sample_iter::<String, _>(&compiled_regex)
+                        .take(1000)
+                        .find(|s| filter_regex.is_match(s))
+                }) {
                     return vec![serde_json::Value::String(sample)];
                 }
                 log::warn!(
@@ -925,6 +968,7 @@ fn all_interesting_inputs_for_qualified_operation(
                     path: operation.path.to_owned(),
                     body: Body::build(api, operation.operation, Some(body)),
                     parameters: IndexMap::default(),
+                    media_type_headers: MediaTypeHeaders::default(),
                 })
                 .collect(),
             None => vec![OpenApiRequest {
@@ -932,6 +976,7 @@ fn all_interesting_inputs_for_qualified_operation(
                 path: operation.path.to_owned(),
                 body: Body::build(api, operation.operation, None),
                 parameters: IndexMap::default(),
+                media_type_headers: MediaTypeHeaders::default(),
             }],
         }
     } else {
@@ -944,6 +989,7 @@ fn all_interesting_inputs_for_qualified_operation(
                     path: operation.path.to_owned(),
                     body: Body::build(api, operation.operation, Some(body)),
                     parameters: param_combination.clone(),
+                    media_type_headers: MediaTypeHeaders::default(),
                 })
                 .collect(),
             None => combinations
@@ -953,6 +999,7 @@ fn all_interesting_inputs_for_qualified_operation(
                     path: operation.path.to_owned(),
                     body: Body::build(api, operation.operation, None),
                     parameters: combination,
+                    media_type_headers: MediaTypeHeaders::default(),
                 })
                 .collect(),
         }
@@ -0,0 +1,254 @@
+//! Selection of the operations that are fuzzed.
+//!
+//! Operations are selected with `--include-operations` and `--exclude-operations`, which
+//! take selectors such as `path:/admin/**`, `method:DELETE`, `operation-id:deleteUser` or
+//! `tag:admin`. An operation is fuzzed if it matches any of the included selectors (or
+//! none are given), and none of the excluded ones. In path globs, `*` matches any text
+//! within a path segment and `**` matches any text, including slashes.
+//!
+//! Operations that are not fuzzed are removed from the specification, so no part of the
+//! fuzzer generates requests for them. Requests for operations that the specification does
+//! not contain (such as those made by the `common5` method mutation strategy) are only sent
+//! if they match the selectors by path and method.
+
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use anyhow::Result;
+use openapiv3::{OpenAPI, Operation};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::input::Method;
+
+/// A criterion that selects operations.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum OperationSelector {
+    Path(PathGlob),
+    Method(Method),
+    OperationId(String),
+    Tag(String),
+}
+
+/// A glob over the paths in the specification.
+#[derive(Debug, Clone)]
+pub struct PathGlob {
+    glob: String,
+    regex: Regex,
+}
+
+impl PartialEq for PathGlob {
+    fn eq(&self, other: &Self) -> bool {
+        self.glob == other.glob
+    }
+}
+
+impl Eq for PathGlob {}
+
+impl FromStr for PathGlob {
+    type Err = regex::Error;
+
+    fn from_str(glob: &str) -> Result<Self, Self::Err> {
+        let pattern = regex::escape(glob)
+            .replace(r"\*\*", ".*")
+            .replace(r"\*", "[^/]*");
+        Ok(Self {
+            glob: glob.to_owned(),
+            regex: Regex::new(&format!("^{pattern}$"))?,
+        })
+    }
+}
+
+impl FromStr for OperationSelector {
+    type Err = anyhow::Error;
+
+    fn from_str(selector: &str) -> Result<Self, Self::Err> {
+        let Some((kind, value)) = selector.split_once(':') else {
+            bail!("Operation selector {selector} should have the form <kind>:<value>");
+        };
+        Ok(match kind {
+            "path" => Self::Path(value.parse()?),
+            "method" => Self::Method(Method::try_from(value).map_err(|err| anyhow!("{err}"))?),
+            "operation-id" => Self::OperationId(value.to_owned()),
+            "tag" => Self::Tag(value.to_owned()),
+            _ => bail!(
+                "Unknown kind {kind} in operation selector {selector}, expected path, method, operation-id or tag"
+            ),
+        })
+    }
+}
+
+impl TryFrom<String> for OperationSelector {
+    type Error = anyhow::Error;
+
+    fn try_from(selector: String) -> Result<Self, Self::Error> {
+        selector.parse()
+    }
+}
+
+impl Display for OperationSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Path(path) => write!(f, "path:{}", path.glob),
+            Self::Method(method) => write!(f, "method:{method}"),
+            Self::OperationId(operation_id) => write!(f, "operation-id:{operation_id}"),
+            Self::Tag(tag) => write!(f, "tag:{tag}"),
+        }
+    }
+}
+
+impl OperationSelector {
+    /// Whether the selector matches the operation with the given path and method. Selectors
+    /// on operation ids and tags never match operations outside the specification.
+    fn matches(&self, path: &str, method: Method, operation: Option<&Operation>) -> bool {
+        match self {
+            Self::Path(glob) => glob.regex.is_match(path),
+            Self::Method(selected) => *selected == method,
+            Self::OperationId(operation_id) => operation
+                .and_then(|operation| operation.operation_id.as_ref())
+                .is_some_and(|id| id == operation_id),
+            Self::Tag(tag) => operation.is_some_and(|operation| operation.tags.contains(tag)),
+        }
+    }
+}
+
+/// Decides which operations are fuzzed.
+#[derive(Debug, Default)]
+pub struct OperationFilter {
+    include: Vec<OperationSelector>,
+    exclude: Vec<OperationSelector>,
+    /// Whether each operation in the (unfiltered) specification is fuzzed
+    operations: HashMap<(Method, String), bool>,
+}
+
+impl OperationFilter {
+    /// Decides for every operation in the specification whether it is fuzzed, and removes
+    /// the operations that are not fuzzed from the specification.
+    pub fn apply(
+        api: &mut OpenAPI,
+        include: &[OperationSelector],
+        exclude: &[OperationSelector],
+    ) -> Result<Self> {
+        let mut filter = Self {
+            include: include.to_vec(),
+            exclude: exclude.to_vec(),
+            operations: HashMap::new(),
+        };
+        if include.is_empty() && exclude.is_empty() {
+            return Ok(filter);
+        }
+
+        for (path, path_item) in api.paths.paths.iter_mut() {
+            let Some(path_item) = path_item.as_mut() else {
+                continue;
+            };
+            for (method, slot) in [
+                (Method::Get, &mut path_item.get),
+                (Method::Put, &mut path_item.put),
+                (Method::Post, &mut path_item.post),
+                (Method::Delete, &mut path_item.delete),
+                (Method::Options, &mut path_item.options),
+                (Method::Head, &mut path_item.head),
+                (Method::Patch, &mut path_item.patch),
+                (Method::Trace, &mut path_item.trace),
+            ] {
+                let Some(operation) = slot else {
+                    continue;
+                };
+                let fuzzed = filter.matches(path, method, Some(operation));
+                if !fuzzed {
+                    log::info!("Not fuzzing {method} {path}");
+                    *slot = None;
+                }
+                filter.operations.insert((method, path.clone()), fuzzed);
+            }
+        }
+        api.paths.paths.retain(|_, path_item| {
+            path_item
+                .as_item()
+                .is_none_or(|path_item| path_item.iter().next().is_some())
+        });
+
+        if api.operations().next().is_none() {
+            bail!("The operation filters exclude every operation in the specification");
+        }
+        Ok(filter)
+    }
+
+    /// Whether a request to the given path with the given method may be sent.
+    pub fn allows(&self, path: &str, method: Method) -> bool {
+        self.operations
+            .get(&(method, path.to_owned()))
+            .copied()
+            .unwrap_or_else(|| self.matches(path, method, None))
+    }
+
+    fn matches(&self, path: &str, method: Method, operation: Option<&Operation>) -> bool {
+        (self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|selector| selector.matches(path, method, operation)))
+            && !self
+                .exclude
+                .iter()
+                .any(|selector| selector.matches(path, method, operation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openapiv3::{OpenAPI, Operation, PathItem};
+
+    use super::{OperationFilter, OperationSelector};
+    use crate::input::Method;
+
+    #[test]
+    fn test_operation_filter() {
+        let mut api = OpenAPI::default();
+        let tagged = |tag: &str| Operation {
+            tags: vec![tag.to_owned()],
+            ..Default::default()
+        };
+        api.paths.paths.insert(
+            "/users/{id}".to_owned(),
+            PathItem {
+                get: Some(tagged("users")),
+                delete: Some(Operation {
+                    operation_id: Some("deleteUser".to_owned()),
+                    ..tagged("users")
+                }),
+                ..Default::default()
+            }
+            .into(),
+        );
+        api.paths.paths.insert(
+            "/admin/shutdown".to_owned(),
+            PathItem::post(tagged("admin")).into(),
+        );
+
+        let selectors = |selectors: &[&str]| {
+            selectors
+                .iter()
+                .map(|selector| selector.parse::<OperationSelector>().unwrap())
+                .collect::<Vec<_>>()
+        };
+        let filter = OperationFilter::apply(
+            &mut api,
+            &selectors(&["path:/users/*", "tag:admin"]),
+            &selectors(&["operation-id:deleteUser", "path:/admin/**"]),
+        )
+        .unwrap();
+
+        assert_eq!(api.operations().count(), 1);
+        assert!(filter.allows("/users/{id}", Method::Get));
+        assert!(!filter.allows("/users/{id}", Method::Delete));
+        assert!(!filter.allows("/admin/shutdown", Method::Post));
+        // Not in the specification, so only the selectors on paths and methods apply
+        assert!(filter.allows("/users/{id}", Method::Put));
+        assert!(!filter.allows("/users/{id}/posts", Method::Get));
+
+        assert!("verb:GET".parse::<OperationSelector>().is_err());
+        assert!(OperationFilter::apply(&mut api, &[], &selectors(&["method:get"])).is_err());
+    }
+}
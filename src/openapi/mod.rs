@@ -2,22 +2,47 @@ use std::{convert::TryFrom, fmt::Debug, path::Path};
 
 use anyhow::{Context, Result};
 use indexmap::IndexMap;
-use openapiv3::{MediaType, OpenAPI, Operation, PathItem, VersionedOpenAPI};
+use openapiv3::{MediaType, OpenAPI, Operation, PathItem};
 
-use crate::input::{method::InvalidMethodError, Method};
+use crate::{
+    configuration::Configuration,
+    input::{method::InvalidMethodError, Method},
+};
 
 pub mod build_request;
+pub mod bundle;
 pub mod curl_request;
 pub mod examples;
+pub mod filter;
+pub mod server;
 pub mod validate_response;
+pub mod xml;
 
-/// Loads the OpenAPI specification from the given path
+/// Loads the OpenAPI specification from the given path (or http(s) URL), including
+/// the files it refers to
 pub fn get_api_spec(path: &Path) -> Result<Box<OpenAPI>, anyhow::Error> {
-    openapi_from_yaml_file(path)
+    bundle::load_spec(path)
         .map(Box::new)
         .with_context(|| format!("Error parsing OpenAPI-file at {}", path.to_string_lossy()))
 }
 
+/// Loads the OpenAPI specification from the configuration, with the server that
+/// requests are sent to as its only server.
+pub fn get_target_api_spec(config: &Configuration) -> Result<Box<OpenAPI>, anyhow::Error> {
+    let path = config
+        .openapi_spec
+        .as_deref()
+        .ok_or_else(|| anyhow!("No OpenAPI specification given"))?;
+    let mut api = get_api_spec(path)?;
+    server::select_server(
+        &mut api,
+        path,
+        config.target_url.as_ref(),
+        config.server_index,
+    )?;
+    Ok(api)
+}
+
 /// A QualifiedOperation is the (path, method, operation) tuple returned from
 /// `api.operations()`, and is used to identify an operation uniquely in the graph.
 #[allow(dead_code)]
@@ -46,12 +71,6 @@ impl<'a> QualifiedOperation<'a> {
     }
 }
 
-pub fn openapi_from_yaml_file(filename: &Path) -> Result<OpenAPI> {
-    let file = std::fs::File::open(filename)?;
-    let open_api: VersionedOpenAPI = serde_yaml::from_reader(file)?;
-    Ok(open_api.upgrade())
-}
-
 pub fn find_method_indices_for_path<'a>(api: &'a OpenAPI, path: &str) -> Vec<(&'a str, usize)> {
     api.operations()
         .enumerate()
@@ -100,6 +119,63 @@ impl WwwForm for IndexMap<String, MediaType> {
     }
 }
 
+pub trait MultipartForm {
+    fn get_multipart_form_content(&self) -> Option<&MediaType>;
+}
+
+impl MultipartForm for IndexMap<String, MediaType> {
+    fn get_multipart_form_content(&self) -> Option<&MediaType> {
+        self.iter()
+            .find_map(|(key, value)| key.starts_with("multipart/form-data").then_some(value))
+    }
+}
+
+pub trait XmlContent {
+    fn get_xml_content(&self) -> Option<&MediaType>;
+    fn get_xml_content_with_type(&self) -> Option<(&str, &MediaType)>;
+}
+
+impl XmlContent for IndexMap<String, MediaType> {
+    /// Finds `application/xml` or `text/xml` content, or a media type with the `+xml` suffix
+    fn get_xml_content(&self) -> Option<&MediaType> {
+        self.get_xml_content_with_type()
+            .map(|(_, media_type)| media_type)
+    }
+
+    /// Finds XML content like `get_xml_content`, along with its media type
+    fn get_xml_content_with_type(&self) -> Option<(&str, &MediaType)> {
+        self.iter()
+            .find(|(key, _)| is_xml_media_type(key))
+            .map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+fn is_xml_media_type(key: &str) -> bool {
+    let essence = key.split(';').next().unwrap_or_default().trim();
+    essence == "application/xml" || essence == "text/xml" || essence.ends_with("+xml")
+}
+
+pub trait RawContent {
+    fn get_raw_content(&self) -> Option<(&str, &MediaType)>;
+}
+
+impl RawContent for IndexMap<String, MediaType> {
+    /// Finds the first media type (in the order of the specification) that is not one of
+    /// the structured types above, such as `application/octet-stream`, images, protobuf or
+    /// vendor `+json` types. Bodies of these types are sent as they are.
+    fn get_raw_content(&self) -> Option<(&str, &MediaType)> {
+        self.iter()
+            .find(|(key, _)| {
+                !(key.starts_with("application/json")
+                    || key.starts_with("application/x-www-form-urlencoded")
+                    || key.starts_with("multipart/form-data")
+                    || key.starts_with("text/plain")
+                    || is_xml_media_type(key))
+            })
+            .map(|(key, value)| (key.as_str(), value))
+    }
+}
+
 pub trait TextPlain {
     #[allow(dead_code)]
     fn get_text_plain(&self) -> Option<&MediaType>;
@@ -0,0 +1,171 @@
+//! Selection of the server that requests are sent to.
+//!
+//! A specification can list several servers, whose URLs may contain variables (such as
+//! `{port}`) and may be relative to the location of the specification. The server is
+//! selected once, after the specification is loaded; from then on it is the only server
+//! in the specification.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use openapiv3::{OpenAPI, Server};
+use url::Url;
+
+use super::bundle::spec_location;
+
+/// Selects the server to send requests to, and makes it the only server in the
+/// specification. This is `target_url` if given, and otherwise the server with the
+/// given index, with its variables substituted and resolved against the location of
+/// the specification.
+pub fn select_server(
+    api: &mut OpenAPI,
+    spec_path: &Path,
+    target_url: Option<&Url>,
+    server_index: usize,
+) -> Result<()> {
+    let url = match target_url {
+        Some(target_url) => target_url.clone(),
+        None => {
+            let server = api.servers.get(server_index).ok_or_else(|| {
+                if api.servers.is_empty() {
+                    anyhow!("The specification contains no servers; pass a target URL instead")
+                } else {
+                    anyhow!(
+                        "Server index {server_index} is out of range, the specification contains {} server(s)",
+                        api.servers.len()
+                    )
+                }
+            })?;
+            server_url(server, spec_path)?
+        }
+    };
+    info!("Sending requests to {url}");
+    api.servers = vec![Server {
+        // Paths from the specification start with a slash
+        url: url.as_str().trim_end_matches('/').to_owned(),
+        ..Default::default()
+    }];
+    Ok(())
+}
+
+/// Parses a full URL, or resolves a path against the selected server the way the paths in
+/// the specification are.
+pub fn server_relative_url(server_url: &str, path_or_url: &str) -> Result<Url> {
+    match Url::parse(path_or_url) {
+        Ok(url) => Ok(url),
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            Url::parse(&format!("{server_url}{path_or_url}"))
+                .with_context(|| format!("Invalid URL {path_or_url}"))
+        }
+        Err(err) => Err(err).with_context(|| format!("Invalid URL {path_or_url}")),
+    }
+}
+
+/// Produces the absolute URL of a server from the specification.
+fn server_url(server: &Server, spec_path: &Path) -> Result<Url> {
+    let url = substitute_variables(server)?;
+    let resolved = match Url::parse(&url) {
+        Ok(resolved) => resolved,
+        Err(url::ParseError::RelativeUrlWithoutBase) => spec_location(spec_path)?
+            .join(&url)
+            .with_context(|| format!("Could not resolve server URL {url}"))?,
+        Err(err) => return Err(err).with_context(|| format!("Invalid server URL {url}")),
+    };
+    if !matches!(resolved.scheme(), "http" | "https") {
+        bail!(
+            "Server URL {url} is relative to the specification, which is not served over HTTP; pass a target URL instead"
+        );
+    }
+    Ok(resolved)
+}
+
+/// Replaces the variables in the URL of a server by their default values. If the default
+/// is not one of the allowed values of a variable, the first allowed value is used.
+fn substitute_variables(server: &Server) -> Result<String> {
+    let mut url = server.url.clone();
+    for (name, variable) in server.variables.iter().flatten() {
+        let value = match variable.enumeration.first() {
+            Some(first) if !variable.enumeration.contains(&variable.default) => {
+                warn!(
+                    "Default value {} of server variable {name} is not one of its allowed values, using {first}",
+                    variable.default
+                );
+                first
+            }
+            _ => &variable.default,
+        };
+        url = url.replace(&format!("{{{name}}}"), value);
+    }
+    if url.contains('{') {
+        bail!("Server URL {url} contains a variable that is not defined");
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use indexmap::IndexMap;
+    use openapiv3::{OpenAPI, Server, ServerVariable};
+    use url::Url;
+
+    use super::select_server;
+
+    fn api_with_servers(urls: &[&str]) -> OpenAPI {
+        OpenAPI {
+            servers: urls
+                .iter()
+                .map(|url| Server {
+                    url: url.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_select_server() {
+        let spec_path = Path::new("openapi.yaml");
+
+        let mut api = api_with_servers(&["http://localhost:8080/", "http://staging/api"]);
+        select_server(&mut api, spec_path, None, 1).unwrap();
+        assert_eq!(api.servers[0].url, "http://staging/api");
+        assert_eq!(api.servers.len(), 1);
+
+        let mut api = api_with_servers(&["http://localhost:8080/"]);
+        let target = Url::parse("http://docker-alias:80/v2").unwrap();
+        select_server(&mut api, spec_path, Some(&target), 0).unwrap();
+        assert_eq!(api.servers[0].url, "http://docker-alias/v2");
+
+        assert!(select_server(&mut api_with_servers(&[]), spec_path, None, 0).is_err());
+        assert!(select_server(&mut api_with_servers(&["/api"]), spec_path, None, 0).is_err());
+        assert!(select_server(&mut api_with_servers(&["/api"]), spec_path, None, 1).is_err());
+    }
+
+    #[test]
+    fn test_server_variables() {
+        let mut api = api_with_servers(&["http://localhost:{port}/{basePath}"]);
+        api.servers[0].variables = Some(IndexMap::from([
+            (
+                "port".to_owned(),
+                ServerVariable {
+                    enumeration: vec!["8080".to_owned(), "8443".to_owned()],
+                    default: "9999".to_owned(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "basePath".to_owned(),
+                ServerVariable {
+                    default: "v1".to_owned(),
+                    ..Default::default()
+                },
+            ),
+        ]));
+        select_server(&mut api, Path::new("openapi.yaml"), None, 0).unwrap();
+        assert_eq!(api.servers[0].url, "http://localhost:8080/v1");
+    }
+}
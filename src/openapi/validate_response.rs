@@ -5,17 +5,18 @@ use openapiv3::{OpenAPI, ReferenceOr, Schema, Type};
 use reqwest::StatusCode;
 use serde_json::Value;
 
-use super::JsonContent;
+use super::{JsonContent, XmlContent};
 use crate::input::{Method, OpenApiRequest};
 
 /// The Response object provided by Reqwest is unwieldy, since its body contents
 /// can only be obtained once by consuming the object. This prevents later reading
 /// the status or obtaining the body contents again in another form.
 ///
-/// This Response is created from a `reqwest::blocking::Response` and allows accessing the
-/// body contents by reference.
+/// This Response is created from a `reqwest::blocking::Response` (or read from an asynchronous
+/// `reqwest::Response`) and allows accessing the body contents by reference.
 pub struct Response {
     status: reqwest::StatusCode,
+    headers: Vec<(String, String)>,
     cookies: Vec<(String, String)>,
     body: Vec<u8>,
 }
@@ -31,6 +32,15 @@ impl Response {
         self.body.len() as u64
     }
 
+    /// The headers of the response, with values that are not valid UTF-8 replaced lossily.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
     pub fn text(&self) -> Result<String, Utf8Error> {
         std::str::from_utf8(&self.body).map(|s| s.to_owned())
     }
@@ -46,18 +56,50 @@ impl From<reqwest::blocking::Response> for Response {
     fn from(resp: reqwest::blocking::Response) -> Self {
         Self {
             status: resp.status(),
+            headers: header_pairs(resp.headers()),
+            cookies: resp
+                .cookies()
+                .map(|c| (c.name().to_owned(), c.value().to_owned()))
+                .collect(),
+            body: resp
+                .bytes()
+                .map(|b| b.into_iter().collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Response {
+    /// Reads the body of an asynchronous response.
+    pub async fn read(resp: reqwest::Response) -> Self {
+        Self {
+            status: resp.status(),
+            headers: header_pairs(resp.headers()),
             cookies: resp
                 .cookies()
                 .map(|c| (c.name().to_owned(), c.value().to_owned()))
                 .collect(),
             body: resp
                 .bytes()
+                .await
                 .map(|b| b.into_iter().collect())
                 .unwrap_or_default(),
         }
     }
 }
 
+fn header_pairs(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.as_str().to_owned(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
 /// ValidationError is returned by `validate_response` if a given response should
 /// not have been given by the API under test.
 #[derive(Debug)]
@@ -104,6 +146,12 @@ pub enum ValidationError {
     /// supported.
     ResponseMalformedJSON { error: serde_json::Error },
 
+    /// The response body returned by the API can not be parsed as XML, while the
+    /// specification calls for XML.
+    ///
+    /// If this variant is returned, the API does not behave as specified.
+    ResponseMalformedXML { error: String },
+
     /// The API returned a response body, but no response is specified.
     ///
     /// If this variant is returned, the API does not behave as specified.
@@ -120,6 +168,22 @@ pub enum ValidationError {
 }
 
 impl ValidationError {
+    /// The name of the kind of mismatch, without its details.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::OperationNotInSpec { .. } => "OperationNotInSpec",
+            Self::StatusNotSpecified { .. } => "StatusNotSpecified",
+            Self::ResponseReferenceBroken { .. } => "ResponseReferenceBroken",
+            Self::ResponseObjectIncorrect { .. } => "ResponseObjectIncorrect",
+            Self::ResponseEnumIncorrect { .. } => "ResponseEnumIncorrect",
+            Self::ResponseMalformedJSON { .. } => "ResponseMalformedJSON",
+            Self::ResponseMalformedXML { .. } => "ResponseMalformedXML",
+            Self::UnexpectedContent { .. } => "UnexpectedContent",
+            Self::MediaTypeContainsNoSchema => "MediaTypeContainsNoSchema",
+            Self::SchemaIsAny(_) => "SchemaIsAny",
+        }
+    }
+
     /// Validation happens recursively, and if a deeply nested field contains an
     /// error, it is nice if the validation error that is eventually returned
     /// pinpoints the path to the field that is incorrect.
@@ -166,6 +230,9 @@ impl std::fmt::Display for ValidationError {
             ValidationError::ResponseMalformedJSON { error } => {
                 write!(fmt, "Error parsing response as JSON: {error}")
             }
+            ValidationError::ResponseMalformedXML { error } => {
+                write!(fmt, "Error parsing response as XML: {error}")
+            }
             ValidationError::UnexpectedContent { content_length } => write!(
                 fmt,
                 "Unexpected response body content. content-length: {content_length}"
@@ -219,10 +286,20 @@ pub fn validate_response(
             })?;
 
     // We now have a response and the list of valid response_options.
-    // If there is no valid option for application/json, the response should also be empty.
-    let media_type = match response_options.content.get_json_content() {
-        Some(media_type) => media_type,
-        None => {
+    // If there is no valid option for application/json or XML, the response should also be empty.
+    // If both are allowed, the content type of the response decides.
+    let xml_response = response
+        .headers()
+        .iter()
+        .any(|(name, value)| name.eq_ignore_ascii_case("content-type") && value.contains("xml"));
+    let (media_type, is_xml) = match (
+        response_options.content.get_json_content(),
+        response_options.content.get_xml_content(),
+    ) {
+        (Some(_), Some(media_type)) if xml_response => (media_type, true),
+        (Some(media_type), _) => (media_type, false),
+        (None, Some(media_type)) => (media_type, true),
+        (None, None) => {
             let content_length = response.content_length();
             return if content_length > 0 {
                 Err(ValidationError::UnexpectedContent { content_length })
@@ -234,15 +311,24 @@ pub fn validate_response(
 
     // Extract the schema for a correct response. If none exists, we can't really check
     // anything, and we consider that a specification error.
-    let response_schema = media_type
+    let schema = media_type
         .schema
         .as_ref()
-        .ok_or_else(|| ValidationError::MediaTypeContainsNoSchema)?
-        .resolve(api);
-
-    let response_contents = response
-        .json()
-        .map_err(|e| ValidationError::ResponseMalformedJSON { error: e })?;
+        .ok_or_else(|| ValidationError::MediaTypeContainsNoSchema)?;
+    let response_schema = schema.resolve(api);
+
+    let response_contents = if is_xml {
+        let text = response
+            .text()
+            .map_err(|error| ValidationError::ResponseMalformedXML {
+                error: error.to_string(),
+            })?;
+        super::xml::parse_response(api, schema, &text)?
+    } else {
+        response
+            .json()
+            .map_err(|e| ValidationError::ResponseMalformedJSON { error: e })?
+    };
 
     validate_object_against_schema(api, response_schema, &response_contents)
 }
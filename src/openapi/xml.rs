@@ -0,0 +1,302 @@
+//! XML representations of values, as described by the `xml` objects of schemas.
+//!
+//! An `xml` object can rename the element of a property, put it in a namespace, make it an
+//! attribute instead of an element, and (for arrays) wrap the items in an element of their
+//! own. The parser of specifications drops `xml` objects, so they are moved to the `x-xml`
+//! extension of their schema before a specification is parsed.
+//!
+//! XML responses are converted to the JSON value they represent, so they can be validated
+//! against the schema like JSON responses.
+
+use openapiv3::{OpenAPI, ReferenceOr, Schema, SchemaKind, Type};
+use roxmltree::{Document, Node};
+use serde_json::{Map, Value};
+
+use super::validate_response::ValidationError;
+
+/// Extension of a schema that holds its `xml` object.
+pub const XML_EXTENSION: &str = "x-xml";
+
+/// The `xml` object of a schema.
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct XmlObject {
+    pub name: Option<String>,
+    pub namespace: Option<String>,
+    pub prefix: Option<String>,
+    pub attribute: bool,
+    pub wrapped: bool,
+}
+
+impl XmlObject {
+    /// The `xml` object of the schema, or the default one if it has none.
+    pub fn of(schema: &Schema) -> Self {
+        schema
+            .extensions
+            .get(XML_EXTENSION)
+            .and_then(|xml| serde_json::from_value(xml.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Moves the `xml` objects of the schemas in a (bundled) specification to their `x-xml`
+/// extension, where the parser keeps them.
+pub fn preserve_xml_objects(value: &mut serde_yaml::Value) {
+    /// Keys that tell that a mapping is a schema, and not for instance a map of headers
+    const SCHEMA_KEYS: [&str; 6] = ["type", "properties", "items", "allOf", "anyOf", "oneOf"];
+
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            if SCHEMA_KEYS.iter().any(|key| mapping.contains_key(key))
+                && !mapping.contains_key(XML_EXTENSION)
+                && mapping
+                    .get("xml")
+                    .is_some_and(serde_yaml::Value::is_mapping)
+            {
+                let xml = mapping.remove("xml").expect("the xml object exists");
+                mapping.insert(XML_EXTENSION.into(), xml);
+            }
+            for (key, child) in mapping.iter_mut() {
+                match key.as_str() {
+                    // Examples are values, not schemas
+                    Some("example" | "examples" | "default" | "enum") => (),
+                    // Properties may be named `xml`
+                    Some("properties") => {
+                        if let Some(properties) = child.as_mapping_mut() {
+                            properties.values_mut().for_each(preserve_xml_objects);
+                        }
+                    }
+                    _ => preserve_xml_objects(child),
+                }
+            }
+        }
+        serde_yaml::Value::Sequence(sequence) => sequence.iter_mut().for_each(preserve_xml_objects),
+        _ => (),
+    }
+}
+
+/// The name of the component a schema refers to, which is the default name of a root element.
+pub fn reference_name(schema: &ReferenceOr<Schema>) -> Option<&str> {
+    schema
+        .as_ref_str()
+        .and_then(|reference| reference.rsplit('/').next())
+}
+
+/// The schema of the items of an array schema.
+pub fn array_items(schema: &Schema) -> Option<&ReferenceOr<Schema>> {
+    match &schema.kind {
+        SchemaKind::Type(Type::Array(array)) => array.items.as_deref(),
+        _ => None,
+    }
+}
+
+/// Parses an XML response into the JSON value it represents according to the schema.
+pub fn parse_response(
+    api: &OpenAPI,
+    schema: &ReferenceOr<Schema>,
+    text: &str,
+) -> Result<Value, ValidationError> {
+    let document =
+        Document::parse(text).map_err(|error| ValidationError::ResponseMalformedXML {
+            error: error.to_string(),
+        })?;
+    let root = document.root_element();
+    let resolved = schema.resolve(api);
+    let expected_name = XmlObject::of(resolved)
+        .name
+        .or_else(|| reference_name(schema).map(str::to_owned));
+    if let Some(expected_name) = expected_name {
+        if root.tag_name().name() != expected_name {
+            return Err(ValidationError::ResponseObjectIncorrect {
+                msg: format!(
+                    "Root element <{}> of the response is not <{expected_name}>",
+                    root.tag_name().name()
+                ),
+            });
+        }
+    }
+    Ok(element_to_json(api, resolved, root))
+}
+
+/// Converts an element to the value it represents according to the schema.
+fn element_to_json(api: &OpenAPI, schema: &Schema, element: Node) -> Value {
+    match &schema.kind {
+        SchemaKind::Type(Type::Array(array)) => Value::Array(
+            element
+                .children()
+                .filter(Node::is_element)
+                .map(|item| match &array.items {
+                    Some(items) => element_to_json(api, items.resolve(api), item),
+                    None => Value::String(text(item)),
+                })
+                .collect(),
+        ),
+        SchemaKind::Type(Type::Object(_)) | SchemaKind::AllOf { .. } => {
+            object_to_json(api, schema, element)
+        }
+        // Values of other schemas are objects if they look like one
+        _ if element.attributes().len() > 0 || element.children().any(|c| c.is_element()) => {
+            object_to_json(api, schema, element)
+        }
+        _ => text_to_json(schema, &text(element)),
+    }
+}
+
+/// Converts the attributes and child elements of an element to an object. Attributes and
+/// elements that are not properties of the schema are kept as strings, so that validation
+/// reports them.
+fn object_to_json(api: &OpenAPI, schema: &Schema, element: Node) -> Value {
+    let properties: Vec<(&String, &Schema)> = schema
+        .properties_iter(api)
+        .map(|(name, property)| (name, property.resolve(api)))
+        .collect();
+    let mut object = Map::new();
+
+    for attribute in element.attributes() {
+        let property = properties.iter().find(|(name, property)| {
+            let xml = XmlObject::of(property);
+            xml.attribute && xml.name.as_deref().unwrap_or(name) == attribute.name()
+        });
+        match property {
+            Some((name, property)) => {
+                object.insert((*name).clone(), text_to_json(property, attribute.value()))
+            }
+            None => object.insert(
+                attribute.name().to_owned(),
+                Value::String(attribute.value().to_owned()),
+            ),
+        };
+    }
+
+    for child in element.children().filter(Node::is_element) {
+        let tag = child.tag_name().name();
+        // An unwrapped array is a series of elements with the name of its items
+        let item_of = properties.iter().find_map(|(name, property)| {
+            let xml = XmlObject::of(property);
+            let items = array_items(property)?.resolve(api);
+            (!xml.attribute
+                && !xml.wrapped
+                && XmlObject::of(items).name.as_deref().unwrap_or(name) == tag)
+                .then_some((*name, items))
+        });
+        if let Some((name, items)) = item_of {
+            let item = element_to_json(api, items, child);
+            match object
+                .entry(name.clone())
+                .or_insert_with(|| Value::Array(vec![]))
+            {
+                Value::Array(array) => array.push(item),
+                _ => unreachable!("the items of unwrapped arrays are collected in arrays"),
+            }
+            continue;
+        }
+        let property = properties.iter().find(|(name, property)| {
+            let xml = XmlObject::of(property);
+            !xml.attribute && xml.name.as_deref().unwrap_or(name) == tag
+        });
+        match property {
+            Some((name, property)) => {
+                object.insert((*name).clone(), element_to_json(api, property, child))
+            }
+            None => object.insert(tag.to_owned(), Value::String(text(child))),
+        };
+    }
+    Value::Object(object)
+}
+
+/// Converts the text of an element or attribute to a value of the type of the schema. Text
+/// that is not of that type stays a string, so that validation reports it.
+fn text_to_json(schema: &Schema, text: &str) -> Value {
+    let trimmed = text.trim();
+    let value = match &schema.kind {
+        SchemaKind::Type(Type::Integer(_)) => trimmed.parse::<i64>().ok().map(Value::from),
+        SchemaKind::Type(Type::Number(_)) => trimmed
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        SchemaKind::Type(Type::Boolean { .. }) => match trimmed {
+            "true" | "1" => Some(Value::Bool(true)),
+            "false" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        _ => None,
+    };
+    value.unwrap_or_else(|| Value::String(text.to_owned()))
+}
+
+/// The text in an element, without that of its child elements.
+fn text(element: Node) -> String {
+    element
+        .children()
+        .filter(Node::is_text)
+        .filter_map(|node| node.text())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use openapiv3::{OpenAPI, ReferenceOr};
+    use serde_json::json;
+
+    use super::{parse_response, preserve_xml_objects};
+
+    #[test]
+    fn test_parse_response() {
+        let mut document: serde_yaml::Value = serde_yaml::from_str(
+            r#"
+openapi: 3.0.0
+info: {title: Pets, version: "1"}
+paths: {}
+components:
+  schemas:
+    Pet:
+      type: object
+      xml: {name: pet, namespace: "https://example.com/pets", prefix: p}
+      properties:
+        id: {type: integer, xml: {attribute: true}}
+        name: {type: string, xml: {name: petName}}
+        xml: {type: boolean}
+        tags:
+          type: array
+          items: {type: string, xml: {name: tag}}
+        photos:
+          type: array
+          xml: {wrapped: true}
+          items: {type: string, xml: {name: url}}
+"#,
+        )
+        .unwrap();
+        preserve_xml_objects(&mut document);
+        let api: OpenAPI = serde_yaml::from_value(document).unwrap();
+        let schema = ReferenceOr::Reference {
+            reference: "#/components/schemas/Pet".to_owned(),
+        };
+
+        let value = parse_response(
+            &api,
+            &schema,
+            r#"<p:pet xmlns:p="https://example.com/pets" id="7">
+                 <p:petName>Rex</p:petName>
+                 <p:xml>1</p:xml>
+                 <p:tag>good</p:tag>
+                 <p:tag>dog</p:tag>
+                 <p:photos><p:url>a.png</p:url></p:photos>
+               </p:pet>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "id": 7,
+                "name": "Rex",
+                "xml": true,
+                "tags": ["good", "dog"],
+                "photos": ["a.png"],
+            })
+        );
+
+        assert!(parse_response(&api, &schema, "<dog/>").is_err());
+        assert!(parse_response(&api, &schema, "<pet>").is_err());
+    }
+}
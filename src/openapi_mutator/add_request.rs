@@ -14,8 +14,8 @@ use openapiv3::{OpenAPI, RequestBody};
 
 use crate::{
     input::{
-        new_rand_input, parameter::ParameterKind, Body, OpenApiInput, OpenApiRequest,
-        ParameterContents,
+        media_type::MediaTypeHeaders, new_rand_input, parameter::ParameterKind, Body, OpenApiInput,
+        OpenApiRequest, ParameterContents,
     },
     openapi::JsonContent,
     state::HasRandAndOpenAPI,
@@ -90,6 +90,7 @@ where
             method,
             path,
             parameters,
+            media_type_headers: MediaTypeHeaders::default(),
             body,
         });
 
@@ -45,9 +45,7 @@ where
     S: HasRand,
 {
     fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
-        let reference_parameters = input
-            .parameter_filter(&|v| v.is_reference())
-            .map(|(_, v)| v);
+        let reference_parameters = input.references_mut().map(|(_, v)| v);
 
         let random_param = match super::choose(state.rand_mut(), reference_parameters) {
             Some(parameter) => parameter,
@@ -1,7 +1,7 @@
 //! Mutates a request series by changing the method (GET, POST, ...) of one of the HTTP
 //! requests to a random different method.
 
-use std::{borrow::Cow, convert::TryInto};
+use std::{borrow::Cow, convert::TryInto, sync::Arc};
 
 pub use libafl::mutators::mutations::*;
 use libafl::{
@@ -11,9 +11,9 @@ use libafl::{
 use libafl_bolts::{rands::Rand, Named};
 
 use crate::{
-    configuration::{Configuration, MethodMutationStrategy},
-    input::{fix_input_parameters, OpenApiInput},
-    openapi::find_method_indices_for_path,
+    configuration::MethodMutationStrategy,
+    input::{fix_input_parameters, Method, OpenApiInput},
+    openapi::{filter::OperationFilter, find_method_indices_for_path},
     state::HasRandAndOpenAPI,
 };
 
@@ -22,24 +22,23 @@ use crate::{
 /// in the specification are used.
 pub struct DifferentMethodMutator {
     method_mutation_strategy: MethodMutationStrategy,
+    filter: Arc<OperationFilter>,
 }
 
 impl DifferentMethodMutator {
     #[must_use]
     /// Creates a new DifferentMethodMutator
-    pub fn new() -> Self {
+    pub fn new(
+        filter: Arc<OperationFilter>,
+        method_mutation_strategy: MethodMutationStrategy,
+    ) -> Self {
         Self {
-            method_mutation_strategy: Configuration::must_get().method_mutation_strategy,
+            method_mutation_strategy,
+            filter,
         }
     }
 }
 
-impl Default for DifferentMethodMutator {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 impl Named for DifferentMethodMutator {
     fn name(&self) -> &Cow<'static, str> {
         &Cow::Borrowed("differentmethodmutator")
@@ -87,6 +86,15 @@ where
             ],
         };
 
+        // Never mutate into an operation that is excluded from fuzzing
+        let available_methods = available_methods
+            .into_iter()
+            .filter(|(method, _)| {
+                Method::try_from(*method)
+                    .is_ok_and(|method| self.filter.allows(&random_input.path, method))
+            })
+            .collect::<Vec<_>>();
+
         if available_methods.is_empty() {
             return Ok(MutationResult::Skipped);
         }
@@ -52,12 +52,10 @@ where
 
         // Don't forget to fix up the `ParameterContents::Reference`s contained in the
         // input requests!
-        for (_, param) in input.parameter_filter(&|v| v.is_reference()) {
+        for (_, param) in input.references_mut() {
             // We have to increment the reference target by one if it is larger than
             // the random_index
-            let reference_index = param
-                .reference_index()
-                .expect("filtered by parameter_filter");
+            let reference_index = param.reference_index().expect("filtered by references_mut");
             if *reference_index > random_index {
                 *reference_index += 1
             }
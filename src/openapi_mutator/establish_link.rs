@@ -11,13 +11,13 @@ use libafl::{
 use libafl_bolts::Named;
 
 use crate::{
-    input::{OpenApiInput, ParameterContents},
+    input::{pointer, OpenApiInput, ParameterContents},
     state::HasRandAndOpenAPI,
 };
 
 /// The `EstablishLinkMutator` adds a connection to the series of requests.
 /// A connection is a `ParameterContents::Reference` variant in a named parameter
-/// or a request body.
+/// or a request body, at any depth, to a value at any depth in an earlier response.
 pub struct EstablishLinkMutator;
 
 impl EstablishLinkMutator {
@@ -49,14 +49,14 @@ where
 
         // Build a list of (x, y),
         // x is the request index for which the response contains a parameter y
-        // y is the parameter name
+        // y is the parameter name, with the JSON Pointer to it if it is nested
         let request_index_and_parameter_name_pairs = input.return_values(api);
         if request_index_and_parameter_name_pairs.is_empty() {
             return Ok(MutationResult::Skipped);
         }
 
-        // Build a list of parameters with the same name as a return parameter from
-        // an earlier request
+        // Build a list of parameters and body fields (at any depth) with the same name as
+        // a return parameter from an earlier request
         let concrete_parameters = input
             .0
             .iter_mut()
@@ -69,19 +69,31 @@ where
                 request
                     .parameters
                     .iter_mut()
+                    .map(|((name, _), param)| (Cow::Borrowed(name.as_str()), None, param))
+                    .chain(
+                        request
+                            .body
+                            .leaves_mut()
+                            .into_iter()
+                            .map(|(location, param)| {
+                                let name = pointer::field_name(&location).into_owned();
+                                (name.into(), Some(location), param)
+                            }),
+                    )
                     // only consider non-reference parameters for replacement with
                     // a reference
-                    .filter(|(_, v)| !v.is_reference())
+                    .filter(|(_, _, v)| !v.is_reference())
                     // filter: this variable occurs in an earlier request's return value
-                    // maps to: (&mut param, the relevant index into return_values)
-                    .filter_map(move |((name, _), param)| {
+                    // maps to: (&mut param, its location in the body, the relevant index
+                    // into return_values)
+                    .filter_map(move |(name, location, param)| {
                         request_index_and_parameter_name_pairs
                             .iter()
                             // Find the first request index that had the desired parameter name in a response
-                            .position(|(request_index, rv_name)| {
-                                *request_index < current_request_index && name == rv_name
+                            .position(|(request_index, rv_name, _)| {
+                                *request_index < current_request_index && name == *rv_name
                             })
-                            .map(|index_return_values| (param, index_return_values))
+                            .map(|index_return_values| (param, location, index_return_values))
                     })
             });
 
@@ -91,11 +103,13 @@ where
         };
 
         // Make the link
+        let (request_index, parameter_name, source_pointer) =
+            &request_index_and_parameter_name_pairs[random_link.2];
         *random_link.0 = ParameterContents::Reference {
-            request_index: request_index_and_parameter_name_pairs[random_link.1].0,
-            parameter_name: request_index_and_parameter_name_pairs[random_link.1]
-                .1
-                .to_owned(),
+            request_index: *request_index,
+            parameter_name: parameter_name.clone(),
+            source_pointer: source_pointer.clone(),
+            target_pointer: random_link.1,
         };
 
         input.assert_valid(self.name());
@@ -0,0 +1,98 @@
+//! Mutates the filename or content type of a file that is uploaded in a multipart body.
+//!
+//! The contents of files are mutated like any other parameter, but their headers are not
+//! parameter contents. Upload handlers often trust them, for instance to decide where to
+//! store a file or how to serve it later.
+
+use std::borrow::Cow;
+
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::input::{multipart::FormPart, Body, OpenApiInput};
+
+/// Filenames that are designed to trigger path traversal, unexpected file types and
+/// length or encoding issues.
+pub const INTERESTING_FILENAMES: [&str; 9] = [
+    "../../../../../../etc/passwd",
+    "..\\..\\..\\..\\windows\\win.ini",
+    "/tmp/wuppiefuzz.txt",
+    "upload.php",
+    "image.png.html",
+    ".htaccess",
+    "upload\0.png",
+    "",
+    "%2e%2e%2f%2e%2e%2fupload.txt",
+];
+
+/// Content types that differ from what upload handlers usually expect.
+pub const INTERESTING_CONTENT_TYPES: [&str; 6] = [
+    "text/html",
+    "image/svg+xml",
+    "application/x-php",
+    "application/x-msdownload",
+    "application/json",
+    "",
+];
+
+/// The `FormPartMutator` replaces the filename or content type of a file in a multipart body
+/// by an interesting value, or makes the filename very long.
+pub struct FormPartMutator;
+
+impl FormPartMutator {
+    #[must_use]
+    /// Creates a new FormPartMutator
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for FormPartMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for FormPartMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("formpartmutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for FormPartMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let files = input
+            .0
+            .iter_mut()
+            .filter_map(|request| match &mut request.body {
+                Body::MultipartFormData(parts) => Some(parts.values_mut()),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|part| match part {
+                FormPart::File {
+                    filename,
+                    content_type,
+                    ..
+                } => Some((filename, content_type)),
+                FormPart::Field(_) => None,
+            });
+        let rand = state.rand_mut();
+        let Some((filename, content_type)) = rand.choose(files) else {
+            return Ok(MutationResult::Skipped);
+        };
+        match rand.below(core::num::NonZero::new(3).unwrap()) {
+            0 => *filename = rand.choose(INTERESTING_FILENAMES).unwrap().to_owned(),
+            1 => *filename = format!("{}{filename}", "A".repeat(1000)),
+            _ => *content_type = rand.choose(INTERESTING_CONTENT_TYPES).unwrap().to_owned(),
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
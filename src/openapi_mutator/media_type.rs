@@ -0,0 +1,125 @@
+//! Declares other media types for a request than those of its body, in the `Content-Type`
+//! and `Accept` headers.
+//!
+//! Targets often pick a parser from the `Content-Type` header and a response format from the
+//! `Accept` header. Bodies that are declared as something they are not, with unusual charsets
+//! or without a content type at all can confuse these choices.
+
+use std::borrow::Cow;
+
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::input::{
+    media_type::{DeclaredHeader, MediaTypeHeaders},
+    OpenApiInput,
+};
+
+/// Content types that bodies are declared as instead of their own, including vendor types
+/// with the `+json` suffix.
+pub const INTERESTING_CONTENT_TYPES: [&str; 11] = [
+    "application/json",
+    "application/xml",
+    "text/plain",
+    "text/html",
+    "application/x-www-form-urlencoded",
+    "multipart/form-data",
+    "application/octet-stream",
+    "application/x-yaml",
+    "application/vnd.wuppiefuzz+json",
+    "application/merge-patch+json",
+    "application/problem+json",
+];
+
+/// Parameters that are added to the content type of the body.
+pub const INTERESTING_PARAMETERS: [&str; 5] = [
+    "; charset=utf-16",
+    "; charset=iso-8859-1",
+    "; charset=utf-7",
+    ";charset=\"UTF-8\"",
+    "; charset=wuppiefuzz",
+];
+
+/// Media types that are accepted instead of JSON.
+pub const INTERESTING_ACCEPT: [&str; 7] = [
+    "application/xml",
+    "text/html",
+    "text/plain",
+    "*/*",
+    "application/vnd.wuppiefuzz+json",
+    "application/json;q=0, */*;q=0.1",
+    "",
+];
+
+/// The `MediaTypeMutator` declares another content type for the body of a random request,
+/// adds a parameter such as a charset to it or leaves it out, or changes the media types the
+/// request accepts.
+pub struct MediaTypeMutator;
+
+impl MediaTypeMutator {
+    #[must_use]
+    /// Creates a new MediaTypeMutator
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for MediaTypeMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for MediaTypeMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("mediatypemutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for MediaTypeMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let rand = state.rand_mut();
+        let Some(request) = rand.choose(input.0.iter_mut()) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let body_content_type = request.body_content_type();
+        let previous = request.media_type_headers.clone();
+        let MediaTypeHeaders {
+            content_type,
+            accept,
+        } = &mut request.media_type_headers;
+        match rand.below(core::num::NonZero::new(5).unwrap()) {
+            0 => {
+                *content_type = Some(DeclaredHeader::Value(
+                    rand.choose(INTERESTING_CONTENT_TYPES).unwrap().to_owned(),
+                ))
+            }
+            // The parameters of multipart bodies (their boundary) are replaced
+            1 => {
+                let essence = body_content_type.split(';').next().unwrap_or_default();
+                *content_type = Some(DeclaredHeader::Value(format!(
+                    "{essence}{}",
+                    rand.choose(INTERESTING_PARAMETERS).unwrap()
+                )))
+            }
+            2 => *content_type = Some(DeclaredHeader::Omitted),
+            3 => {
+                *accept = Some(DeclaredHeader::Value(
+                    rand.choose(INTERESTING_ACCEPT).unwrap().to_owned(),
+                ))
+            }
+            _ => *accept = Some(DeclaredHeader::Omitted),
+        }
+        if request.media_type_headers == previous {
+            return Ok(MutationResult::Skipped);
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
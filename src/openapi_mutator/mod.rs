@@ -6,7 +6,7 @@
 //! request, changing the parameter values (using a LibAFL byte sequence mutator, for example).
 
 use core::num::NonZero;
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
 
 pub use libafl::mutators::mutations::*;
 use libafl::{
@@ -23,7 +23,9 @@ use libafl_bolts::{
 };
 
 use crate::{
+    configuration::MethodMutationStrategy,
     input::{new_rand_input, parameter::SimpleValue, OpenApiInput, ParameterContents},
+    openapi::filter::OperationFilter,
     state::OpenApiFuzzerState,
 };
 
@@ -45,9 +47,23 @@ pub mod establish_link;
 use establish_link::EstablishLinkMutator;
 pub mod string_interesting;
 use string_interesting::StringInterestingMutator;
+pub mod form_part;
+use form_part::FormPartMutator;
+pub mod xml_attack;
+use xml_attack::XmlAttackMutator;
+pub mod media_type;
+use media_type::MediaTypeMutator;
 
-/// Creates a tuple list containing all available mutators from this module.
-pub fn havoc_mutations_openapi<C, I, R, SC>() -> tuple_list_type!(
+/// Creates a tuple list containing all available mutators from this module. The filter
+/// prevents mutating requests into operations that are excluded from fuzzing, and the
+/// strategy decides which methods a request can be mutated into.
+pub fn havoc_mutations_openapi<C, I, R, SC>(
+    filter: Arc<OperationFilter>,
+    method_mutation_strategy: MethodMutationStrategy,
+) -> tuple_list_type!(
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
+    OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
     OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
     OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
     OpenApiMutator<OpenApiFuzzerState<I, C, R, SC>>,
@@ -112,12 +128,18 @@ where
         OpenApiMutator::from_bytes_mutator(Box::new(WordInterestingMutator::new())),
         OpenApiMutator::from_series_mutator(Box::new(AddRequestMutator::new())),
         OpenApiMutator::from_series_mutator(Box::new(DifferentPathMutator::new())),
-        OpenApiMutator::from_series_mutator(Box::new(DifferentMethodMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(DifferentMethodMutator::new(
+            filter,
+            method_mutation_strategy,
+        ))),
         OpenApiMutator::from_series_mutator(Box::new(DuplicateRequestMutator::new())),
         OpenApiMutator::from_series_mutator(Box::new(SwapRequestsMutator::new())),
         OpenApiMutator::from_series_mutator(Box::new(RemoveRequestMutator::new())),
         OpenApiMutator::from_series_mutator(Box::new(BreakLinkMutator::new())),
         OpenApiMutator::from_series_mutator(Box::new(EstablishLinkMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(FormPartMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(XmlAttackMutator::new())),
+        OpenApiMutator::from_series_mutator(Box::new(MediaTypeMutator::new())),
     )
 }
 
@@ -295,26 +317,30 @@ fn mutate_parameter_contents<S: HasRand>(
     state: &mut S,
     contents_mutator: &mut dyn Mutator<BytesInput, S>,
 ) -> Result<MutationResult, Error> {
-    // Used if we pick an element from an array or object to mutate
+    // Used if we pick an element from an array or object to mutate. Nested references are
+    // left alone, like references at the top level.
     let random_element;
     match param_contents {
         ParameterContents::Object(obj_properties) => {
-            random_element = match state.rand_mut().choose(obj_properties.values_mut()) {
+            let concrete_properties = obj_properties.values_mut().filter(|v| !v.is_reference());
+            random_element = match choose(state.rand_mut(), concrete_properties) {
                 None => {
-                    log::info!("Tried to mutate empty object; skipping. If this happens a lot WuppieFuzz may need improvement on this.");
+                    log::info!("Tried to mutate object without concrete values; skipping. If this happens a lot WuppieFuzz may need improvement on this.");
                     return Ok(MutationResult::Skipped);
                 }
                 Some(element) => element,
             };
         }
+        ParameterContents::Array(arr_contents) if arr_contents.is_empty() => {
+            // Generate a new element for this empty array
+            arr_contents.push(ParameterContents::Bytes(new_rand_input(state.rand_mut())));
+            return Ok(MutationResult::Mutated);
+        }
         ParameterContents::Array(arr_contents) => {
-            random_element = if let Some(element) = state.rand_mut().choose(arr_contents.iter_mut())
-            {
-                element
-            } else {
-                // Generate a new element for this empty array
-                arr_contents.push(ParameterContents::Bytes(new_rand_input(state.rand_mut())));
-                return Ok(MutationResult::Mutated);
+            let concrete_elements = arr_contents.iter_mut().filter(|v| !v.is_reference());
+            random_element = match choose(state.rand_mut(), concrete_elements) {
+                None => return Ok(MutationResult::Skipped),
+                Some(element) => element,
             };
         }
         ParameterContents::LeafValue(leaf) => {
@@ -336,16 +362,11 @@ fn mutate_parameter_contents<S: HasRand>(
             return mutation_result;
         }
         ParameterContents::Reference { .. } => unreachable!(
-            "Non-nested reference parameters should have been filtered out of concrete_parameters"
+            "Reference parameters should have been filtered out of concrete_parameters and their elements"
         ),
     }
-    if let ParameterContents::Reference { .. } = random_element {
-        log::warn!("Tried to mutate nested reference. If this happens a lot some solution should be implemented here. Skipping for now.");
-        Ok(MutationResult::Skipped)
-    } else {
-        // This was nested in an array or object, recursively mutate
-        mutate_parameter_contents(random_element, state, contents_mutator)
-    }
+    // This was nested in an array or object, recursively mutate
+    mutate_parameter_contents(random_element, state, contents_mutator)
 }
 
 /// This is an alternative for `Rand::choose`, which requires the given iterator
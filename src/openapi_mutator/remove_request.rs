@@ -52,7 +52,7 @@ where
 
         // Don't forget to fix up the `ParameterContents::Reference`s contained in the
         // input requests!
-        for (_, param) in input.parameter_filter(&|v| v.is_reference()) {
+        for (_, param) in input.references_mut() {
             // We have to break any `ParameterContents::Reference`s to the removed request
             param.break_reference_if_target(state.rand_mut(), |i| i == random_index);
             // We have to decrement the reference target by one if it is larger than
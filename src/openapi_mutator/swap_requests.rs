@@ -56,11 +56,9 @@ where
 
         // Don't forget to fix up the `ParameterContents::Reference`s contained in the
         // input requests!
-        for (appears_in, param) in input.parameter_filter(&|v| v.is_reference()) {
+        for (appears_in, param) in input.references_mut() {
             // Swap reference targets if they refer to our swapped requests
-            let reference_index = param
-                .reference_index()
-                .expect("filtered by parameter_filter");
+            let reference_index = param.reference_index().expect("filtered by references_mut");
             if *reference_index == random_index1 {
                 *reference_index = random_index2
             } else if *reference_index == random_index2 {
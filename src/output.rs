@@ -0,0 +1,244 @@
+//! Locations of everything a fuzzing campaign writes to disk.
+//!
+//! Without an output directory, the corpus, the findings and the reports are written relative
+//! to the working directory: `queue`, `crashes`, `crash_buckets.json`, `timeouts`, `resets`,
+//! `reports/<timestamp>` and `reports/grafana/report.db`.
+//!
+//! With `--output-dir <DIR>`, every run gets its own directory `<DIR>/runs/<timestamp>`
+//! containing `queue`, `crashes`, `crash_buckets.json`, `timeouts`, `resets` and `reports`,
+//! and `<DIR>/latest` links to the most recent run. The report database distinguishes runs
+//! itself, so it is shared by all runs and lives in `<DIR>/grafana/report.db`. When the
+//! campaign ends, a `manifest.json` listing the produced files is written to the run
+//! directory.
+
+use std::{
+    fs::{create_dir_all, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::{configuration::Configuration, crash_buckets::BUCKETS_FILE};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// The paths to which a fuzzing campaign writes its output.
+#[derive(Debug)]
+pub struct OutputPaths {
+    /// Directory of this run, if an output directory is configured
+    run_dir: Option<PathBuf>,
+    /// Time at which the run started, which also names its directories
+    started: DateTime<Utc>,
+    /// Directory of the evolving corpus
+    pub queue: PathBuf,
+    /// Directory of the inputs that triggered a crash
+    pub crashes: PathBuf,
+    /// File with the crash buckets, next to the crashes directory
+    pub crash_buckets: PathBuf,
+    /// Directory of the inputs with a request that timed out
+    pub timeouts: PathBuf,
+    /// Directory of the inputs with a request whose connection the target dropped
+    pub resets: PathBuf,
+    /// Directory of the reports of this run
+    pub reports: PathBuf,
+    /// Database with the requests and responses of all runs
+    pub report_db: PathBuf,
+}
+
+/// Summary of a run, written to its directory when the run ends.
+#[derive(Serialize)]
+struct Manifest<'a> {
+    started: String,
+    finished: String,
+    seed: u64,
+    openapi_spec: Option<&'a Path>,
+    report_db: &'a Path,
+    /// All files in the run directory, relative to it
+    files: Vec<PathBuf>,
+}
+
+impl OutputPaths {
+    /// Determines the output paths of a new run, and creates the run directory
+    /// and the reports directory (if reporting is enabled).
+    pub fn new(config: &Configuration) -> Result<Self> {
+        let started = Utc::now();
+        let timestamp = format!("{}", started.format("%Y-%m-%dT%H%M%S%.3fZ"));
+        let paths = match &config.output_dir {
+            None => Self {
+                run_dir: None,
+                started,
+                queue: PathBuf::from("queue"),
+                crashes: PathBuf::from("crashes"),
+                crash_buckets: PathBuf::from(BUCKETS_FILE),
+                timeouts: PathBuf::from("timeouts"),
+                resets: PathBuf::from("resets"),
+                reports: Path::new("reports").join(&timestamp),
+                report_db: PathBuf::from("reports/grafana/report.db"),
+            },
+            Some(output_dir) => {
+                let run_dir = output_dir.join("runs").join(&timestamp);
+                create_dir_all(&run_dir).with_context(|| {
+                    format!("Could not create run directory {}", run_dir.display())
+                })?;
+                update_latest_link(output_dir, &timestamp);
+                Self {
+                    queue: run_dir.join("queue"),
+                    crashes: run_dir.join("crashes"),
+                    crash_buckets: run_dir.join(BUCKETS_FILE),
+                    timeouts: run_dir.join("timeouts"),
+                    resets: run_dir.join("resets"),
+                    reports: run_dir.join("reports"),
+                    report_db: output_dir.join("grafana").join("report.db"),
+                    run_dir: Some(run_dir),
+                    started,
+                }
+            }
+        };
+        if config.report {
+            create_dir_all(&paths.reports).with_context(|| {
+                format!(
+                    "Could not create reports directory {}",
+                    paths.reports.display()
+                )
+            })?;
+        }
+        Ok(paths)
+    }
+
+    /// Writes the manifest listing everything this run produced to the run directory.
+    /// Does nothing if no output directory is configured.
+    pub fn write_manifest(&self, config: &Configuration) -> Result<()> {
+        let Some(run_dir) = &self.run_dir else {
+            return Ok(());
+        };
+        let mut files = WalkDir::new(run_dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            // Skip the lock and metadata files LibAFL keeps next to the inputs
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|entry| entry.path().strip_prefix(run_dir).ok().map(Path::to_owned))
+            .filter(|path| path != Path::new(MANIFEST_FILE))
+            .collect::<Vec<_>>();
+        files.sort();
+        let manifest = Manifest {
+            started: self.started.to_rfc3339(),
+            finished: Utc::now().to_rfc3339(),
+            seed: config.seed,
+            openapi_spec: config.openapi_spec.as_deref(),
+            report_db: &self.report_db,
+            files,
+        };
+        let manifest_path = run_dir.join(MANIFEST_FILE);
+        let mut writer = BufWriter::new(
+            File::create(&manifest_path)
+                .with_context(|| format!("Could not create {}", manifest_path.display()))?,
+        );
+        serde_json::to_writer_pretty(&mut writer, &manifest)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Points `<output_dir>/latest` to the run directory `runs/<timestamp>`. Failing to do so
+/// (e.g. on Windows without the privilege to create symlinks) is not fatal.
+fn update_latest_link(output_dir: &Path, timestamp: &str) {
+    let latest = output_dir.join("latest");
+    let target = Path::new("runs").join(timestamp);
+    if latest.symlink_metadata().is_ok() {
+        #[cfg(unix)]
+        let removed = std::fs::remove_file(&latest);
+        #[cfg(windows)]
+        let removed = std::fs::remove_dir(&latest);
+        if let Err(err) = removed {
+            warn!("Could not remove old link {}: {err}", latest.display());
+            return;
+        }
+    }
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(&target, &latest);
+    #[cfg(windows)]
+    let linked = std::os::windows::fs::symlink_dir(&target, &latest);
+    if let Err(err) = linked {
+        warn!(
+            "Could not link {} to the latest run: {err}",
+            latest.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{canonicalize, create_dir_all, read_to_string, write},
+        path::PathBuf,
+    };
+
+    use super::OutputPaths;
+    use crate::configuration::{Configuration, PartialConfiguration};
+
+    fn config(output_dir: Option<PathBuf>) -> Configuration {
+        PartialConfiguration {
+            openapi_spec: Some("openapi.yaml".into()),
+            output_dir,
+            seed: Some(7),
+            ..Default::default()
+        }
+        .try_into()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_output_paths() {
+        // Without an output directory, everything is relative to the working directory
+        let paths = OutputPaths::new(&config(None)).unwrap();
+        assert_eq!(paths.queue, PathBuf::from("queue"));
+        assert_eq!(paths.crashes, PathBuf::from("crashes"));
+        assert!(paths.reports.starts_with("reports"));
+
+        // With one, every run gets its own directory and `latest` links to the last run
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(Some(dir.path().to_owned()));
+        let first = OutputPaths::new(&config).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let second = OutputPaths::new(&config).unwrap();
+        let first_run = first.run_dir.as_ref().unwrap();
+        let second_run = second.run_dir.as_ref().unwrap();
+        assert_ne!(first_run, second_run);
+        assert!(first_run.starts_with(dir.path().join("runs")));
+        assert_eq!(second.queue, second_run.join("queue"));
+        assert_eq!(second.crashes, second_run.join("crashes"));
+        assert_eq!(first.report_db, second.report_db);
+        assert_eq!(
+            canonicalize(dir.path().join("latest")).unwrap(),
+            canonicalize(second_run).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(Some(dir.path().to_owned()));
+        let paths = OutputPaths::new(&config).unwrap();
+        create_dir_all(&paths.crashes).unwrap();
+        write(paths.crashes.join("4a5b"), "[]").unwrap();
+        // LibAFL's lock and metadata files are not listed
+        write(paths.crashes.join(".4a5b.lafl_lock"), "").unwrap();
+        write(paths.crashes.join(".4a5b.metadata"), "{}").unwrap();
+        paths.write_manifest(&config).unwrap();
+        // Writing it again does not list the manifest itself
+        paths.write_manifest(&config).unwrap();
+
+        let manifest_path = paths.run_dir.as_ref().unwrap().join("manifest.json");
+        let manifest: serde_json::Value =
+            serde_json::from_str(&read_to_string(manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest["seed"], 7);
+        assert_eq!(manifest["openapi_spec"], "openapi.yaml");
+        assert_eq!(manifest["files"], serde_json::json!(["crashes/4a5b"]));
+    }
+}
@@ -1,9 +1,12 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
 use serde_json::Value;
 
 use crate::{
-    input::{Body, Method, OpenApiRequest, ParameterContents::Object},
+    input::{
+        multipart::FormPart, pointer, xml::XmlBody, Body, Method, OpenApiRequest,
+        ParameterContents::Object,
+    },
     openapi::validate_response::Response,
 };
 
@@ -11,11 +14,104 @@ use crate::{
 /// are made. This allows the harness to insert the values in subsequent requests
 /// if a parameter contains a backreference to an earlier request.
 #[derive(Debug, Clone)]
-pub struct ParameterFeedback(Vec<HashMap<String, Value>>);
+pub struct ParameterFeedback(Vec<RequestValues>);
+
+/// The values collected from a single request.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+struct RequestValues {
+    /// Values saved by name, such as cookies
+    named: HashMap<String, Value>,
+    /// Values saved by a JSON Pointer, such as placeholders for nested values
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pointed: HashMap<String, Value>,
+    /// The json body of the response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response: Option<Value>,
+    /// The body of the request, if it was posted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    posted: Option<Value>,
+}
+
+impl RequestValues {
+    /// Returns the value with the given name, or at the given JSON Pointer if there is one.
+    /// Pointers point into the response, or else into the posted body. Names are looked up
+    /// in the posted body, the values saved by name and the top level of the response, in
+    /// that order.
+    fn get(&self, name: &str, pointer: Option<&str>) -> Option<&Value> {
+        if let Some(pointer) = pointer {
+            return self.pointed.get(pointer).or_else(|| {
+                [&self.response, &self.posted]
+                    .into_iter()
+                    .flatten()
+                    .find_map(|document| document.pointer(pointer))
+            });
+        }
+        self.posted
+            .as_ref()
+            .and_then(|posted| posted.get(name))
+            .or_else(|| self.named.get(name))
+            .or_else(|| top_level(self.response.as_ref()?)?.get(name))
+    }
+
+    /// Returns all values with their names, and the JSON Pointers to those that are nested
+    /// deeper than the top level of the response, up to `MAX_POINTER_DEPTH`.
+    fn values(&self) -> Vec<(Cow<'_, str>, Option<String>, &Value)> {
+        let mut values: Vec<_> = self
+            .named
+            .iter()
+            .map(|(name, value)| (Cow::Borrowed(name.as_str()), None, value))
+            .collect();
+        values.extend(
+            self.pointed.iter().map(|(pointer, value)| {
+                (pointer::field_name(pointer), Some(pointer.clone()), value)
+            }),
+        );
+        if let Some(Value::Object(fields)) = &self.posted {
+            values.extend(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.as_str().into(), None, value)),
+            );
+        }
+        if let Some(response) = &self.response {
+            let top_level = match response {
+                Value::Array(_) => "/0",
+                _ => "",
+            };
+            values.extend(pointer::value_leaves(response).into_iter().map(
+                |(leaf_pointer, value)| {
+                    let name = pointer::field_name(&leaf_pointer).into_owned().into();
+                    match leaf_pointer.strip_prefix(top_level) {
+                        Some(rest) if pointer::tokens(rest).count() == 1 => (name, None, value),
+                        _ => (name, Some(leaf_pointer), value),
+                    }
+                },
+            ));
+        }
+        values
+    }
+}
+
+/// The object at the top level of a response: the response itself, or its first element
+/// if it is an array.
+fn top_level(response: &Value) -> Option<&Value> {
+    match response {
+        Value::Array(elements) => elements.first(),
+        _ => Some(response),
+    }
+}
 
 impl From<&Vec<HashMap<String, Value>>> for ParameterFeedback {
     fn from(collection: &Vec<HashMap<String, Value>>) -> Self {
-        Self(collection.clone())
+        Self(
+            collection
+                .iter()
+                .map(|named| RequestValues {
+                    named: named.clone(),
+                    ..RequestValues::default()
+                })
+                .collect(),
+        )
     }
 }
 
@@ -40,20 +136,17 @@ impl<'de> serde::Deserialize<'de> for ParameterFeedback {
 impl ParameterFeedback {
     #[must_use]
     pub fn new(num_requests: usize) -> Self {
-        Self(vec![HashMap::new(); num_requests])
+        Self(vec![RequestValues::default(); num_requests])
     }
 
-    /// Returns the value saved for the given request
-    pub fn get(&self, request_index: usize, param: &str) -> Option<&Value> {
-        // Tuple indexing leads to clones... Better to implement as double hashmap?
-        self.0.get(request_index)?.get(param)
+    /// Returns the value saved for the given request, by name or, for values nested in the
+    /// response (or posted body) of the request, by a JSON Pointer.
+    pub fn get(&self, request_index: usize, param: &str, pointer: Option<&str>) -> Option<&Value> {
+        self.0.get(request_index)?.get(param, pointer)
     }
 
     pub fn contains(&self, request_index: usize, param: &str) -> bool {
-        self.0
-            .get(request_index)
-            .map(|m| m.contains_key(param))
-            .unwrap_or(false)
+        self.get(request_index, param, None).is_some()
     }
 
     /// Adds the given parameter/value combination to memory. Returns whether successful
@@ -61,37 +154,26 @@ impl ParameterFeedback {
     pub fn set(&mut self, request_index: usize, param: String, value: Value) -> bool {
         self.0
             .get_mut(request_index)
-            .map(|m| m.insert(param, value))
+            .map(|values| values.named.insert(param, value))
+            .is_some()
+    }
+
+    /// Adds a value at the given JSON Pointer to memory, which is found before the response.
+    /// Returns whether successful, as `set` does.
+    pub fn set_pointer(&mut self, request_index: usize, pointer: String, value: Value) -> bool {
+        self.0
+            .get_mut(request_index)
+            .map(|values| values.pointed.insert(pointer, value))
             .is_some()
     }
 
     /// Processes the values returned in a Response.
     ///
-    /// The body is parsed as a json object or an array of objects, and if successful,
-    /// the fields are saved as parameter values. Cookies set as a `Set-Cookie` header
-    /// are saved in their `param=value` form.
+    /// The body is parsed as json, and if successful, its values are saved. Cookies set as
+    /// a `Set-Cookie` header are saved in their `param=value` form.
     pub fn process_response(&mut self, request_index: usize, mut response: Response) {
-        // We take any returned json values and save key-value parameters we find
-        // (e.g. id = 37) for use as parameters in later requests.
-        match response.json::<serde_json::Value>() {
-            // Objects in responses: save all field/value combinations
-            Ok(serde_json::Value::Object(hashmap)) => {
-                for (param, value) in hashmap.into_iter() {
-                    self.set(request_index, param, value);
-                }
-            }
-            // Arrays in responses: take the first element, which should be an object,
-            // and save all field/value combinations.
-            // Since we can only save one value per field name per request, we can only
-            // keep one object worth of values, and we forget the rest.
-            Ok(serde_json::Value::Array(vec)) => {
-                if let Some(serde_json::Value::Object(hashmap)) = vec.into_iter().next() {
-                    for (param, value) in hashmap.into_iter() {
-                        self.set(request_index, param, value);
-                    }
-                }
-            }
-            _ => (),
+        if let Ok(body) = response.json::<serde_json::Value>() {
+            self.process_json(request_index, body);
         }
         // We also record the values of any Set-Cookie headers
         for (name, value) in response.cookies() {
@@ -99,22 +181,112 @@ impl ParameterFeedback {
         }
     }
 
+    /// Processes the json body of a response, as in `process_response`.
+    ///
+    /// The fields of an object, or of the first element of an array, can then be found by
+    /// their name (e.g. id = 37) for use as parameters in later requests. Since we can only
+    /// find one value per field name per request, values nested deeper or in other elements
+    /// are found by a JSON Pointer instead.
+    pub fn process_json(&mut self, request_index: usize, body: Value) {
+        if let Some(values) = self.0.get_mut(request_index) {
+            values.response = Some(body);
+        }
+    }
+
+    /// Finds the latest request before `before` for which the given value was saved, and
+    /// returns its index, the name of the value and the JSON Pointer to it if it is nested
+    /// in the response. Values saved under the given name are preferred. Other names only
+    /// match values that are unlikely to be equal by chance: strings of at least four
+    /// characters and numbers of at least three digits.
+    pub fn find_reference(
+        &self,
+        before: usize,
+        name: &str,
+        value: &Value,
+    ) -> Option<(usize, String, Option<String>)> {
+        let text = match value {
+            Value::String(text) if !text.is_empty() => text.clone(),
+            Value::Number(number) => number.to_string(),
+            _ => return None,
+        };
+        let distinctive = match value {
+            Value::String(_) => text.chars().count() >= 4,
+            _ => text.trim_start_matches('-').len() >= 3,
+        };
+        let matches = |saved: &Value| match saved {
+            Value::String(saved) => *saved == text,
+            Value::Number(saved) => saved.to_string() == text,
+            _ => false,
+        };
+        let earlier = || self.0.iter().take(before).enumerate().rev();
+        // Values at the top level are preferred, and short pointers over longer ones
+        let preference = |pointer: &Option<String>| {
+            pointer
+                .as_ref()
+                .map(|pointer| (pointer.len(), pointer.clone()))
+        };
+        let found = |index: usize, values: Vec<(Cow<'_, str>, Option<String>, &Value)>| {
+            values
+                .into_iter()
+                .min_by_key(|(_, pointer, _)| preference(pointer))
+                .map(|(saved_name, pointer, _)| (index, saved_name.into_owned(), pointer))
+        };
+        earlier()
+            .find_map(|(index, values)| {
+                let values = values
+                    .values()
+                    .into_iter()
+                    .filter(|(saved_name, _, saved)| {
+                        saved_name.eq_ignore_ascii_case(name) && matches(saved)
+                    });
+                found(index, values.collect())
+            })
+            .or_else(|| {
+                earlier()
+                    .filter(|_| distinctive)
+                    .find_map(|(index, values)| {
+                        let values = values
+                            .values()
+                            .into_iter()
+                            .filter(|(_, _, saved)| matches(saved));
+                        found(index, values.collect())
+                    })
+            })
+    }
+
     /// Process any body values in a request if its type is POST.
     ///
-    /// If there is a request body with fields, either JSON or form data, the contents are
+    /// If there is a request body with fields, either JSON, XML or form data, the contents are
     /// added to the ParameterFeedback. This is useful because if create make a resource in the
     /// program under test, future requests need to be able to refer back to it.
     pub fn process_post_request(&mut self, request_index: usize, request: OpenApiRequest) {
-        match request.body {
-            Body::ApplicationJson(Object(obj_contents))
-            | Body::XWwwFormUrlencoded(Object(obj_contents))
-                if request.method == Method::Post =>
-            {
-                for (param, value) in obj_contents {
-                    self.set(request_index, param, value.to_value());
-                }
-            }
-            _ => (),
+        if request.method != Method::Post {
+            return;
+        }
+        let posted = match request.body {
+            Body::ApplicationJson(contents @ Object(_))
+            | Body::XWwwFormUrlencoded(contents @ Object(_))
+            | Body::ApplicationXml(XmlBody {
+                contents: contents @ Object(_),
+                ..
+            })
+            | Body::Raw {
+                bytes: contents @ Object(_),
+                ..
+            } => contents.to_value(),
+            Body::MultipartFormData(parts) => Value::Object(
+                parts
+                    .into_iter()
+                    .filter_map(|(param, part)| match part {
+                        FormPart::Field(value) => Some((param, value.to_value())),
+                        FormPart::File { .. } => None,
+                    })
+                    .collect(),
+            ),
+            _ => return,
+        };
+        if let Some(values) = self.0.get_mut(request_index) {
+            values.posted = Some(posted);
         }
     }
 
@@ -0,0 +1,220 @@
+//! Limits on the requests sent to the target, shared by all workers.
+//!
+//! The number of requests per second and the number of concurrent requests can be capped.
+//! When the target throttles a request (with HTTP 429 Too Many Requests, or 503 Service
+//! Unavailable with a `Retry-After` header), all workers pause for the time the target asks
+//! for, or for an exponentially growing back-off if it does not say. Time spent paused does
+//! not count towards the timeout of the campaign.
+
+use std::{
+    num::{NonZeroU32, NonZeroUsize},
+    sync::{Arc, Mutex, MutexGuard, OnceLock},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+
+use crate::configuration::Configuration;
+
+/// Back-off after the first throttled response without a `Retry-After` header
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest pause, also when the target asks for a longer one
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// How long to wait before trying again when too many requests are in flight
+const CONCURRENCY_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Limits the requests of all workers.
+pub struct RateLimiter {
+    /// Minimum time between two requests, if the rate is limited
+    interval: Option<Duration>,
+    /// Maximum number of requests in flight at the same time
+    max_concurrent: Option<NonZeroUsize>,
+    state: Mutex<LimiterState>,
+}
+
+struct LimiterState {
+    /// Earliest time at which the next request may be sent
+    next_request: Instant,
+    /// Time until which all requests are paused because the target throttled one
+    paused_until: Instant,
+    /// Total time for which requests are paused, including the current pause
+    paused_total: Duration,
+    /// Pause after the next throttled response without a `Retry-After` header
+    backoff: Duration,
+    /// Number of requests in flight
+    in_flight: usize,
+}
+
+/// Permission to send a request, which counts as in flight until the permit is dropped.
+pub struct Permit<'a>(&'a RateLimiter);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.0.lock().in_flight -= 1;
+    }
+}
+
+impl RateLimiter {
+    pub fn new(
+        requests_per_second: Option<NonZeroU32>,
+        max_concurrent: Option<NonZeroUsize>,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            interval: requests_per_second.map(|rate| Duration::from_secs(1) / rate.get()),
+            max_concurrent,
+            state: Mutex::new(LimiterState {
+                next_request: now,
+                paused_until: now,
+                paused_total: Duration::ZERO,
+                backoff: INITIAL_BACKOFF,
+                in_flight: 0,
+            }),
+        }
+    }
+
+    /// Returns the rate limiter shared by all workers, configured from the configuration.
+    pub fn shared(config: &Configuration) -> Arc<Self> {
+        static SHARED: OnceLock<Arc<RateLimiter>> = OnceLock::new();
+        Arc::clone(
+            SHARED.get_or_init(|| {
+                Arc::new(Self::new(config.rate_limit, config.max_concurrent_requests))
+            }),
+        )
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LimiterState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Returns permission to send a request if one may be sent right away, or else how long to
+    /// wait before trying again. This never blocks, so requests can be sent concurrently from a
+    /// single thread.
+    pub fn try_acquire(&self) -> Result<Permit<'_>, Duration> {
+        let mut state = self.lock();
+        let now = Instant::now();
+        let mut ready_at = state.paused_until;
+        if self.interval.is_some() {
+            ready_at = ready_at.max(state.next_request);
+        }
+        if ready_at > now {
+            return Err(ready_at - now);
+        }
+        if self
+            .max_concurrent
+            .is_some_and(|max| state.in_flight >= max.get())
+        {
+            return Err(CONCURRENCY_POLL_INTERVAL);
+        }
+        if let Some(interval) = self.interval {
+            state.next_request = now.max(state.next_request) + interval;
+        }
+        state.in_flight += 1;
+        Ok(Permit(self))
+    }
+
+    /// Pauses all requests because the target throttled one, for `retry_after` if the target
+    /// asked for a specific delay, or else for the current back-off (which then doubles).
+    pub fn throttle(&self, status: StatusCode, retry_after: Option<Duration>) {
+        let mut state = self.lock();
+        let delay = match retry_after {
+            Some(retry_after) => retry_after.min(MAX_BACKOFF),
+            None => {
+                let backoff = state.backoff;
+                state.backoff = (backoff * 2).min(MAX_BACKOFF);
+                backoff
+            }
+        };
+        let now = Instant::now();
+        let until = now + delay;
+        if until > state.paused_until {
+            warn!("The target throttled a request ({status}), pausing for {delay:?}");
+            let extension = until - state.paused_until.max(now);
+            state.paused_total += extension;
+            state.paused_until = until;
+        }
+    }
+
+    /// Resets the back-off after a response that was not throttled.
+    pub fn reset_backoff(&self) {
+        self.lock().backoff = INITIAL_BACKOFF;
+    }
+
+    /// Returns how long requests have been paused so far.
+    pub fn paused(&self) -> Duration {
+        let state = self.lock();
+        let remaining = state.paused_until.saturating_duration_since(Instant::now());
+        state.paused_total - remaining
+    }
+}
+
+/// Whether the target throttled the request that produced this response.
+pub fn is_throttled(response: &Response) -> bool {
+    response.status() == StatusCode::TOO_MANY_REQUESTS
+        || (response.status() == StatusCode::SERVICE_UNAVAILABLE
+            && response.headers().contains_key(RETRY_AFTER))
+}
+
+/// Returns the delay the target asks for in the `Retry-After` header of a response, which
+/// is either a number of seconds or a date.
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        num::{NonZeroU32, NonZeroUsize},
+        time::{Duration, Instant},
+    };
+
+    use reqwest::StatusCode;
+
+    use super::{Permit, RateLimiter};
+
+    fn acquire(limiter: &RateLimiter) -> Permit<'_> {
+        loop {
+            match limiter.try_acquire() {
+                Ok(permit) => return permit,
+                Err(delay) => std::thread::sleep(delay),
+            }
+        }
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(NonZeroU32::new(20), None);
+        let start = Instant::now();
+        for _ in 0..5 {
+            drop(acquire(&limiter));
+        }
+        // The first request is sent right away, the others 50ms apart
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        limiter.throttle(
+            StatusCode::TOO_MANY_REQUESTS,
+            Some(Duration::from_millis(100)),
+        );
+        let paused = Instant::now();
+        drop(acquire(&limiter));
+        assert!(paused.elapsed() >= Duration::from_millis(100));
+        assert!(limiter.paused() >= Duration::from_millis(100));
+
+        let limiter = RateLimiter::new(None, NonZeroUsize::new(1));
+        let permit = limiter.try_acquire().ok().unwrap();
+        assert!(limiter.try_acquire().is_err());
+        drop(permit);
+        assert!(limiter.try_acquire().is_ok());
+    }
+}
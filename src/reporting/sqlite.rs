@@ -1,4 +1,4 @@
-use std::{fs::create_dir_all, path::Path};
+use std::{fs::create_dir_all, path::Path, sync::Mutex};
 
 use anyhow::Context;
 use chrono::SecondsFormat;
@@ -15,12 +15,17 @@ use crate::{
 };
 
 /// Instantiates a MySqLite reporter if desired by the configuration
-pub fn get_reporter(config: &Configuration) -> Result<Option<MySqLite>, anyhow::Error> {
+pub fn get_reporter(
+    config: &Configuration,
+    path: &Path,
+) -> Result<Option<MySqLite>, anyhow::Error> {
     if !config.report {
         return Ok(None);
     }
-    create_dir_all("reports/grafana")?;
-    Ok(Some(MySqLite::new(Path::new("reports/grafana/report.db"))?))
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    Ok(Some(MySqLite::new(path, config.seed)?))
 }
 
 pub struct MySqLite {
@@ -29,17 +34,30 @@ pub struct MySqLite {
 }
 
 impl MySqLite {
-    pub fn new(path: &Path) -> anyhow::Result<MySqLite> {
+    pub fn new(path: &Path, seed: u64) -> anyhow::Result<MySqLite> {
         let conn = Connection::open(path).expect("Can not create database file for reporting");
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS runs (
                 id INTEGER PRIMARY KEY NOT NULL,
-                `timestamp` DATETIME NOT NULL
+                `timestamp` DATETIME NOT NULL,
+                `seed` varchar(20)
             )",
             [],
         )
         .context("Could not create `runs` table")?;
+        // Databases created by older versions do not have the seed column yet
+        let has_seed: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('runs') WHERE name = 'seed'",
+                [],
+                |row| row.get(0),
+            )
+            .context("Could not inspect `runs` table")?;
+        if !has_seed {
+            conn.execute("ALTER TABLE runs ADD COLUMN `seed` varchar(20)", [])
+                .context("Could not add `seed` column to `runs` table")?;
+        }
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS requests (
@@ -90,16 +108,31 @@ impl MySqLite {
 
         info!("Created tables for the reporting");
 
+        // All workers of this campaign report under the same run
+        static RUN_ID: Mutex<Option<i64>> = Mutex::new(None);
+        let mut run_id = RUN_ID.lock().unwrap();
+        if let Some(run_id) = *run_id {
+            return Ok(MySqLite { conn, run_id });
+        }
+
         let mut stmt = conn
-            .prepare("INSERT INTO runs (timestamp) VALUES(?)")
+            .prepare("INSERT INTO runs (timestamp, seed) VALUES(?,?)")
             .context("Could not prepare insert statement for runs")?;
         let time = chrono::offset::Utc::now();
-        let run_id = stmt
-            .insert([time.to_rfc3339_opts(SecondsFormat::Millis, true)])
+        let new_run_id = stmt
+            .insert([
+                time.to_rfc3339_opts(SecondsFormat::Millis, true),
+                // As text, since SQLite integers can not hold all u64 values
+                seed.to_string(),
+            ])
             .context("Could not create new run")?;
         // end borrow of connection
         drop(stmt);
-        Ok(MySqLite { conn, run_id })
+        *run_id = Some(new_run_id);
+        Ok(MySqLite {
+            conn,
+            run_id: new_run_id,
+        })
     }
 }
 
@@ -17,21 +17,17 @@ use crate::{
         validate_response::{validate_response, Response},
     },
     parameter_feedback::ParameterFeedback,
+    transport_failure::TransportFailure,
 };
 
 /// Reproduces a given input file generated by the fuzzer (as a crash file or a corpus entry).
 pub fn reproduce(input_file: &Path) -> Result<()> {
     let config = Configuration::get().map_err(anyhow::Error::msg)?;
     crate::setup_logging(config);
-    let api = crate::get_api_spec(
-        config
-            .openapi_spec
-            .as_ref()
-            .ok_or_else(|| anyhow!("No OpenAPI specification given"))?,
-    )?;
+    let api = crate::openapi::get_target_api_spec(config)?;
     let inputs = OpenApiInput::from_file(input_file)?;
 
-    let (authentication, cookie_store, client) = crate::build_http_client()?;
+    let (authentication, cookie_store, client, _) = crate::build_http_client(config)?;
 
     println!(
         "Input file {:?} contains {} inputs",
@@ -39,6 +35,11 @@ pub fn reproduce(input_file: &Path) -> Result<()> {
         inputs.0.len()
     );
 
+    if !config.reset_hooks.is_empty() {
+        info!("Resetting the state of the target");
+        crate::reset::run_hooks(&config.reset_hooks, &client, &api)?;
+    }
+
     let mut parameter_feedback = ParameterFeedback::new(inputs.0.len());
 
     for (request_index, request) in inputs.0.iter().enumerate() {
@@ -97,7 +98,11 @@ pub fn reproduce(input_file: &Path) -> Result<()> {
                 }
             }
             Err(e) => {
-                error!("Error sending the request: {}", e);
+                error!(
+                    "Error sending the request: {}: {}",
+                    TransportFailure::classify(&e),
+                    e
+                );
                 break;
             }
         }
@@ -0,0 +1,176 @@
+//! Hooks that reset the state of the target before a request sequence.
+//!
+//! The requests of a sequence change the state of the target, so its responses depend on
+//! everything that was sent before. Reset hooks bring the target back to a known state before
+//! every sequence (or every `--reset-interval` sequences), so coverage and crashes do not
+//! depend on earlier sequences. The reproducer runs the same hooks before replaying an input.
+//!
+//! Hooks are given as `http:<METHOD> <URL>` (where the URL may be a path relative to the
+//! target URL, such as `http:POST /test/reset`), `command:<COMMAND>` (run through the shell)
+//! or `sql:<DATABASE> < <SCRIPT>` (a SQL script run against a local SQLite database). Hooks
+//! run in the order they are given, and a hook that fails stops the campaign.
+
+use std::{fmt::Display, path::PathBuf, str::FromStr};
+
+use anyhow::{Context, Result};
+use openapiv3::OpenAPI;
+use reqwest::{blocking::Client, Method};
+use rusqlite::Connection;
+use serde::Deserialize;
+
+use crate::{openapi::server::server_relative_url, target::shell_command};
+
+/// A way to reset the state of the target.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum ResetHook {
+    /// HTTP request, to a full URL or to a path relative to the target URL
+    Http { method: Method, url: String },
+    /// Shell command
+    Command(String),
+    /// SQL script run against a SQLite database
+    Sql { database: PathBuf, script: PathBuf },
+}
+
+impl FromStr for ResetHook {
+    type Err = anyhow::Error;
+
+    fn from_str(hook: &str) -> Result<Self, Self::Err> {
+        let Some((kind, value)) = hook.split_once(':') else {
+            bail!("Reset hook {hook} should have the form <kind>:<value>");
+        };
+        Ok(match kind {
+            "http" => {
+                let Some((method, url)) = value.trim().split_once(' ') else {
+                    bail!("HTTP reset hook {hook} should have the form http:<METHOD> <URL>");
+                };
+                Self::Http {
+                    method: method
+                        .to_uppercase()
+                        .parse()
+                        .with_context(|| format!("Invalid method in reset hook {hook}"))?,
+                    url: url.trim().to_owned(),
+                }
+            }
+            "command" => Self::Command(value.to_owned()),
+            "sql" => {
+                let Some((database, script)) = value.split_once('<') else {
+                    bail!("SQL reset hook {hook} should have the form sql:<DATABASE> < <SCRIPT>");
+                };
+                Self::Sql {
+                    database: database.trim().into(),
+                    script: script.trim().into(),
+                }
+            }
+            _ => bail!("Unknown kind {kind} in reset hook {hook}, expected http, command or sql"),
+        })
+    }
+}
+
+impl TryFrom<String> for ResetHook {
+    type Error = anyhow::Error;
+
+    fn try_from(hook: String) -> Result<Self, Self::Error> {
+        hook.parse()
+    }
+}
+
+impl Display for ResetHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http { method, url } => write!(f, "http:{method} {url}"),
+            Self::Command(command) => write!(f, "command:{command}"),
+            Self::Sql { database, script } => {
+                write!(f, "sql:{} < {}", database.display(), script.display())
+            }
+        }
+    }
+}
+
+impl ResetHook {
+    /// Runs the hook, sending HTTP requests with the given (authenticated) client.
+    fn run(&self, client: &Client, api: &OpenAPI) -> Result<()> {
+        match self {
+            Self::Http { method, url } => {
+                let server_url = api.servers.first().map_or("", |server| server.url.as_str());
+                client
+                    .request(method.clone(), server_relative_url(server_url, url)?)
+                    .send()?
+                    .error_for_status()?;
+            }
+            Self::Command(command) => {
+                let status = shell_command(command).status()?;
+                if !status.success() {
+                    bail!("The command exited with {status}");
+                }
+            }
+            Self::Sql { database, script } => {
+                let script = std::fs::read_to_string(script)
+                    .with_context(|| format!("Could not read {}", script.display()))?;
+                Connection::open(database)?.execute_batch(&script)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs the reset hooks in order, stopping at the first one that fails.
+pub fn run_hooks(hooks: &[ResetHook], client: &Client, api: &OpenAPI) -> Result<()> {
+    for hook in hooks {
+        log::debug!("Running reset hook {hook}");
+        hook.run(client, api)
+            .with_context(|| format!("Reset hook {hook} failed"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use openapiv3::OpenAPI;
+    use reqwest::{blocking::Client, Method};
+    use rusqlite::Connection;
+
+    use super::{run_hooks, ResetHook};
+
+    #[test]
+    fn test_reset_hooks() {
+        assert_eq!(
+            "http:post /test/reset".parse::<ResetHook>().unwrap(),
+            ResetHook::Http {
+                method: Method::POST,
+                url: "/test/reset".to_owned()
+            }
+        );
+        assert!("http:/test/reset".parse::<ResetHook>().is_err());
+        assert!("sql:app.db".parse::<ResetHook>().is_err());
+        assert!("docker:restart".parse::<ResetHook>().is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("app.db");
+        let script = dir.path().join("reset.sql");
+        std::fs::write(
+            &script,
+            "DROP TABLE IF EXISTS items; CREATE TABLE items (name TEXT); INSERT INTO items VALUES ('seed');",
+        )
+        .unwrap();
+        let hook = format!("sql:{} < {}", database.display(), script.display())
+            .parse::<ResetHook>()
+            .unwrap();
+        let connection = Connection::open(&database).unwrap();
+        for _ in 0..2 {
+            run_hooks(
+                std::slice::from_ref(&hook),
+                &Client::new(),
+                &OpenAPI::default(),
+            )
+            .unwrap();
+            let count: i64 = connection
+                .query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+
+        let failing = "command:exit 3".parse::<ResetHook>().unwrap();
+        assert!(run_hooks(&[failing], &Client::new(), &OpenAPI::default()).is_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! Detection of responses that are much larger than usual.
+//!
+//! A small request that makes the target return a huge response points to an amplification
+//! bug, such as a page size or nesting depth that the target does not limit. Each worker keeps
+//! a baseline of the size of the responses to every operation (see [`crate::anomaly`]). A
+//! response is large when it is more than `--response-size-multiple` times the median size for
+//! its operation and more than that many MADs above it. The input is then saved as a crash,
+//! with the sizes of the request and the response, and their ratio, in the metadata of the
+//! crash.
+//!
+//! The size of some responses grows with a numeric parameter, such as the page size of a
+//! paginated list, which spreads the sizes of the operation. So the tracker also keeps the
+//! recent sizes against the value of every numeric parameter of an operation. When the size
+//! rises with the value of a parameter (their rank correlation is at least 0.9), a response to
+//! a value larger than all recent ones only has to be more than `--response-size-multiple`
+//! times the median size to be large, and the metadata of the crash names the parameter, whose
+//! limit is likely missing.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    num::NonZeroU32,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    anomaly::{AnomalyFeedback, OperationBaselines},
+    input::{parameter::SimpleValue, Method, OpenApiRequest, ParameterContents},
+};
+
+/// Number of recent (value, size) samples kept for each numeric parameter of an operation
+const WINDOW: usize = 100;
+/// Number of samples of a parameter needed before the size is compared to its value
+const MIN_SAMPLES: usize = 10;
+/// Number of different values of a parameter needed before the size is compared to its value
+const MIN_DISTINCT_VALUES: usize = 3;
+/// Rank correlation between the value of a parameter and the response size from which the
+/// size grows with the parameter
+const MIN_CORRELATION: f64 = 0.9;
+
+/// Metadata of a crash caused by a large response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LargeResponseMetadata {
+    /// Index of the request in the input
+    pub request_index: usize,
+    /// Operation of the request
+    pub method: Method,
+    pub path: String,
+    /// Size of the request line, headers and body
+    pub request_bytes: u64,
+    /// Size of the (decompressed) response body
+    pub response_bytes: u64,
+    /// Number of response bytes per request byte
+    pub amplification: f64,
+    /// Median response size of the operation before this response
+    pub median_bytes: u64,
+    /// Median absolute deviation of the response size of the operation
+    pub mad_bytes: u64,
+    /// Numeric parameter the response size grows with, if its value was larger than before
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub growing_parameter: Option<String>,
+}
+
+libafl_bolts::impl_serdeany!(LargeResponseMetadata);
+
+/// The response size baselines of all operations.
+pub struct ResponseSizeTracker {
+    multiple: Option<NonZeroU32>,
+    baselines: OperationBaselines,
+    parameter_sizes: ParameterSizes,
+}
+
+impl ResponseSizeTracker {
+    pub fn new(multiple: Option<NonZeroU32>) -> Self {
+        Self {
+            multiple,
+            baselines: OperationBaselines::default(),
+            parameter_sizes: ParameterSizes::default(),
+        }
+    }
+
+    /// The response size baselines, to save them with the campaign state.
+    pub fn baselines(&self) -> &OperationBaselines {
+        &self.baselines
+    }
+
+    /// Continues with the baselines of a saved campaign.
+    pub fn restore_baselines(&mut self, baselines: OperationBaselines) {
+        self.baselines = baselines;
+    }
+
+    /// The recent response sizes against the numeric parameters, to save them with the
+    /// campaign state.
+    pub fn parameter_sizes(&self) -> &ParameterSizes {
+        &self.parameter_sizes
+    }
+
+    /// Continues with the response sizes against the numeric parameters of a saved campaign.
+    pub fn restore_parameter_sizes(&mut self, parameter_sizes: ParameterSizes) {
+        self.parameter_sizes = parameter_sizes;
+    }
+
+    /// Adds the size of a response to the baseline of its operation, and to the sizes against
+    /// the values of its numeric `parameters`. Returns the details of the response if it was
+    /// large compared to the baseline before it, or if it grew with a parameter.
+    pub fn observe(
+        &mut self,
+        request_index: usize,
+        method: Method,
+        path: &str,
+        parameters: &[(String, f64)],
+        request_bytes: u64,
+        response_bytes: u64,
+    ) -> Option<LargeResponseMetadata> {
+        let multiple = self.multiple?;
+        let baseline = self.baselines.observe(method, path, response_bytes);
+        let growing_parameter =
+            self.parameter_sizes
+                .observe(method, path, parameters, response_bytes);
+        let baseline = baseline?;
+        let grown = growing_parameter.is_some()
+            && response_bytes > baseline.median.saturating_mul(u64::from(multiple.get()));
+        (grown || baseline.is_exceeded(response_bytes, multiple)).then(|| LargeResponseMetadata {
+            request_index,
+            method,
+            path: path.to_owned(),
+            request_bytes,
+            response_bytes,
+            amplification: response_bytes as f64 / request_bytes.max(1) as f64,
+            median_bytes: baseline.median,
+            mad_bytes: baseline.mad,
+            growing_parameter: growing_parameter.filter(|_| grown),
+        })
+    }
+}
+
+/// Recent response sizes against the values of the numeric parameters of each operation.
+/// They are saved with the campaign state, like the baselines.
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(from = "Vec<ParameterSamples>", into = "Vec<ParameterSamples>")]
+pub struct ParameterSizes {
+    /// Recent (value, size) samples of each parameter of each operation, oldest first
+    samples: HashMap<(Method, String, String), VecDeque<(f64, u64)>>,
+}
+
+/// Recent samples of a single parameter, since JSON maps can not have the parameter as their
+/// key
+#[derive(Clone, Serialize, Deserialize)]
+struct ParameterSamples {
+    method: Method,
+    path: String,
+    parameter: String,
+    samples: VecDeque<(f64, u64)>,
+}
+
+impl From<ParameterSizes> for Vec<ParameterSamples> {
+    fn from(sizes: ParameterSizes) -> Self {
+        sizes
+            .samples
+            .into_iter()
+            .map(|((method, path, parameter), samples)| ParameterSamples {
+                method,
+                path,
+                parameter,
+                samples,
+            })
+            .collect()
+    }
+}
+
+impl From<Vec<ParameterSamples>> for ParameterSizes {
+    fn from(parameters: Vec<ParameterSamples>) -> Self {
+        Self {
+            samples: parameters
+                .into_iter()
+                .map(|p| ((p.method, p.path, p.parameter), p.samples))
+                .collect(),
+        }
+    }
+}
+
+impl ParameterSizes {
+    /// Adds the size of a response to the samples of the numeric parameters of its request.
+    /// Returns the first parameter the size grew with before this response, if the value of
+    /// the parameter is larger than all its recent values.
+    fn observe(
+        &mut self,
+        method: Method,
+        path: &str,
+        parameters: &[(String, f64)],
+        response_bytes: u64,
+    ) -> Option<String> {
+        let mut growing = None;
+        for (name, value) in parameters {
+            let samples = self
+                .samples
+                .entry((method, path.to_owned(), name.clone()))
+                .or_default();
+            if growing.is_none()
+                && samples.len() >= MIN_SAMPLES
+                && samples.iter().all(|(v, _)| v < value)
+                && grows(samples)
+            {
+                growing = Some(name.clone());
+            }
+            if samples.len() == WINDOW {
+                samples.pop_front();
+            }
+            samples.push_back((*value, response_bytes));
+        }
+        growing
+    }
+}
+
+/// Whether the size rises with the value in the samples: they have enough different values,
+/// and the Spearman rank correlation between the values and the sizes is strong.
+fn grows(samples: &VecDeque<(f64, u64)>) -> bool {
+    let values = ranks(samples.iter().map(|(value, _)| *value).collect());
+    let mut distinct = samples.iter().map(|(value, _)| *value).collect::<Vec<_>>();
+    distinct.sort_by(f64::total_cmp);
+    distinct.dedup();
+    if distinct.len() < MIN_DISTINCT_VALUES {
+        return false;
+    }
+    let sizes = ranks(samples.iter().map(|(_, size)| *size as f64).collect());
+    correlation(&values, &sizes) >= MIN_CORRELATION
+}
+
+/// The rank of every element, where equal elements get the average of their ranks.
+fn ranks(values: Vec<f64>) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end - 1) as f64 / 2.0;
+        for &index in &order[start..end] {
+            ranks[index] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// The Pearson correlation of two series, or 0 if one of them is constant.
+fn correlation(xs: &[f64], ys: &[f64]) -> f64 {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.iter().zip(ys) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return 0.0;
+    }
+    covariance / (variance_x * variance_y).sqrt()
+}
+
+/// The numeric parameters of a request and their values. Numbers in strings count as well,
+/// since query and path parameters are often strings.
+pub fn numeric_parameters(request: &OpenApiRequest) -> Vec<(String, f64)> {
+    request
+        .parameters
+        .iter()
+        .filter_map(|((name, _), contents)| {
+            let value = match contents {
+                ParameterContents::LeafValue(SimpleValue::Number(number)) => number.as_f64()?,
+                ParameterContents::LeafValue(SimpleValue::String(string)) => string.parse().ok()?,
+                _ => return None,
+            };
+            value.is_finite().then(|| (name.clone(), value))
+        })
+        .collect()
+}
+
+/// The size of a request as sent: its request line, headers and body.
+pub fn request_size(request: &reqwest::Request) -> u64 {
+    let line = request.method().as_str().len() + request.url().as_str().len();
+    let headers: usize = request
+        .headers()
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len())
+        .sum();
+    let body = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .map_or(0, <[u8]>::len);
+    (line + headers + body) as u64
+}
+
+/// Objective for inputs with a large response.
+pub fn large_response_objective() -> AnomalyFeedback<LargeResponseMetadata> {
+    AnomalyFeedback::new("LargeResponseFeedback")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::ResponseSizeTracker;
+    use crate::input::Method;
+
+    #[test]
+    fn test_large_responses() {
+        let mut tracker = ResponseSizeTracker::new(NonZeroU32::new(10));
+        let mut observe =
+            |response_bytes| tracker.observe(1, Method::Get, "/items", &[], 50, response_bytes);
+        for bytes in [2000, 2100, 1900, 2000, 2050, 1950, 2000, 2200, 1800, 2000] {
+            assert!(observe(bytes).is_none());
+        }
+        assert!(observe(15_000).is_none());
+        let large = observe(1_000_000).unwrap();
+        assert_eq!(large.request_index, 1);
+        assert_eq!(large.median_bytes, 2000);
+        assert_eq!(large.amplification, 20_000.0);
+
+        let mut disabled = ResponseSizeTracker::new(None);
+        for _ in 0..20 {
+            assert!(disabled
+                .observe(0, Method::Get, "/items", &[], 50, 1_000_000)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn test_growing_responses() {
+        let mut tracker = ResponseSizeTracker::new(NonZeroU32::new(10));
+        let mut observe = |limit: f64| {
+            let parameters = [("limit".to_owned(), limit), ("page".to_owned(), 1.0)];
+            let response_bytes = 100 + 50 * limit as u64;
+            tracker.observe(0, Method::Get, "/items", &parameters, 50, response_bytes)
+        };
+        for limit in [
+            1.0, 5.0, 10.0, 20.0, 50.0, 2.0, 8.0, 30.0, 100.0, 40.0, 60.0,
+        ] {
+            assert!(observe(limit).is_none());
+        }
+        // Not larger than the recent values of the parameter
+        assert!(observe(99.0).is_none());
+        let large = observe(100_000.0).unwrap();
+        assert_eq!(large.growing_parameter.as_deref(), Some("limit"));
+        assert_eq!(large.median_bytes, 1600);
+
+        // The size does not grow with the parameter
+        let mut tracker = ResponseSizeTracker::new(NonZeroU32::new(10));
+        for (index, limit) in [
+            1.0, 5.0, 10.0, 20.0, 50.0, 2.0, 8.0, 30.0, 100.0, 40.0, 60.0,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let parameters = [("limit".to_owned(), limit)];
+            let response_bytes = 1000 + 1000 * (index as u64 % 3);
+            assert!(tracker
+                .observe(0, Method::Get, "/items", &parameters, 50, response_bytes)
+                .is_none());
+        }
+        let parameters = [("limit".to_owned(), 100_000.0)];
+        let large = tracker
+            .observe(0, Method::Get, "/items", &parameters, 50, 25_000)
+            .unwrap();
+        assert_eq!(large.growing_parameter, None);
+    }
+}
@@ -0,0 +1,451 @@
+//! Saving and restoring a fuzzing campaign, so that it can be continued with `--resume`
+//! after it was stopped (e.g. by a ctrl-c or by a CI timeout).
+//!
+//! The resume directory contains the serialized fuzzer state (corpus, solutions, scheduler
+//! and feedback metadata, execution count), the cumulative coverage of both the endpoint
+//! coverage client and the code coverage client, the latency and response size baselines,
+//! and the crash buckets, so buckets that are full stay full.
+//!
+//! A restored state is relocated to the run that resumes it: it uses the specification
+//! of that run (with its target URL and operation filters), and its corpus and solutions are
+//! copied to the directories of that run, so new entries and findings are written there.
+
+use std::{
+    fs::{create_dir_all, File},
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use libafl::{
+    corpus::{Corpus, InMemoryOnDiskCorpus, OnDiskCorpus},
+    state::{HasCorpus, HasSolutions, Stoppable},
+};
+use openapiv3::OpenAPI;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+use crate::{
+    anomaly::OperationBaselines,
+    coverage_clients::CoverageClient,
+    crash_buckets::{CrashBuckets, BUCKETS_FILE},
+    executor::FuzzerState,
+    input::OpenApiInput,
+    latency::LatencyTracker,
+    response_size::{ParameterSizes, ResponseSizeTracker},
+};
+
+const STATE_FILE: &str = "state.yaml";
+const ENDPOINT_COVERAGE_FILE: &str = "endpoint_coverage.json";
+const CODE_COVERAGE_FILE: &str = "code_coverage.json";
+const BASELINES_FILE: &str = "baselines.json";
+
+/// The latency and response size baselines of a worker
+#[derive(Serialize, Deserialize)]
+struct SavedBaselines {
+    latency: OperationBaselines,
+    response_size: OperationBaselines,
+    #[serde(default)]
+    parameter_sizes: ParameterSizes,
+}
+
+/// Whether a fuzzer state was saved in `dir`.
+pub fn has_state(dir: &Path) -> bool {
+    dir.join(STATE_FILE).exists()
+}
+
+/// Loads the fuzzer state saved in `dir`. Returns `None` if nothing was saved there yet,
+/// in which case the campaign starts from scratch.
+pub fn load_state(dir: &Path) -> Result<Option<FuzzerState>> {
+    let Some(mut state) = read_file::<FuzzerState>(&dir.join(STATE_FILE), Format::Yaml)? else {
+        return Ok(None);
+    };
+    // The state was saved after a stop was requested
+    state.discard_stop_request();
+    Ok(Some(state))
+}
+
+/// Relocates a restored state to the current run: replaces its specification by `api`, and
+/// copies its corpus and solutions to the `queue` and `crashes` directories of the run if
+/// they were kept elsewhere.
+pub fn relocate_state(
+    state: &mut FuzzerState,
+    api: OpenAPI,
+    queue: &Path,
+    crashes: &Path,
+) -> Result<()> {
+    state.set_api(api);
+    if state.corpus().dir_path() != queue {
+        let corpus = copy_testcases(state.corpus(), InMemoryOnDiskCorpus::new(queue)?)?;
+        *state.corpus_mut() = corpus;
+    }
+    if state.solutions().dir_path() != crashes {
+        let solutions = copy_testcases(state.solutions(), OnDiskCorpus::new(crashes)?)?;
+        *state.solutions_mut() = solutions;
+    }
+    Ok(())
+}
+
+/// Adds all testcases of `from` to the empty corpus `to`, with their metadata, enabled
+/// and disabled ones alike. They are added in the order of their ids, so they keep their
+/// ids, which the scheduler metadata refers to. Fails if `from` has gaps between its ids,
+/// which only removing testcases leaves behind.
+fn copy_testcases<C: Corpus<OpenApiInput>>(
+    from: &impl Corpus<OpenApiInput>,
+    mut to: C,
+) -> Result<C> {
+    let mut ids = (0..from.count_all())
+        .map(|nth| from.nth_from_all(nth))
+        .collect::<Vec<_>>();
+    ids.sort();
+    for id in ids {
+        let mut testcase = from.get_from_all(id)?.borrow().clone();
+        from.load_input_into(&mut testcase)
+            .with_context(|| format!("Could not load testcase {id} of the saved state"))?;
+        *testcase.file_path_mut() = None;
+        *testcase.metadata_path_mut() = None;
+        let copied = if from.get(id).is_ok() {
+            to.add(testcase)?
+        } else {
+            to.add_disabled(testcase)?
+        };
+        ensure!(
+            copied == id,
+            "Testcase {id} of the saved state would get id {copied}"
+        );
+    }
+    Ok(to)
+}
+
+/// Restores the cumulative coverage of the coverage clients from `dir`, if it was saved.
+pub fn restore_coverage(
+    dir: &Path,
+    endpoint_client: &mut dyn CoverageClient,
+    code_client: &mut dyn CoverageClient,
+) -> Result<()> {
+    if let Some(saved) = read_file(&dir.join(ENDPOINT_COVERAGE_FILE), Format::Json)? {
+        endpoint_client
+            .restore_state(saved)
+            .context("Could not restore endpoint coverage")?;
+    }
+    if let Some(saved) = read_file(&dir.join(CODE_COVERAGE_FILE), Format::Json)? {
+        code_client
+            .restore_state(saved)
+            .context("Could not restore code coverage")?;
+    }
+    Ok(())
+}
+
+/// Restores the latency and response size baselines from `dir`, if they were saved.
+pub fn restore_baselines(
+    dir: &Path,
+    latency: &mut LatencyTracker,
+    response_size: &mut ResponseSizeTracker,
+) -> Result<()> {
+    if let Some(saved) = read_file::<SavedBaselines>(&dir.join(BASELINES_FILE), Format::Json)? {
+        latency.restore_baselines(saved.latency);
+        response_size.restore_baselines(saved.response_size);
+        response_size.restore_parameter_sizes(saved.parameter_sizes);
+    }
+    Ok(())
+}
+
+/// Restores the crash buckets from `dir`, if they were saved.
+pub fn restore_crash_buckets(dir: &Path, buckets: &mut CrashBuckets) -> Result<()> {
+    if let Some(saved) = read_file(&dir.join(BUCKETS_FILE), Format::Json)? {
+        buckets.restore(saved);
+    }
+    Ok(())
+}
+
+/// Saves the fuzzer state to `dir`.
+pub fn save_state(dir: &Path, state: &FuzzerState) -> Result<()> {
+    write_file(dir, STATE_FILE, Format::Yaml, state)
+}
+
+/// Saves the cumulative coverage of the coverage clients to `dir`.
+pub fn save_coverage(
+    dir: &Path,
+    endpoint_client: &dyn CoverageClient,
+    code_client: &dyn CoverageClient,
+) -> Result<()> {
+    write_file(
+        dir,
+        ENDPOINT_COVERAGE_FILE,
+        Format::Json,
+        &endpoint_client.save_state()?,
+    )?;
+    write_file(
+        dir,
+        CODE_COVERAGE_FILE,
+        Format::Json,
+        &code_client.save_state()?,
+    )
+}
+
+/// Saves the latency and response size baselines to `dir`.
+pub fn save_baselines(
+    dir: &Path,
+    latency: &LatencyTracker,
+    response_size: &ResponseSizeTracker,
+) -> Result<()> {
+    let saved = SavedBaselines {
+        latency: latency.baselines().clone(),
+        response_size: response_size.baselines().clone(),
+        parameter_sizes: response_size.parameter_sizes().clone(),
+    };
+    write_file(dir, BASELINES_FILE, Format::Json, &saved)
+}
+
+/// Saves the crash buckets to `dir`.
+pub fn save_crash_buckets(dir: &Path, buckets: &CrashBuckets) -> Result<()> {
+    write_file(dir, BUCKETS_FILE, Format::Json, &buckets.buckets())
+}
+
+/// Serialization format of a file in the resume directory
+#[derive(Clone, Copy)]
+enum Format {
+    /// Used for the fuzzer state, since the inputs in its corpus contain maps with
+    /// structured keys, which JSON can not represent
+    Yaml,
+    /// Used for the (large, flat) coverage maps, the baselines and the crash buckets
+    Json,
+}
+
+fn read_file<T: DeserializeOwned>(path: &Path, format: Format) -> Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file = File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+    let reader = BufReader::new(file);
+    let value = match format {
+        Format::Yaml => serde_yaml::from_reader(reader).map_err(anyhow::Error::from),
+        Format::Json => serde_json::from_reader(reader).map_err(anyhow::Error::from),
+    }
+    .with_context(|| format!("Could not parse {}", path.display()))?;
+    Ok(Some(value))
+}
+
+/// Writes `value` to `dir/filename` through a temporary file, so an interrupted save
+/// never leaves a half-written file behind.
+fn write_file<T: Serialize + ?Sized>(
+    dir: &Path,
+    filename: &str,
+    format: Format,
+    value: &T,
+) -> Result<()> {
+    create_dir_all(dir)
+        .with_context(|| format!("Could not create resume directory {}", dir.display()))?;
+    let file = NamedTempFile::new_in(dir)?;
+    let mut writer = BufWriter::new(file);
+    match format {
+        Format::Yaml => serde_yaml::to_writer(&mut writer, value).map_err(anyhow::Error::from),
+        Format::Json => serde_json::to_writer(&mut writer, value).map_err(anyhow::Error::from),
+    }
+    .with_context(|| format!("Could not serialize {filename}"))?;
+    writer.flush()?;
+    writer.into_inner()?.persist(dir.join(filename))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        num::{NonZeroU32, NonZeroUsize},
+        time::Duration,
+    };
+
+    use libafl::{
+        corpus::{Corpus, InMemoryOnDiskCorpus, OnDiskCorpus, Testcase},
+        feedbacks::{CrashFeedback, MaxMapFeedback},
+        observers::StdMapObserver,
+        state::{HasCorpus, HasExecutions, HasSolutions},
+    };
+    use libafl_bolts::rands::StdRand;
+
+    use super::{
+        load_state, relocate_state, restore_baselines, restore_crash_buckets, save_baselines,
+        save_crash_buckets, save_state,
+    };
+    use crate::{
+        crash_buckets::{CrashBuckets, CrashKind, CrashSignature, BUCKETS_FILE},
+        executor::FuzzerState,
+        input::{Method, OpenApiInput},
+        latency::LatencyTracker,
+        response_size::ResponseSizeTracker,
+        state::OpenApiFuzzerState,
+    };
+
+    #[test]
+    fn test_state_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut map = [0u8; 16];
+        let observer = unsafe { StdMapObserver::from_mut_ptr("map", map.as_mut_ptr(), map.len()) };
+        let mut feedback = MaxMapFeedback::new(&observer);
+        let mut objective = CrashFeedback::new();
+        let mut state: FuzzerState = OpenApiFuzzerState::new(
+            StdRand::with_seed(0),
+            InMemoryOnDiskCorpus::new(dir.path().join("queue")).unwrap(),
+            OnDiskCorpus::new(dir.path().join("crashes")).unwrap(),
+            &mut feedback,
+            &mut objective,
+            openapiv3::OpenAPI::default(),
+        )
+        .unwrap();
+        // Parameters are keyed by (name, kind), which JSON could not represent
+        let input: OpenApiInput = serde_yaml::from_str(
+            "
+- method: DELETE
+  path: /items/{id}
+  parameters:
+    ? - id
+      - Path
+    : DataType: RawBytes
+      Contents: IiMiIiIi6uoiIiEiIgX//wU=
+",
+        )
+        .unwrap();
+        let id = state.corpus_mut().add(Testcase::new(input)).unwrap();
+        // Inputs are written to disk when added, and kept in memory once loaded again
+        let mut testcase = state.corpus().get(id).unwrap().borrow_mut();
+        state.corpus().load_input_into(&mut testcase).unwrap();
+        drop(testcase);
+        *state.executions_mut() = 42;
+
+        let resume_dir = dir.path().join("resume");
+        assert!(load_state(&resume_dir).unwrap().is_none());
+        save_state(&resume_dir, &state).unwrap();
+        let restored = load_state(&resume_dir).unwrap().unwrap();
+        assert_eq!(*restored.executions(), 42);
+        assert_eq!(restored.corpus().count(), 1);
+    }
+
+    #[test]
+    fn test_relocate_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let run = |name: &str| {
+            (
+                dir.path().join(name).join("queue"),
+                dir.path().join(name).join("crashes"),
+            )
+        };
+        let api = |title: &str| {
+            let mut api = openapiv3::OpenAPI::default();
+            api.info.title = title.to_owned();
+            api
+        };
+        let (old_queue, old_crashes) = run("old");
+        let mut map = [0u8; 16];
+        let observer = unsafe { StdMapObserver::from_mut_ptr("map", map.as_mut_ptr(), map.len()) };
+        let mut state: FuzzerState = OpenApiFuzzerState::new(
+            StdRand::with_seed(0),
+            InMemoryOnDiskCorpus::new(&old_queue).unwrap(),
+            OnDiskCorpus::new(&old_crashes).unwrap(),
+            &mut MaxMapFeedback::new(&observer),
+            &mut CrashFeedback::new(),
+            api("old spec"),
+        )
+        .unwrap();
+        let input = |path: &str| -> OpenApiInput {
+            serde_yaml::from_str(&format!("- method: GET\n  path: {path}\n")).unwrap()
+        };
+        state.corpus_mut().add(Testcase::new(input("/a"))).unwrap();
+        state
+            .corpus_mut()
+            .add_disabled(Testcase::new(input("/disabled")))
+            .unwrap();
+        let b = state.corpus_mut().add(Testcase::new(input("/b"))).unwrap();
+        state
+            .solutions_mut()
+            .add(Testcase::new(input("/crash")))
+            .unwrap();
+        let resume_dir = dir.path().join("resume");
+        save_state(&resume_dir, &state).unwrap();
+
+        // A resumed run uses its own specification and directories
+        let (queue, crashes) = run("new");
+        let mut restored = load_state(&resume_dir).unwrap().unwrap();
+        relocate_state(&mut restored, api("new spec"), &queue, &crashes).unwrap();
+        assert_eq!(restored.api().info.title, "new spec");
+        assert_eq!(restored.corpus().dir_path(), &queue);
+        assert_eq!(restored.solutions().dir_path(), &crashes);
+        let files = |dir: &std::path::Path| {
+            std::fs::read_dir(dir)
+                .unwrap()
+                .filter(|entry| {
+                    !entry
+                        .as_ref()
+                        .unwrap()
+                        .file_name()
+                        .to_string_lossy()
+                        .starts_with('.')
+                })
+                .count()
+        };
+        assert_eq!(files(&queue), 3);
+        assert_eq!(files(&crashes), 1);
+        // .. with the same entries, in the same order
+        let first = restored.corpus().first().unwrap();
+        let first = restored.corpus().cloned_input_for_id(first).unwrap();
+        assert_eq!(first.0[0].path, "/a");
+        // .. and the same ids, also after a disabled entry
+        let b = restored.corpus().cloned_input_for_id(b).unwrap();
+        assert_eq!(b.0[0].path, "/b");
+        assert_eq!(restored.corpus().count_disabled(), 1);
+
+        // New findings are written to the directories of the resumed run
+        restored
+            .solutions_mut()
+            .add(Testcase::new(input("/new")))
+            .unwrap();
+        assert_eq!(files(&crashes), 2);
+        assert_eq!(files(&old_crashes), 1);
+    }
+
+    #[test]
+    fn test_baselines_and_buckets_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let resume_dir = dir.path().join("resume");
+        let multiple = NonZeroU32::new(10);
+        let mut latency = LatencyTracker::new(multiple, None);
+        let mut response_size = ResponseSizeTracker::new(multiple);
+        for limit in 0..10 {
+            latency.observe(0, Method::Get, "/a", Duration::from_millis(10));
+            let parameters = [("limit".to_owned(), f64::from(limit))];
+            response_size.observe(0, Method::Get, "/a", &parameters, 100, 100 + limit as u64);
+        }
+        let signature =
+            CrashSignature::new(Method::Get, "/a", Some(500), CrashKind::ServerError, b"");
+        let mut buckets = CrashBuckets::load(
+            &dir.path().join("old").join(BUCKETS_FILE),
+            NonZeroUsize::MIN,
+        )
+        .unwrap();
+        assert!(buckets.record(&signature).1);
+        save_baselines(&resume_dir, &latency, &response_size).unwrap();
+        save_crash_buckets(&resume_dir, &buckets).unwrap();
+
+        // A resumed run compares to the baselines right away
+        let mut latency = LatencyTracker::new(multiple, None);
+        let mut response_size = ResponseSizeTracker::new(multiple);
+        restore_baselines(&resume_dir, &mut latency, &mut response_size).unwrap();
+        assert!(latency
+            .observe(0, Method::Get, "/a", Duration::from_secs(1))
+            .is_some());
+        let parameters = [("limit".to_owned(), 1000.0)];
+        let large = response_size
+            .observe(0, Method::Get, "/a", &parameters, 100, 10_000)
+            .unwrap();
+        assert_eq!(large.growing_parameter.as_deref(), Some("limit"));
+
+        // .. and its full buckets stay full
+        let mut buckets = CrashBuckets::load(
+            &dir.path().join("new").join(BUCKETS_FILE),
+            NonZeroUsize::MIN,
+        )
+        .unwrap();
+        restore_crash_buckets(&resume_dir, &mut buckets).unwrap();
+        assert!(!buckets.record(&signature).1);
+        assert_eq!(buckets.buckets()[0].hits, 2);
+    }
+}
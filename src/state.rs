@@ -361,6 +361,17 @@ where
         objective.init_state(&mut state)?;
         Ok(state)
     }
+
+    /// Returns the api spec that is passed to the mutators.
+    pub fn api(&self) -> &OpenAPI {
+        &self.api
+    }
+
+    /// Replaces the api spec that is passed to the mutators, e.g. when a saved state is
+    /// restored for a run with another specification.
+    pub fn set_api(&mut self, api: OpenAPI) {
+        self.api = api;
+    }
 }
 
 // Necessary because of borrow checking conflicts
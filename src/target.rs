@@ -0,0 +1,307 @@
+//! Lifecycle of the target: health checks, and restarting it when it stops responding.
+//!
+//! The target is down when a request gets no response and its health check fails, or when
+//! several requests in a row get no response. The health check is a GET request to the
+//! configured endpoint, which must not result in a server error, or else a connection to the
+//! target. When the target is down, the worker that noticed first runs the target command
+//! (if any) and waits until the health check passes again, while the other workers wait for
+//! it. Time spent waiting does not count towards the timeout of the campaign.
+
+use std::{
+    net::TcpStream,
+    process::{Child, Command, Stdio},
+    sync::{Condvar, Mutex, MutexGuard, OnceLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use openapiv3::OpenAPI;
+use reqwest::blocking::Client;
+use url::Url;
+
+use crate::{configuration::Configuration, openapi::server::server_relative_url};
+
+/// Time-out of a single health check
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time between two health checks while waiting for the target
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+/// How long to wait for the target to come up before giving up
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+static SHARED: OnceLock<Target> = OnceLock::new();
+
+/// The target of the campaign, shared by all workers.
+pub struct Target {
+    health_check: HealthCheck,
+    command: Option<String>,
+    client: Client,
+    state: Mutex<TargetState>,
+    /// Notified when a worker is done bringing the target back up
+    recovered: Condvar,
+}
+
+enum HealthCheck {
+    /// GET request to a health-check endpoint
+    Get(Url),
+    /// Connection to the host and port of the target
+    Connect(Url),
+}
+
+struct TargetState {
+    /// Number of times the target came back after being down
+    generation: u64,
+    /// The target command, while it is running
+    process: Option<Child>,
+    /// Total time spent waiting for the target to come back
+    downtime: Duration,
+    /// Whether the target did not come back the last time it went down
+    lost: bool,
+    /// Whether a worker is bringing the target back up
+    recovering: bool,
+}
+
+/// Stops the target command when the campaign ends.
+#[must_use]
+pub struct RunningTarget(&'static Target);
+
+impl Drop for RunningTarget {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+impl Target {
+    /// Creates the target served at `server_url`, with the given health-check endpoint and
+    /// target command.
+    fn new(server_url: &str, health_check: Option<&str>, command: Option<String>) -> Result<Self> {
+        let health_check = match health_check {
+            None => HealthCheck::Connect(Url::parse(server_url)?),
+            Some(endpoint) => HealthCheck::Get(
+                server_relative_url(server_url, endpoint).context("Invalid health check")?,
+            ),
+        };
+        Ok(Self {
+            health_check,
+            command,
+            client: Client::builder().timeout(HEALTH_CHECK_TIMEOUT).build()?,
+            state: Mutex::new(TargetState {
+                generation: 0,
+                process: None,
+                downtime: Duration::ZERO,
+                lost: false,
+                recovering: false,
+            }),
+            recovered: Condvar::new(),
+        })
+    }
+
+    /// Sets up the target shared by all workers, starting it with the target command if one
+    /// is configured. The target command is stopped when the returned value is dropped.
+    pub fn start(config: &Configuration, api: &OpenAPI) -> Result<RunningTarget> {
+        let server = api
+            .servers
+            .first()
+            .context("The specification contains no servers")?;
+        let target = Self::new(
+            &server.url,
+            config.health_check.as_deref(),
+            config.target_command.clone(),
+        )?;
+        if target.command.is_some() {
+            info!("Starting the target");
+            target.restart(&mut target.lock().process)?;
+        }
+        if SHARED.set(target).is_err() {
+            bail!("The target was already started");
+        }
+        Ok(RunningTarget(Self::shared()?))
+    }
+
+    /// Returns the target shared by all workers, once it is started.
+    pub fn shared() -> Result<&'static Self> {
+        SHARED.get().context("The target was not started")
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TargetState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Locks the state of the target, after waiting until no worker is bringing it back up.
+    fn lock_when_up(&self) -> MutexGuard<'_, TargetState> {
+        self.recovered
+            .wait_while(self.lock(), |state| state.recovering)
+            .unwrap()
+    }
+
+    /// Returns how often the target came back after being down. Waits while it is down.
+    pub fn generation(&self) -> u64 {
+        self.lock_when_up().generation
+    }
+
+    /// Returns how long the workers have waited for the target so far.
+    pub fn downtime(&self) -> Duration {
+        self.lock().downtime
+    }
+
+    /// Whether the health check of the target passes.
+    pub fn is_healthy(&self) -> bool {
+        match &self.health_check {
+            HealthCheck::Get(url) => self
+                .client
+                .get(url.clone())
+                .send()
+                .is_ok_and(|response| !response.status().is_server_error()),
+            HealthCheck::Connect(url) => url.socket_addrs(|| None).is_ok_and(|addresses| {
+                addresses.iter().any(|address| {
+                    TcpStream::connect_timeout(address, HEALTH_CHECK_TIMEOUT).is_ok()
+                })
+            }),
+        }
+    }
+
+    /// Brings the target back up after it went down in the given generation: restarts it with
+    /// the target command if one is configured, and waits until its health check passes.
+    /// Returns false if another worker already did so. The state is not locked meanwhile, so
+    /// other workers only wait for the target when they need it to be up.
+    pub fn recover(&self, generation: u64) -> Result<bool> {
+        let mut state = self.lock_when_up();
+        if state.lost {
+            bail!("The target did not come back up earlier");
+        }
+        if state.generation != generation {
+            return Ok(false);
+        }
+        state.recovering = true;
+        let mut process = state.process.take();
+        drop(state);
+
+        let down_since = Instant::now();
+        let recovered = if self.command.is_some() {
+            warn!("The target stopped responding, restarting it");
+            self.restart(&mut process)
+        } else {
+            warn!("The target stopped responding, waiting for it to come back");
+            self.wait_until_healthy(&mut process)
+        };
+
+        let mut state = self.lock();
+        state.recovering = false;
+        state.process = process;
+        state.downtime += down_since.elapsed();
+        match &recovered {
+            Ok(()) => {
+                info!("The target is back up after {:?}", down_since.elapsed());
+                state.generation += 1;
+            }
+            Err(_) => state.lost = true,
+        }
+        drop(state);
+        self.recovered.notify_all();
+        recovered.map(|()| true)
+    }
+
+    /// Stops the target command if it is still running, and runs it again.
+    fn restart(&self, process: &mut Option<Child>) -> Result<()> {
+        let command = self
+            .command
+            .as_ref()
+            .expect("A target command is configured");
+        Self::stop_process(process);
+        *process = Some(
+            shell_command(command)
+                .stdin(Stdio::null())
+                .spawn()
+                .with_context(|| format!("Could not run target command {command}"))?,
+        );
+        self.wait_until_healthy(process)
+    }
+
+    /// Polls the health check until it passes. Fails if the target command exits with an
+    /// error first, or if the target does not come up in time.
+    fn wait_until_healthy(&self, process: &mut Option<Child>) -> Result<()> {
+        let started = Instant::now();
+        while !self.is_healthy() {
+            if let Some(running) = process {
+                if let Some(status) = running.try_wait()? {
+                    *process = None;
+                    if !status.success() {
+                        bail!("The target command exited with {status}");
+                    }
+                }
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                bail!("The target did not come up within {STARTUP_TIMEOUT:?}");
+            }
+            std::thread::sleep(HEALTH_CHECK_INTERVAL);
+        }
+        Ok(())
+    }
+
+    /// Stops the target command if it is still running.
+    fn stop(&self) {
+        Self::stop_process(&mut self.lock_when_up().process);
+    }
+
+    fn stop_process(process: &mut Option<Child>) {
+        if let Some(mut process) = process.take() {
+            if let Err(err) = process.kill().and_then(|_| process.wait()) {
+                warn!("Could not stop the target command: {err}");
+            }
+        }
+    }
+}
+
+/// Prepares running a command through the shell of the platform.
+pub fn shell_command(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut shell = Command::new(shell);
+    shell.args([flag, command]);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::TcpListener,
+        time::{Duration, Instant},
+    };
+
+    use super::Target;
+
+    #[test]
+    fn test_health_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let target = Target::new(&format!("http://127.0.0.1:{port}/api"), None, None).unwrap();
+        assert!(target.is_healthy());
+        drop(listener);
+        assert!(!target.is_healthy());
+        assert!(target.recover(1).is_ok_and(|recovered| !recovered));
+    }
+
+    #[test]
+    fn test_recover_without_blocking() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+        let target = Target::new(&format!("http://{address}/api"), None, None).unwrap();
+        std::thread::scope(|scope| {
+            let recovery = scope.spawn(|| target.recover(0));
+            std::thread::sleep(Duration::from_millis(200));
+            // The downtime is available while the target is down
+            let asked = Instant::now();
+            assert_eq!(target.downtime(), Duration::ZERO);
+            assert!(asked.elapsed() < Duration::from_millis(100));
+            let _listener = TcpListener::bind(address).unwrap();
+            // .. but the generation only once the target is back up
+            assert_eq!(target.generation(), 1);
+            assert!(recovery.join().unwrap().unwrap());
+            assert!(target.downtime() > Duration::ZERO);
+        });
+    }
+}
@@ -0,0 +1,176 @@
+//! Transcripts of the requests and responses of the inputs that are saved as findings.
+//!
+//! A saved input only holds the requests before their backreferences were resolved, and the
+//! target may have changed by the time a finding is reproduced. So every crash, timeout and
+//! reset is saved with a transcript of what happened: each request as it was sent (with the
+//! backreferences filled in, and as a curl command), its response (status, headers and the
+//! start of the body) or transport error, its latency, the validation error if there was one,
+//! and the seed of the run. The transcript is part of the metadata of the finding, which LibAFL
+//! writes to `.<name>.metadata` next to the input.
+
+use std::borrow::Cow;
+
+use libafl::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    Error, HasMetadata,
+};
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{input::OpenApiRequest, openapi::validate_response::Response};
+
+/// Number of bytes of a response body that are kept in a transcript
+const MAX_BODY_LENGTH: usize = 4096;
+
+/// What happened when the requests of an input were sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptMetadata {
+    /// Seed of the run that found the input
+    pub seed: u64,
+    pub requests: Vec<TranscriptEntry>,
+}
+
+libafl_bolts::impl_serdeany!(TranscriptMetadata);
+
+/// A request of an input, and what came back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    /// Index of the request in the input
+    pub request_index: usize,
+    /// The request with its backreferences resolved, as YAML in the format of the inputs
+    pub request: String,
+    /// The request as a curl command
+    pub curl: String,
+    pub response: Option<TranscriptResponse>,
+    /// Why the request got no response, if it did not
+    pub error: Option<String>,
+    /// Why the response does not match the specification, if it was validated and does not
+    pub validation_error: Option<String>,
+    /// Time until the response was received, in milliseconds
+    pub latency_ms: Option<f64>,
+}
+
+impl TranscriptEntry {
+    pub fn new(request_index: usize, request: &OpenApiRequest, curl: &str) -> Self {
+        Self {
+            request_index,
+            request: serde_yaml::to_string(request)
+                .unwrap_or_else(|err| format!("Could not serialize the request: {err}")),
+            curl: curl.to_owned(),
+            response: None,
+            error: None,
+            validation_error: None,
+            latency_ms: None,
+        }
+    }
+}
+
+/// A response in a transcript, with the start of its body.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// The start of the body, decoded as UTF-8 with invalid characters replaced
+    pub body: String,
+    /// Length of the whole body in bytes
+    pub body_length: u64,
+}
+
+impl From<&Response> for TranscriptResponse {
+    fn from(response: &Response) -> Self {
+        let body = response.body();
+        Self {
+            status: response.status().as_u16(),
+            headers: response.headers().to_vec(),
+            body: String::from_utf8_lossy(&body[..body.len().min(MAX_BODY_LENGTH)]).into_owned(),
+            body_length: response.content_length(),
+        }
+    }
+}
+
+/// Objective that attaches the transcript of the last input, which the executor left in the
+/// state, to the finding. It comes last in the objective, and never decides whether an input
+/// is a finding.
+pub struct TranscriptFeedback;
+
+impl Named for TranscriptFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("TranscriptFeedback");
+        &NAME
+    }
+}
+
+impl<S> StateInitializer<S> for TranscriptFeedback {}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for TranscriptFeedback
+where
+    S: HasMetadata,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        if let Some(transcript) = state.metadata_map_mut().remove::<TranscriptMetadata>() {
+            testcase.add_metadata(*transcript);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::{TranscriptEntry, TranscriptMetadata};
+    use crate::input::{
+        media_type::MediaTypeHeaders,
+        parameter::{ParameterKind, SimpleValue},
+        Body, Method, OpenApiRequest, ParameterContents,
+    };
+
+    #[test]
+    fn test_transcript_is_json() {
+        let request = OpenApiRequest {
+            method: Method::Get,
+            path: "/items/{id}".to_owned(),
+            body: Body::Empty,
+            parameters: IndexMap::from([(
+                ("id".to_owned(), ParameterKind::Path),
+                ParameterContents::LeafValue(SimpleValue::String("42".to_owned())),
+            )]),
+            media_type_headers: MediaTypeHeaders::default(),
+        };
+        let transcript = TranscriptMetadata {
+            seed: 7,
+            requests: vec![TranscriptEntry::new(
+                0,
+                &request,
+                "curl http://localhost/items/42",
+            )],
+        };
+        // Metadata is saved as JSON, which has no tuple keys like those of the parameters
+        let json = serde_json::to_string(&transcript).unwrap();
+        let entry = &serde_json::from_str::<TranscriptMetadata>(&json)
+            .unwrap()
+            .requests[0];
+        let resolved: OpenApiRequest = serde_yaml::from_str(&entry.request).unwrap();
+        assert_eq!(resolved.path, "/items/{id}");
+        assert_eq!(resolved.parameters.len(), 1);
+    }
+}
@@ -0,0 +1,161 @@
+//! Classification of the errors of requests that got no response.
+//!
+//! A request that times out may have hit an endpoint that hangs, which is often a denial of
+//! service bug, and a connection that the target drops without responding may point to a
+//! crashed handler. Both can be saved as findings (see `--transport-objectives`), which are
+//! deduplicated by the crash buckets like crashes. Refused connections and TLS errors rather
+//! point to a target that is down or misconfigured.
+
+use std::{error::Error, fmt::Display, io::ErrorKind};
+
+use crate::{configuration::TransportObjective, crash_buckets::CrashKind};
+
+/// The reason that a request got no response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportFailure {
+    /// No response within the request time-out
+    Timeout,
+    /// The target reset or closed the connection before responding
+    Reset,
+    /// The target did not accept the connection
+    Refused,
+    /// The TLS handshake or connection failed
+    Tls,
+    /// Any other error, such as a failed DNS lookup or a response that is not valid HTTP
+    Other,
+}
+
+impl TransportFailure {
+    /// Determines why a request got no response.
+    pub fn classify(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return Self::Timeout;
+        }
+        let mut source = error.source();
+        while let Some(cause) = source {
+            if cause.is::<openssl::error::ErrorStack>() || cause.is::<openssl::ssl::Error>() {
+                return Self::Tls;
+            }
+            if let Some(io_error) = cause.downcast_ref::<std::io::Error>() {
+                match io_error.kind() {
+                    ErrorKind::TimedOut => return Self::Timeout,
+                    ErrorKind::ConnectionRefused => return Self::Refused,
+                    ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::UnexpectedEof => return Self::Reset,
+                    _ => (),
+                }
+            }
+            if cause
+                .downcast_ref::<hyper::Error>()
+                .is_some_and(hyper::Error::is_incomplete_message)
+            {
+                // The connection was closed before a response was received
+                return Self::Reset;
+            }
+            source = cause.source();
+        }
+        Self::Other
+    }
+
+    /// The objective under which this failure is saved, if any.
+    pub fn objective(self) -> Option<TransportObjective> {
+        match self {
+            Self::Timeout => Some(TransportObjective::Timeout),
+            Self::Reset => Some(TransportObjective::Reset),
+            Self::Refused | Self::Tls | Self::Other => None,
+        }
+    }
+
+    /// The kind of crash bucket for this failure, if it is saved as a finding.
+    pub fn crash_kind(self) -> Option<CrashKind> {
+        match self.objective()? {
+            TransportObjective::Timeout => Some(CrashKind::Timeout),
+            TransportObjective::Reset => Some(CrashKind::ConnectionReset),
+        }
+    }
+}
+
+impl Display for TransportFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Timeout => "Request timed out",
+            Self::Reset => "Connection reset",
+            Self::Refused => "Connection refused",
+            Self::Tls => "TLS error",
+            Self::Other => "Transport error",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, net::TcpListener, time::Duration};
+
+    use reqwest::blocking::Client;
+
+    use super::TransportFailure;
+
+    /// Sends a request to a local listener that handles connections with `handle`.
+    fn failure(
+        scheme: &str,
+        handle: impl Fn(std::net::TcpStream) + Send + 'static,
+    ) -> TransportFailure {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                handle(stream.unwrap());
+            }
+        });
+        let client = Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let error = client
+            .get(format!("{scheme}://127.0.0.1:{port}/"))
+            .send()
+            .unwrap_err();
+        TransportFailure::classify(&error)
+    }
+
+    #[test]
+    fn test_classify() {
+        let closed = |mut stream: std::net::TcpStream| {
+            let _ = stream.read(&mut [0; 1024]);
+        };
+        assert_eq!(failure("http", closed), TransportFailure::Reset);
+        let hanging = |stream| {
+            std::thread::sleep(Duration::from_secs(1));
+            drop(stream);
+        };
+        assert_eq!(failure("http", hanging), TransportFailure::Timeout);
+        let not_http = |mut stream: std::net::TcpStream| {
+            let _ = stream.read(&mut [0; 1024]);
+            let _ = std::io::Write::write_all(&mut stream, b"not HTTP\r\n\r\n");
+        };
+        assert_eq!(failure("http", not_http), TransportFailure::Other);
+        #[cfg(target_os = "linux")]
+        {
+            use std::io::Write;
+            let plain_http = |mut stream: std::net::TcpStream| {
+                let _ = stream.read(&mut [0; 1024]);
+                let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+            };
+            assert_eq!(failure("https", plain_http), TransportFailure::Tls);
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        let error = Client::new()
+            .get(format!("http://127.0.0.1:{port}/"))
+            .send()
+            .unwrap_err();
+        assert_eq!(
+            TransportFailure::classify(&error),
+            TransportFailure::Refused
+        );
+    }
+}
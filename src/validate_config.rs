@@ -0,0 +1,361 @@
+//! Checks the configuration and every file it refers to before a run, so that problems
+//! are reported all at once instead of surfacing one by one while the fuzzer starts up.
+
+use std::{
+    fmt::Display,
+    net::TcpStream,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use openapiv3::OpenAPI;
+use serde_yaml::Value;
+
+use crate::{
+    authentication::Mode,
+    configuration::{Configuration, CoverageConfiguration},
+    coverage_clients::coverage_host,
+    header::read_header_file,
+    input::OpenApiInput,
+    interpolation::read_yaml_file,
+    openapi::{
+        bundle::{load_spec, parse_document, read_url, resolve_pointer, spec_location},
+        filter::OperationFilter,
+        find_operation,
+        server::select_server,
+    },
+};
+
+/// How long to wait for the coverage agent to accept a connection
+const COVERAGE_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A problem found in the configuration, optionally located in a file.
+struct Problem {
+    file: Option<PathBuf>,
+    line: Option<usize>,
+    message: String,
+}
+
+impl Problem {
+    fn new(message: impl Display) -> Self {
+        Self {
+            file: None,
+            line: None,
+            message: message.to_string(),
+        }
+    }
+
+    fn in_file(file: &Path, message: impl Display) -> Self {
+        Self {
+            file: Some(file.to_owned()),
+            ..Self::new(message)
+        }
+    }
+
+    fn at_line(mut self, line: Option<usize>) -> Self {
+        self.line = line;
+        self
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{line}: {}", file.display(), self.message),
+            (Some(file), None) => write!(f, "{}: {}", file.display(), self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Loads the configuration and all files it refers to, and reports every problem found.
+/// Returns an error if there is at least one.
+pub fn validate_config() -> Result<()> {
+    let problems = match Configuration::get() {
+        Ok(config) => check_configuration(config),
+        Err(err) => vec![Problem::new(format!("Invalid configuration: {err:#}"))],
+    };
+
+    if problems.is_empty() {
+        println!("The configuration is valid.");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("{problem}");
+    }
+    bail!("Found {} problem(s) in the configuration", problems.len())
+}
+
+fn check_configuration(config: &Configuration) -> Vec<Problem> {
+    let mut problems = vec![];
+
+    let spec_path = config
+        .openapi_spec
+        .as_deref()
+        .expect("A valid configuration has an OpenAPI specification");
+    let api = check_spec(spec_path, config, &mut problems);
+
+    if let Some(path) = &config.authentication {
+        if let Err(err) = read_yaml_file::<Mode>(path) {
+            problems.push(Problem::in_file(path, err.root_cause()));
+        }
+    }
+    if let Some(path) = &config.header {
+        if let Err(err) = read_header_file(path) {
+            problems.push(Problem::in_file(path, err.root_cause()));
+        }
+    }
+    if let (Some(dir), Some(api)) = (&config.initial_corpus, &api) {
+        check_corpus(dir, api, &mut problems);
+    }
+    check_coverage(config, &mut problems);
+
+    problems
+}
+
+/// Checks that the specification can be loaded, that all its references resolve and
+/// that the configured server can be selected. Returns the specification if it could
+/// be loaded.
+fn check_spec(path: &Path, config: &Configuration, problems: &mut Vec<Problem>) -> Option<OpenAPI> {
+    let (text, location) = match spec_location(path).and_then(|url| Ok((read_url(&url)?, url))) {
+        Ok(read) => read,
+        Err(err) => {
+            problems.push(Problem::in_file(path, format!("{err:#}")));
+            return None;
+        }
+    };
+    let document = match parse_document(&text, &location) {
+        Ok(document) => document,
+        Err(err) => {
+            problems.push(Problem::in_file(path, err));
+            return None;
+        }
+    };
+
+    let mut references = vec![];
+    collect_references(&document, &mut references);
+    references.sort_unstable();
+    references.dedup();
+    let mut broken_references = false;
+    for reference in references {
+        let message = match reference.strip_prefix('#') {
+            Some(pointer) if resolve_pointer(&document, pointer).is_some() => continue,
+            Some(_) => format!("Reference {reference} does not resolve"),
+            // References to other files are checked when the specification is loaded
+            None => continue,
+        };
+        broken_references = true;
+        problems.push(Problem::in_file(path, message).at_line(find_line(&text, reference)));
+    }
+    if broken_references {
+        // Loading a specification with broken references panics
+        return None;
+    }
+
+    let api = match load_spec(path) {
+        Ok(api) => api,
+        Err(err) => {
+            problems.push(Problem::in_file(path, format!("{err:#}")));
+            return None;
+        }
+    };
+    let server_line = api
+        .servers
+        .get(config.server_index)
+        .and_then(|server| find_line(&text, &server.url));
+    if let Err(err) = select_server(
+        &mut api.clone(),
+        path,
+        config.target_url.as_ref(),
+        config.server_index,
+    ) {
+        problems.push(Problem::in_file(path, format!("{err:#}")).at_line(server_line));
+    }
+    if let Err(err) = OperationFilter::apply(
+        &mut api.clone(),
+        &config.include_operations,
+        &config.exclude_operations,
+    ) {
+        problems.push(Problem::new(err));
+    }
+    Some(api)
+}
+
+/// Collects the values of all `$ref` fields in the document.
+fn collect_references<'a>(value: &'a Value, references: &mut Vec<&'a str>) {
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                match (key.as_str(), value.as_str()) {
+                    (Some("$ref"), Some(reference)) => references.push(reference),
+                    _ => collect_references(value, references),
+                }
+            }
+        }
+        Value::Sequence(sequence) => sequence
+            .iter()
+            .for_each(|value| collect_references(value, references)),
+        Value::Tagged(tagged) => collect_references(&tagged.value, references),
+        _ => (),
+    }
+}
+
+/// Returns the (1-based) number of the first line containing `needle`.
+fn find_line(text: &str, needle: &str) -> Option<usize> {
+    text.lines()
+        .position(|line| line.contains(needle))
+        .map(|index| index + 1)
+}
+
+/// Checks that every file in the corpus directory is a valid input, and that its
+/// requests refer to operations in the specification.
+fn check_corpus(dir: &Path, api: &OpenAPI, problems: &mut Vec<Problem>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            problems.push(Problem::in_file(dir, err));
+            return;
+        }
+    };
+    let mut files = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+        // Skip the lock and metadata files LibAFL keeps next to the inputs
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    files.sort();
+    for file in files {
+        let input = match std::fs::read_to_string(&file)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| Ok(serde_yaml::from_str::<OpenApiInput>(&text)?))
+        {
+            Ok(input) => input,
+            Err(err) => {
+                problems.push(Problem::in_file(&file, err));
+                continue;
+            }
+        };
+        for (index, request) in input.0.iter().enumerate() {
+            if find_operation(api, &request.path, request.method).is_none() {
+                problems.push(Problem::in_file(
+                    &file,
+                    format!(
+                        "Request {index} refers to {} {}, which is not an operation in the specification",
+                        request.method, request.path
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// Checks that the source and class directories exist, and that the coverage agent
+/// accepts connections.
+fn check_coverage(config: &Configuration, problems: &mut Vec<Problem>) {
+    let directories = match &config.coverage_configuration {
+        CoverageConfiguration::Endpoint => return,
+        CoverageConfiguration::Lcov { source_dir }
+        | CoverageConfiguration::Coverband { source_dir } => vec![("source", source_dir)],
+        CoverageConfiguration::Jacoco {
+            source_dir,
+            jacoco_class_dir,
+            ..
+        } => vec![("source", source_dir), ("class", jacoco_class_dir)],
+    };
+    for (kind, dir) in directories {
+        if let Some(dir) = dir.as_deref().filter(|dir| !dir.is_dir()) {
+            problems.push(Problem::in_file(
+                dir,
+                format!("The {kind} directory does not exist"),
+            ));
+        }
+    }
+
+    let host = coverage_host(config).expect("Code coverage is configured");
+    if let Err(err) = TcpStream::connect_timeout(&host, COVERAGE_CONNECT_TIMEOUT) {
+        problems.push(Problem::new(format!(
+            "Could not connect to the coverage agent at {host}: {err}"
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openapiv3::OpenAPI;
+    use serde_yaml::Value;
+
+    use super::{check_corpus, collect_references, resolve_pointer};
+
+    #[test]
+    fn test_references() {
+        let document: Value = serde_yaml::from_str(
+            "
+paths:
+  /pets:
+    get:
+      responses:
+        200:
+          $ref: '#/components/responses/Pets'
+        404:
+          $ref: '#/components/responses/Missing'
+components:
+  responses:
+    Pets:
+      description: A list of pets
+",
+        )
+        .unwrap();
+
+        let mut references = vec![];
+        collect_references(&document, &mut references);
+        assert_eq!(
+            references,
+            [
+                "#/components/responses/Pets",
+                "#/components/responses/Missing"
+            ]
+        );
+        assert!(resolve_pointer(&document, "/components/responses/Pets").is_some());
+        assert!(resolve_pointer(&document, "/paths/~1pets/get/responses/200").is_some());
+        assert!(resolve_pointer(&document, "/components/responses/Missing").is_none());
+    }
+
+    #[test]
+    fn test_check_corpus() {
+        let dir = tempfile::tempdir().unwrap();
+        let api: OpenAPI = serde_yaml::from_str(
+            "
+openapi: 3.0.0
+info:
+  title: Corpus
+  version: 1.0.0
+paths:
+  /pets:
+    get:
+      responses:
+        '200':
+          description: OK
+",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("0"), "- method: GET\n  path: /pets\n").unwrap();
+        // LibAFL keeps hidden files and directories next to the inputs
+        std::fs::write(dir.path().join(".0.lafl_lock"), "").unwrap();
+        std::fs::write(dir.path().join(".0.metadata"), "{}").unwrap();
+        std::fs::create_dir(dir.path().join(".cache")).unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        let mut problems = vec![];
+        check_corpus(dir.path(), &api, &mut problems);
+        assert!(problems.is_empty());
+
+        std::fs::write(dir.path().join("1"), "- method: GET\n  path: /cats\n").unwrap();
+        check_corpus(dir.path(), &api, &mut problems);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(
+            problems[0].file.as_deref(),
+            Some(dir.path().join("1").as_path())
+        );
+    }
+}